@@ -0,0 +1,12 @@
+int main(void) {
+    int squares[5];
+    for (int i = 0; i < 5; i = i + 1)
+        squares[i] = i * i;
+    squares[2] = squares[2] + 10;
+
+    int sum = 0;
+    for (int i = 0; i < 5; i = i + 1)
+        sum = sum + squares[i];
+
+    return sum + sizeof squares;
+}
@@ -0,0 +1,39 @@
+int main(void) {
+  int a = 3;
+  int b = 0;
+  if (a > 2)
+    if (a == 3)
+      b = a * 4;
+    else
+      b = 1;
+  else
+    b = 2;
+  int c = b > 10 ? (a || 0) + b : b - 1;
+  int sum = 0;
+  for (int i = 0; i < 10; i = i + 1) {
+    if (i % 2 == 0)
+      continue;
+    if (i > 7)
+      break;
+    sum = sum + i;
+  }
+  int n = 0;
+  while (1) {
+    n = n + 1;
+    {
+      int n = 100;
+      sum = sum + n / 100;
+    }
+    if (n == 4)
+      break;
+  }
+  do {
+    n = n - 1;
+    if (n == 2)
+      continue;
+    sum = sum + 1;
+  } while (n > 0);
+  if (c == 13 && !(b != 12) && sum == 23)
+    return c + (a ? 20 : 30);
+  return 0;
+}
@@ -0,0 +1,14 @@
+int main(void) {
+  int sum = 0;
+  int iterations = 0;
+  for (int i = 0; i < 10; i = i + 1) {
+    // Guards against a `continue` skipping the increment.
+    iterations = iterations + 1;
+    if (iterations > 100)
+      return 255;
+    if (i % 2)
+      continue;
+    sum = sum + i;
+  }
+  return sum;
+}
@@ -0,0 +1,20 @@
+int counter;
+int step = 5;
+unsigned int big = 4294967295u;
+
+int increment(void) {
+    counter = counter + step;
+    return counter;
+}
+
+int main(void) {
+    increment();
+    increment();
+
+    // A local variable shadows the global one.
+    int step = 100;
+    if (big > 1u)
+        counter = counter + 1;
+
+    return counter + step - 100;
+}
@@ -0,0 +1,8 @@
+int helper(void) {
+    int a = 2;
+    a = a * 3;
+}
+
+int main(void) {
+    helper();
+}
@@ -0,0 +1,13 @@
+int main(void) {
+    int count = 0;
+    int *p = &count;
+    for (int i = 0; i < 5; i = i + 1)
+        *p = *p + i;
+
+    int total = 10;
+    int *q = p;
+    q = &total;
+    *q = *q + *p;
+
+    return count + total + sizeof p;
+}
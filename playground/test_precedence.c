@@ -0,0 +1,13 @@
+int main(void) {
+  int a = 0;
+  int b = 3;
+  int c = 1;
+  int d = 2;
+  int e = 1;
+  int f = 2;
+  int g = 3;
+  int all = a || b && c == d < e + f * g;
+  int arithmetic = e + f * g - d % g / e - a;
+  int relational = c == d < e != g > f >= b <= a;
+  return all + 2 * arithmetic + 32 * relational;
+}
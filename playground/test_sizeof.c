@@ -0,0 +1,11 @@
+int main(void) {
+    int x = 1;
+    // The operand of `sizeof` is never evaluated.
+    int size = sizeof(int) + sizeof x + sizeof(x = 5) + sizeof(unsigned);
+
+    // Sizes are unsigned.
+    if (sizeof(int) - 5 > 0)
+        x = x + 50;
+
+    return size * 10 + x;
+}
@@ -0,0 +1,8 @@
+int main(void) {
+  int a = 7;
+  int b = 2;
+  int product = -a * b + a * -b;
+  int quotient = -a / -b;
+  int remainder = -a % b;
+  return 100 + product + quotient * 10 + remainder;
+}
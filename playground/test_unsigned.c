@@ -0,0 +1,16 @@
+int main(void) {
+    unsigned int max = 0u - 1u;
+    int result = 0;
+
+    if (max > 1u)
+        result = result + 1;
+    if (max / 2u == 2147483647u)
+        result = result + 2;
+    if (max % 10u == 5u)
+        result = result + 4;
+    // `-1` is converted to unsigned.
+    if (!(-1 < 1u))
+        result = result + 8;
+
+    return result;
+}
@@ -0,0 +1,19 @@
+int total;
+
+void add(void) {
+    total = total + 3;
+}
+
+void add_twice(void) {
+    add();
+    if (total > 100)
+        return;
+    add();
+    return;
+}
+
+int main(void) {
+    add_twice();
+    add_twice();
+    return total;
+}
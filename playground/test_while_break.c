@@ -0,0 +1,13 @@
+int main(void) {
+  int a = 0;
+  int b = 0;
+  while (a < 100) {
+    a = a + 1;
+    if (a % 2 == 0)
+      continue;
+    b = b + a;
+    if (b > 20)
+      break;
+  }
+  return b;
+}
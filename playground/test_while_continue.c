@@ -0,0 +1,21 @@
+int main(void) {
+  int i = 0;
+  int sum = 0;
+  while (i < 10) {
+    i = i + 1;
+    if (i % 3)
+      continue;
+    sum = sum + i;
+  }
+
+  // `continue` re-tests the condition, also in a `do` loop.
+  int j = 0;
+  do {
+    j = j + 1;
+    if (j < 5)
+      continue;
+    sum = sum + 100;
+  } while (j < 2);
+
+  return sum + j;
+}
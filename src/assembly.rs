@@ -1,23 +1,122 @@
-use std::{collections::HashMap, env, fmt::Debug, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::Debug,
+    fs, mem,
+    path::PathBuf,
+    str::FromStr,
+};
 
 use crate::{
     ast::{self, Identifier},
+    errors::{Error, Result},
     tac::{self, TAC},
 };
 
+/// Platform the assembly code is emitted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Linux,
+    MacOs,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    /// Parses `linux` or `macos`.
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "linux" => Ok(Target::Linux),
+            "macos" => Ok(Target::MacOs),
+            _ => Err(format!(
+                "Unknown target {value:?}, expected `linux` or `macos`"
+            )),
+        }
+    }
+}
+
+impl Default for Target {
+    /// Defaults to the platform the compiler is running on.
+    fn default() -> Self {
+        if env::consts::OS == "macos" {
+            Target::MacOs
+        } else {
+            Target::Linux
+        }
+    }
+}
+
 #[derive(Clone)]
-pub struct Program(pub Function);
+pub struct Program(pub Vec<Function>, pub Vec<StaticVariable>);
 
 impl Program {
     pub fn format(&self) -> String {
-        if env::consts::OS == "linux" {
-            format!(
-                r#"{}.section .note.GNU-stack,"",@progbits"#,
-                self.0.format()
-            )
-        } else {
-            self.0.format()
+        self.format_for(Target::default())
+    }
+
+    /// Formats the program for the given target.
+    ///
+    /// Panics if a pseudo register is left, see
+    /// [`Program::try_format_for`].
+    pub fn format_for(&self, target: Target) -> String {
+        self.try_format_for(target)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Formats the program for the given target, or reports the
+    /// first instruction still holding a pseudo register, which
+    /// no `AssemblyPass` replaced.
+    ///
+    /// Functions are emitted within the `.text` section, followed
+    /// by the static variables in their own sections. The GNU-stack
+    /// note is emitted once after all of them.
+    pub fn try_format_for(&self, target: Target) -> Result<String> {
+        for function in &self.0 {
+            let pseudo = function.instructions.iter().find(|instruction| {
+                instruction
+                    .operands()
+                    .iter()
+                    .any(|operand| matches!(operand, Operand::Pseudo(_)))
+            });
+
+            if let Some(instruction) = pseudo {
+                return Err(Error::UnreplacedPseudoRegister {
+                    function: function.name.clone(),
+                    instruction: instruction.dump(),
+                });
+            }
         }
+
+        let functions: String = self
+            .0
+            .iter()
+            .map(|function| function.format_for(target))
+            .collect();
+        let statics: String = self
+            .1
+            .iter()
+            .map(|variable| variable.format_for(target))
+            .collect();
+        let text = format!("\t.text\n{}{}", functions, statics);
+
+        Ok(match target {
+            Target::Linux => format!(r#"{}.section .note.GNU-stack,"",@progbits"#, text),
+            Target::MacOs => text,
+        })
+    }
+
+    /// Dumps the functions, then the static variables, in a
+    /// textual form that doesn't depend on the host and accepts
+    /// pseudo registers, see [`Instruction::dump`].
+    pub fn dump(&self) -> String {
+        let functions: String = self.0.iter().map(Function::dump).collect();
+        let statics: String = self
+            .1
+            .iter()
+            .map(|variable| format!("static {} = {}\n", variable.name.0, variable.init))
+            .collect();
+
+        functions + &statics
     }
 }
 
@@ -30,7 +129,92 @@ impl From<&mut Assembly> for Program {
 impl Debug for Program {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         //f.debug_tuple("AssemblyProgram").field(&self.0).finish()
-        write!(f, "Program(\n\t{:?}\n)", &self.0)
+        write!(f, "Program(")?;
+        for function in &self.0 {
+            write!(f, "\n\t{:?}", function)?;
+        }
+        for variable in &self.1 {
+            write!(f, "\n\t{:?}", variable)?;
+        }
+        write!(f, "\n)")
+    }
+}
+
+/// A 4 byte variable living for the whole program, zero
+/// initialized ones go into `.bss`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVariable {
+    pub name: ast::Identifier,
+    pub init: i64,
+}
+
+impl StaticVariable {
+    pub fn format(&self) -> String {
+        self.format_for(Target::default())
+    }
+
+    /// Formats the variable for the given target.
+    pub fn format_for(&self, target: Target) -> String {
+        let name = symbol(&self.name, target);
+
+        let mut result = match target {
+            Target::Linux => format!("\t.type {name}, @object\n"),
+            Target::MacOs => String::new(),
+        };
+        result.push_str(&format!("\t.globl {name}\n"));
+
+        if self.init == 0 {
+            result.push_str(&format!("\t.bss\n\t.balign 4\n{name}:\n\t.zero 4\n"));
+        } else {
+            result.push_str(&format!(
+                "\t.data\n\t.balign 4\n{name}:\n\t.long {}\n",
+                self.init
+            ));
+        }
+
+        if target == Target::Linux {
+            result.push_str(&format!("\t.size {name}, 4\n"));
+        }
+
+        result
+    }
+}
+
+/// How instructions are rendered, either emitted for a target or
+/// dumped for inspection.
+#[derive(Debug, Clone, Copy)]
+enum Syntax {
+    Emit(Target),
+    /// Dumps use the Linux symbols, so they don't depend on the
+    /// host.
+    Dump,
+}
+
+impl Syntax {
+    fn target(self) -> Target {
+        match self {
+            Syntax::Emit(target) => target,
+            Syntax::Dump => Target::Linux,
+        }
+    }
+}
+
+/// Returns the assembler symbol of a function or static
+/// variable, which on macOS must start with an underscore.
+fn symbol(name: &Identifier, target: Target) -> String {
+    match target {
+        Target::Linux => name.0.clone(),
+        Target::MacOs => format!("_{}", name.0),
+    }
+}
+
+/// Returns the assembler name of a jump target. Local labels,
+/// `.L` prefixed on Linux and `L` prefixed on macOS, never reach
+/// the symbol table nor clash with function symbols.
+fn local_label(label: &Identifier, target: Target) -> String {
+    match target {
+        Target::Linux => format!(".L_{}", label.0),
+        Target::MacOs => format!("L_{}", label.0),
     }
 }
 
@@ -42,23 +226,61 @@ pub struct Function {
 
 impl Function {
     pub fn format(&self) -> String {
-        // In maconame s function names must start with an underscore.
-        let name = if env::consts::OS == "macos" {
-            &format!("_{}", self.name.0)
-        } else {
-            &self.name.0
-        };
+        self.format_for(Target::default())
+    }
+
+    /// Returns the size of the stack frame, that is, the argument
+    /// of the `AllocateStack` instruction emitted by
+    /// `AssemblyPass::allocate_stack`, or 0 before it runs.
+    pub fn frame_size(&self) -> i64 {
+        self.instructions
+            .iter()
+            .find_map(|instruction| match instruction {
+                Instruction::AllocateStack(size) => Some(*size),
+                _ => None,
+            })
+            .unwrap_or(0)
+    }
+
+    /// Formats the function for the given target.
+    pub fn format_for(&self, target: Target) -> String {
+        let name = symbol(&self.name, target);
 
-        let mut result = format!(
+        // ELF symbols carry their type and size, Mach-O ones don't.
+        let mut result = match target {
+            Target::Linux => format!("\t.type {name}, @function\n"),
+            Target::MacOs => String::new(),
+        };
+        result.push_str(&format!(
             "\t.globl {}\n{}:\n\tpushq\t%rbp\n\tmovq\t%rsp, %rbp\n",
             name, name
-        );
+        ));
 
         for instruction in &self.instructions {
             if matches!(instruction, Instruction::Label(_)) {
-                result.push_str(&format!("{}\n", instruction.format()));
+                result.push_str(&format!("{}\n", instruction.format_for(target)));
             } else {
-                result.push_str(&format!("\t{}\n", instruction.format()));
+                result.push_str(&format!("\t{}\n", instruction.format_for(target)));
+            }
+        }
+
+        if target == Target::Linux {
+            result.push_str(&format!("\t.size {name}, .-{name}\n"));
+        }
+
+        result
+    }
+
+    /// Lists the instructions one per line after the name of the
+    /// function, without its prologue, see [`Instruction::dump`].
+    pub fn dump(&self) -> String {
+        let mut result = format!("{}:\n", self.name.0);
+
+        for instruction in &self.instructions {
+            if matches!(instruction, Instruction::Label(_)) {
+                result.push_str(&format!("{}\n", instruction.dump()));
+            } else {
+                result.push_str(&format!("\t{}\n", instruction.dump()));
             }
         }
 
@@ -76,12 +298,33 @@ impl Debug for Function {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Instruction {
-    Mov { src: Operand, dst: Operand },
+    Mov {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Moves 8 bytes, the size of a pointer.
+    Movq {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Moves a byte into 4 bytes, extending its sign.
+    Movsx {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Moves a byte into 4 bytes, filling the upper ones with
+    /// zeroes.
+    Movzx {
+        src: Operand,
+        dst: Operand,
+    },
     Unary(UnaryOperator, Operand),
     Binary(BinaryOperator, Operand, Operand),
     Idiv(Operand),
+    /// Unsigned division of `EDX:EAX`.
+    Div(Operand),
     Cdq,
     AllocateStack(i64),
     Ret,
@@ -89,39 +332,190 @@ pub enum Instruction {
     Jmp(Identifier),
     JumpCC(CondCode, Identifier),
     SetCC(CondCode, Operand),
+    /// Moves the source into the destination if the condition
+    /// holds.
+    CmovCC(CondCode, Operand, Operand),
     Label(Identifier),
+    DeallocateStack(i64),
+    /// A comment line, ignored by the assembler.
+    Comment(String),
+    /// Pushes the 8 byte operand onto the stack.
+    Push(Operand),
+    /// Pops 8 bytes off the stack into the operand.
+    Pop(Operand),
+    /// Loads the address of the memory operand `src` into the
+    /// register `dst`.
+    Lea {
+        src: Operand,
+        dst: Operand,
+    },
+    /// Calls a function, `external` ones aren't defined in the
+    /// program being compiled.
+    Call {
+        name: Identifier,
+        external: bool,
+    },
 }
 
 pub type Instructions = Vec<Instruction>;
 
 impl Instruction {
-    #[allow(unused_variables)]
+    /// Returns the operands referenced by the instruction.
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Instruction::Mov { src, dst }
+            | Instruction::Movq { src, dst }
+            | Instruction::Movsx { src, dst }
+            | Instruction::Movzx { src, dst }
+            | Instruction::Lea { src, dst } => vec![src, dst],
+            Instruction::Unary(_, operand) => vec![operand],
+            Instruction::Binary(_, src, dst) => vec![src, dst],
+            Instruction::Idiv(operand) | Instruction::Div(operand) => vec![operand],
+            Instruction::Cmp(op1, op2) => vec![op1, op2],
+            Instruction::SetCC(_, operand) => vec![operand],
+            Instruction::CmovCC(_, src, dst) => vec![src, dst],
+            Instruction::Push(operand) | Instruction::Pop(operand) => vec![operand],
+            Instruction::DeallocateStack(_)
+            | Instruction::Call { .. }
+            | Instruction::Comment(_) => {
+                vec![]
+            }
+            Instruction::Cdq
+            | Instruction::AllocateStack(_)
+            | Instruction::Ret
+            | Instruction::Jmp(_)
+            | Instruction::JumpCC(_, _)
+            | Instruction::Label(_) => vec![],
+        }
+    }
+
+    /// Returns mutable references to the operands of the
+    /// instruction, in the same order as [`Instruction::operands`].
+    /// Passes rewriting operands go through it, so a new
+    /// instruction only has to be listed here.
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Instruction::Mov { src, dst }
+            | Instruction::Movq { src, dst }
+            | Instruction::Movsx { src, dst }
+            | Instruction::Movzx { src, dst }
+            | Instruction::Lea { src, dst } => vec![src, dst],
+            Instruction::Unary(_, operand) => vec![operand],
+            Instruction::Binary(_, src, dst) => vec![src, dst],
+            Instruction::Idiv(operand) | Instruction::Div(operand) => vec![operand],
+            Instruction::Cmp(op1, op2) => vec![op1, op2],
+            Instruction::SetCC(_, operand) => vec![operand],
+            Instruction::CmovCC(_, src, dst) => vec![src, dst],
+            Instruction::Push(operand) | Instruction::Pop(operand) => vec![operand],
+            Instruction::DeallocateStack(_)
+            | Instruction::Call { .. }
+            | Instruction::Comment(_)
+            | Instruction::Cdq
+            | Instruction::AllocateStack(_)
+            | Instruction::Ret
+            | Instruction::Jmp(_)
+            | Instruction::JumpCC(_, _)
+            | Instruction::Label(_) => vec![],
+        }
+    }
+
     pub fn format(&self) -> String {
+        self.format_for(Target::default())
+    }
+
+    /// Formats the instruction for the given target, which
+    /// only matters for the symbols of called functions and
+    /// static variables.
+    pub fn format_for(&self, target: Target) -> String {
+        self.render(Syntax::Emit(target))
+    }
+
+    /// Renders the instruction like [`Instruction::format_for`]
+    /// on Linux, except that pseudo registers are printed as
+    /// `pseudo(name)` instead of panicking, so the instructions
+    /// can be inspected before `AssemblyPass::replace_pseudo_registers`.
+    pub fn dump(&self) -> String {
+        self.render(Syntax::Dump)
+    }
+
+    fn render(&self, syntax: Syntax) -> String {
+        let target = syntax.target();
+
         match self {
             Instruction::Mov { src, dst } => {
-                format!("movl\t{}, {}", src.format(), dst.format())
+                format!("movl\t{}, {}", src.format(syntax), dst.format(syntax))
             }
             Instruction::Unary(operator, operand) => {
-                format!("{}\t{}", operator.format(), operand.format())
+                format!("{}\t{}", operator.format(), operand.format(syntax))
             }
             Instruction::AllocateStack(i) => format!("subq\t${}, %rsp", i),
             Instruction::Ret => "movq\t%rbp, %rsp\n\tpopq\t%rbp\n\tret".to_string(),
             Instruction::Binary(binary_operator, operand, operand1) => format!(
                 "{}\t{}, {}",
                 binary_operator.format(),
-                operand.format(),
-                operand1.format()
+                if binary_operator.is_shift() {
+                    operand.format_inside_setcc(syntax)
+                } else {
+                    operand.format(syntax)
+                },
+                operand1.format(syntax)
             ),
-            Instruction::Idiv(operand) => format!("idivl\t{}", operand.format()),
+            Instruction::Idiv(operand) => format!("idivl\t{}", operand.format(syntax)),
+            Instruction::Div(operand) => format!("divl\t{}", operand.format(syntax)),
             Instruction::Cdq => "cdq".to_string(),
-            Instruction::Cmp(op1, op2) => format!("cmpl\t{}, {}", op1.format(), op2.format()),
-            Instruction::Jmp(label) => format!("jmp\t.L_{}", label.0),
-            Instruction::JumpCC(cond, label) => format!("j{}\t.L_{}", cond.format(), label.0),
+            Instruction::Cmp(op1, op2) => {
+                format!("cmpl\t{}, {}", op1.format(syntax), op2.format(syntax))
+            }
+            Instruction::Jmp(label) => format!("jmp\t{}", local_label(label, target)),
+            Instruction::JumpCC(cond, label) => {
+                format!("j{}\t{}", cond.format(), local_label(label, target))
+            }
             Instruction::SetCC(cond, operand) => {
                 // Add a parameter to this call to format within SetCC
-                format!("set{}\t{}", cond.format(), operand.format_inside_setcc())
+                format!(
+                    "set{}\t{}",
+                    cond.format(),
+                    operand.format_inside_setcc(syntax)
+                )
+            }
+            Instruction::CmovCC(cond, src, dst) => {
+                format!(
+                    "cmov{}\t{}, {}",
+                    cond.format(),
+                    src.format(syntax),
+                    dst.format(syntax)
+                )
             }
-            Instruction::Label(label) => format!(".L_{}:", label.0),
+            Instruction::Label(label) => format!("{}:", local_label(label, target)),
+            Instruction::DeallocateStack(i) => format!("addq\t${}, %rsp", i),
+            Instruction::Comment(comment) => format!("# {comment}"),
+            Instruction::Push(operand) => format!("pushq\t{}", operand.format_quad(syntax)),
+            Instruction::Pop(operand) => format!("popq\t{}", operand.format_quad(syntax)),
+            Instruction::Movq { src, dst } => format!(
+                "movq\t{}, {}",
+                src.format_quad(syntax),
+                dst.format_quad(syntax)
+            ),
+            // The source is a byte, named like the operand of `set`.
+            Instruction::Movsx { src, dst } => format!(
+                "movsbl\t{}, {}",
+                src.format_inside_setcc(syntax),
+                dst.format(syntax)
+            ),
+            Instruction::Movzx { src, dst } => format!(
+                "movzbl\t{}, {}",
+                src.format_inside_setcc(syntax),
+                dst.format(syntax)
+            ),
+            Instruction::Lea { src, dst } => {
+                format!("leaq\t{}, {}", src.format(syntax), dst.format_quad(syntax))
+            }
+            // External functions may be defined in a shared library,
+            // which the PLT resolves on Linux.
+            Instruction::Call { name, external } => match target {
+                Target::Linux if *external => format!("call\t{}@PLT", name.0),
+                _ => format!("call\t{}", symbol(name, target)),
+            },
         }
     }
 }
@@ -138,6 +532,7 @@ impl Debug for Instruction {
             Self::AllocateStack(arg0) => f.debug_tuple("\n\tAllocateStack").field(arg0).finish(),
             Self::Ret => write!(f, "\n\tRet\n\t\t"),
             Self::Idiv(operand) => f.debug_tuple("\n\tIdiv").field(operand).finish(),
+            Self::Div(operand) => f.debug_tuple("\n\tDiv").field(operand).finish(),
             Self::Cdq => write!(f, "\n\tCdq"),
             Self::Binary(operator, src, dst) => f
                 .debug_tuple("\n\tBinary")
@@ -149,12 +544,49 @@ impl Debug for Instruction {
             Self::Jmp(id) => f.debug_tuple("\n\tJmp").field(id).finish(),
             Self::JumpCC(cond, id) => f.debug_tuple("\n\tJumpCC").field(cond).field(id).finish(),
             Self::SetCC(cond, op) => f.debug_tuple("\n\tSetCC").field(cond).field(op).finish(),
+            Self::CmovCC(cond, src, dst) => f
+                .debug_tuple("\n\tCmovCC")
+                .field(cond)
+                .field(src)
+                .field(dst)
+                .finish(),
             Self::Label(id) => f.debug_tuple("\n\tLabel").field(id).finish(),
+            Self::DeallocateStack(size) => {
+                f.debug_tuple("\n\tDeallocateStack").field(size).finish()
+            }
+            Self::Push(operand) => f.debug_tuple("\n\tPush").field(operand).finish(),
+            Self::Pop(operand) => f.debug_tuple("\n\tPop").field(operand).finish(),
+            Self::Movq { src, dst } => f
+                .debug_struct("\n\tMovq")
+                .field("src", src)
+                .field("dst", dst)
+                .finish(),
+            Self::Movsx { src, dst } => f
+                .debug_struct("\n\tMovsx")
+                .field("src", src)
+                .field("dst", dst)
+                .finish(),
+            Self::Movzx { src, dst } => f
+                .debug_struct("\n\tMovzx")
+                .field("src", src)
+                .field("dst", dst)
+                .finish(),
+            Self::Lea { src, dst } => f
+                .debug_struct("\n\tLea")
+                .field("src", src)
+                .field("dst", dst)
+                .finish(),
+            Self::Call { name, external } => f
+                .debug_struct("\n\tCall")
+                .field("name", name)
+                .field("external", external)
+                .finish(),
+            Self::Comment(comment) => f.debug_tuple("\n\tComment").field(comment).finish(),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum CondCode {
     E,
     NE,
@@ -162,6 +594,11 @@ pub enum CondCode {
     GE,
     L,
     LE,
+    /// Unsigned comparisons, "above" and "below".
+    A,
+    AE,
+    B,
+    BE,
 }
 
 impl CondCode {
@@ -173,11 +610,15 @@ impl CondCode {
             CondCode::LE => "le".into(),
             CondCode::G => "g".into(),
             CondCode::GE => "ge".into(),
+            CondCode::A => "a".into(),
+            CondCode::AE => "ae".into(),
+            CondCode::B => "b".into(),
+            CondCode::BE => "be".into(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOperator {
     Neg,
     Not,
@@ -199,14 +640,28 @@ pub enum BinaryOperator {
     Mult,
     Divide,
     Remainder,
+    /// Shifts left, by a count either immediate or in `CL`.
+    Sal,
+    /// Shifts right, copying the sign bit.
+    Sar,
+    /// Shifts right, filling with zeroes.
+    Shr,
 }
 
 impl BinaryOperator {
+    /// Returns true for shifts, whose count is a byte.
+    pub fn is_shift(&self) -> bool {
+        matches!(self, Self::Sal | Self::Sar | Self::Shr)
+    }
+
     pub fn format(&self) -> String {
         match self {
             Self::Add => "addl".to_string(),
             Self::Sub => "subl".to_string(),
             Self::Mult => "imull".to_string(),
+            Self::Sal => "sall".to_string(),
+            Self::Sar => "sarl".to_string(),
+            Self::Shr => "shrl".to_string(),
             o => format!("The operation {o:?} should not be formated"),
         }
     }
@@ -217,26 +672,60 @@ pub enum Operand {
     Imm(i64),
     Register(Reg),
     Pseudo(ast::Identifier),
+    /// The slot this many bytes below `RBP`, a negative offset
+    /// lies above it, where arguments passed on the stack are.
     Stack(i64),
+    /// A static variable, addressed relative to `%rip`.
+    Data(ast::Identifier),
+    /// The memory at `base + index * scale`, both registers
+    /// holding 8 byte values.
+    Indexed {
+        base: Reg,
+        index: Reg,
+        scale: i64,
+    },
+    /// The memory a register holding an address points to.
+    Memory(Reg),
 }
 
 impl Operand {
+    /// Returns true if the operand lives in memory, instructions
+    /// can take at most one such operand.
+    pub fn is_memory(&self) -> bool {
+        matches!(
+            self,
+            Operand::Stack(_) | Operand::Data(_) | Operand::Indexed { .. } | Operand::Memory(_)
+        )
+    }
+
     /// Takes an extra parameter, `within_setcc`.
-    fn format(&self) -> String {
-        match self {
-            Operand::Imm(i) => format!("${}", i),
-            Operand::Register(r) => r.format(),
-            Operand::Pseudo(_) => panic!("Pseudo registers are never formated"),
-            Operand::Stack(s) => format!("-{}(%rbp)", s),
+    fn format(&self, syntax: Syntax) -> String {
+        match (self, syntax) {
+            (Operand::Imm(i), _) => format!("${}", i),
+            (Operand::Register(r), _) => r.format(),
+            (Operand::Pseudo(name), Syntax::Dump) => format!("pseudo({})", name.0),
+            (Operand::Pseudo(_), Syntax::Emit(_)) => panic!("Pseudo registers are never formated"),
+            (Operand::Stack(s), _) => format!("{}(%rbp)", -s),
+            (Operand::Data(name), _) => format!("{}(%rip)", symbol(name, syntax.target())),
+            (Operand::Indexed { base, index, scale }, _) => {
+                format!("({},{},{scale})", base.format_quad(), index.format_quad())
+            }
+            (Operand::Memory(reg), _) => format!("({})", reg.format_quad()),
         }
     }
 
-    fn format_inside_setcc(&self) -> String {
+    fn format_inside_setcc(&self, syntax: Syntax) -> String {
         match self {
-            Operand::Imm(i) => format!("${}", i),
             Operand::Register(r) => r.format_inside_setcc(),
-            Operand::Pseudo(_) => panic!("Pseudo registers are never formated"),
-            Operand::Stack(s) => format!("-{}(%rbp)", s),
+            operand => operand.format(syntax),
+        }
+    }
+
+    /// Formats the operand of an 8 byte instruction, such as `push`.
+    fn format_quad(&self, syntax: Syntax) -> String {
+        match self {
+            Operand::Register(r) => r.format_quad(),
+            operand => operand.format(syntax),
         }
     }
 }
@@ -244,25 +733,58 @@ impl Operand {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum Reg {
     AX,
+    CX,
     DX,
+    DI,
+    SI,
+    R8,
+    R9,
     R10,
     R11,
 }
 
+/// Registers holding the first arguments of a function call,
+/// in order.
+pub const ARGUMENT_REGISTERS: [Reg; 6] = [Reg::DI, Reg::SI, Reg::DX, Reg::CX, Reg::R8, Reg::R9];
+
 impl Reg {
     pub fn format(&self) -> String {
         match self {
             Reg::AX => "%eax".to_string(),
-            Reg::R10 => "%r10d".to_string(),
+            Reg::CX => "%ecx".to_string(),
             Reg::DX => "%edx".to_string(),
+            Reg::DI => "%edi".to_string(),
+            Reg::SI => "%esi".to_string(),
+            Reg::R8 => "%r8d".to_string(),
+            Reg::R9 => "%r9d".to_string(),
+            Reg::R10 => "%r10d".to_string(),
             Reg::R11 => "%r11d".to_string(),
         }
     }
 
+    pub fn format_quad(&self) -> String {
+        match self {
+            Reg::AX => "%rax".into(),
+            Reg::CX => "%rcx".into(),
+            Reg::DX => "%rdx".into(),
+            Reg::DI => "%rdi".into(),
+            Reg::SI => "%rsi".into(),
+            Reg::R8 => "%r8".into(),
+            Reg::R9 => "%r9".into(),
+            Reg::R10 => "%r10".into(),
+            Reg::R11 => "%r11".into(),
+        }
+    }
+
     pub fn format_inside_setcc(&self) -> String {
         match self {
             Reg::AX => "%al".into(),
+            Reg::CX => "%cl".into(),
             Reg::DX => "%dl".into(),
+            Reg::DI => "%dil".into(),
+            Reg::SI => "%sil".into(),
+            Reg::R8 => "%r8b".into(),
+            Reg::R9 => "%r9b".into(),
             Reg::R10 => "%r10b".into(),
             Reg::R11 => "%r11b".into(),
         }
@@ -305,20 +827,35 @@ impl Reg {
 pub struct Assembly {
     source: tac::Program,
     pub program: Option<Program>,
-    pub pseudo_registers: HashMap<Operand, i64>,
-    pub offset: i64,
+    /// Stack slots of the pseudo registers of each function, by
+    /// function name. Each function has its own frame, so the
+    /// same name may have a different slot in each one.
+    pub pseudo_registers: HashMap<Identifier, HashMap<Operand, i64>>,
+    /// Stack slots given so far to the pseudo registers of the
+    /// function being lowered.
+    frame: HashMap<Operand, i64>,
+    /// Deepest stack slot given so far to a pseudo register of
+    /// the function being lowered. Frame sizes are computed from
+    /// each function's own instructions by
+    /// `AssemblyPass::allocate_stack`, not from this.
+    offset: i64,
+    annotate: bool,
+    /// Names of the static variables, which are never given a
+    /// stack slot.
+    statics: HashSet<Identifier>,
+    /// Pointers of the function being lowered, which take 8
+    /// byte stack slots.
+    pointers: HashSet<Identifier>,
+    /// Names of the functions defined in the program, any other
+    /// function is external.
+    functions: HashSet<Identifier>,
 }
 
 impl From<String> for Assembly {
     fn from(value: String) -> Self {
         let source = TAC::from(value).to_tac_program();
 
-        Self {
-            source,
-            program: None,
-            pseudo_registers: HashMap::new(),
-            offset: 0,
-        }
+        Assembly::from(source)
     }
 }
 
@@ -326,11 +863,33 @@ impl From<&mut TAC> for Assembly {
     fn from(value: &mut TAC) -> Self {
         let source = value.to_tac_program();
 
+        Assembly::from(source)
+    }
+}
+
+impl From<tac::Program> for Assembly {
+    fn from(source: tac::Program) -> Self {
+        let statics = source
+            .1
+            .iter()
+            .map(|variable| variable.name.clone())
+            .collect();
+        let functions = source
+            .0
+            .iter()
+            .map(|function| function.identifier.clone())
+            .collect();
+
         Self {
             source,
             program: None,
             pseudo_registers: HashMap::new(),
+            frame: HashMap::new(),
             offset: 0,
+            annotate: false,
+            statics,
+            pointers: HashSet::new(),
+            functions,
         }
     }
 }
@@ -344,6 +903,13 @@ impl From<PathBuf> for Assembly {
 }
 
 impl Assembly {
+    /// Precedes the instructions lowered from each three address
+    /// code instruction with a comment holding it.
+    pub fn annotate(mut self, annotate: bool) -> Self {
+        self.annotate = annotate;
+        self
+    }
+
     /// Converts an Assembly object into an Assembly Program object.
     pub fn to_assembly_program(&mut self) -> Program {
         // Parsing the program
@@ -355,28 +921,211 @@ impl Assembly {
     }
 
     pub fn parse_program(&mut self) -> Program {
-        self.program = Some(Program(self.parse_function(self.source.0.clone())));
+        let functions = self
+            .source
+            .0
+            .clone()
+            .into_iter()
+            .map(|function| self.parse_function(function))
+            .collect();
+        let statics = self
+            .source
+            .1
+            .iter()
+            .map(|variable| StaticVariable {
+                name: variable.name.clone(),
+                init: variable.init,
+            })
+            .collect();
+        self.program = Some(Program(functions, statics));
 
         self.program.clone().expect("Returning program")
     }
 
     fn parse_function(&mut self, function: tac::Function) -> Function {
+        // Every function has its own stack frame.
+        self.frame = HashMap::new();
+        self.offset = 0;
+        self.pointers = function.pointers;
+
+        // The slot of an array starts at its first element, the
+        // lowest address.
+        for (array, size) in &function.arrays {
+            self.offset += size;
+            self.frame
+                .insert(Operand::Pseudo(array.clone()), self.offset);
+        }
+
+        let mut reads: HashMap<Identifier, usize> = HashMap::new();
+        for val in function.body.iter().flat_map(tac::Instruction::sources) {
+            if let tac::Val::Var(name) = val {
+                *reads.entry(name.clone()).or_default() += 1;
+            }
+        }
+        // Temporaries read once, such as the result of a `!`
+        // only feeding a conditional jump.
+        let read_once: HashSet<Identifier> = reads
+            .into_iter()
+            .filter(|(_, count)| *count == 1)
+            .map(|(name, _)| name)
+            .collect();
+
+        // Arguments are passed following `parse_function_call`,
+        // each one is copied into its parameter.
         let mut instructions = Vec::new();
-        for instruction in function.body {
+        for (index, param) in function.params.iter().enumerate() {
+            let src = match ARGUMENT_REGISTERS.get(index) {
+                Some(register) => Operand::Register(register.clone()),
+                // Past the saved `RBP` and the return address.
+                None => Operand::Stack(-16 - 8 * (index - ARGUMENT_REGISTERS.len()) as i64),
+            };
+            instructions.push(Instruction::Mov {
+                src,
+                dst: self.parse_operand(&tac::Val::Var(param.clone())),
+            });
+        }
+
+        let mut body = function.body.into_iter().peekable();
+        while let Some(instruction) = body.next() {
+            if self.annotate
+                && !matches!(
+                    instruction,
+                    tac::Instruction::Label(_) | tac::Instruction::Line(_)
+                )
+            {
+                instructions.push(Instruction::Comment(instruction.format()));
+            }
+
+            if let Some(condition) = Self::negated_jump(&instruction, body.peek(), &read_once) {
+                let jump = body.next().expect("Should peek the jump");
+                if self.annotate {
+                    instructions.push(Instruction::Comment(jump.format()));
+                }
+                instructions.append(&mut self.parse_negated_jump(condition, jump));
+                continue;
+            }
+
             // Moves each element in self.parse_instruction into the instructions
             // vec
             instructions.append(&mut self.parse_instruction(instruction));
         }
 
+        self.pseudo_registers
+            .insert(function.identifier.clone(), mem::take(&mut self.frame));
+
         Function {
+            instructions: Self::share_epilogue(&function.identifier, instructions),
             name: function.identifier,
-            instructions,
         }
     }
 
+    /// Tears down the stack frame once at the end of a function
+    /// returning from several places, each `ret` jumps there
+    /// instead, except for a last one right before it.
+    fn share_epilogue(function: &Identifier, instructions: Instructions) -> Instructions {
+        let returns = instructions
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::Ret))
+            .count();
+        if returns < 2 {
+            return instructions;
+        }
+
+        // C identifiers can't hold a `.`, so no other label
+        // matches this one.
+        let epilogue: Identifier = format!("return.{}", function.0).into();
+        let mut instructions: Instructions = instructions
+            .into_iter()
+            .map(|instruction| match instruction {
+                Instruction::Ret => Instruction::Jmp(epilogue.clone()),
+                instruction => instruction,
+            })
+            .collect();
+
+        if instructions.last() == Some(&Instruction::Jmp(epilogue.clone())) {
+            instructions.pop();
+        }
+        instructions.push(Instruction::Label(epilogue));
+        instructions.push(Instruction::Ret);
+
+        instructions
+    }
+
+    /// Returns the operand of a `!` whose result is only read by
+    /// the conditional jump following it, which can then test
+    /// the operand directly with the inverted condition.
+    fn negated_jump(
+        instruction: &tac::Instruction,
+        next: Option<&tac::Instruction>,
+        read_once: &HashSet<Identifier>,
+    ) -> Option<tac::Val> {
+        let tac::Instruction::Unary {
+            operator: ast::UnaryOperator::Not,
+            src,
+            dst: tac::Val::Var(dst),
+        } = instruction
+        else {
+            return None;
+        };
+
+        match next? {
+            tac::Instruction::JumpIfZero {
+                condition: tac::Val::Var(condition),
+                ..
+            }
+            | tac::Instruction::JumpIfNotZero {
+                condition: tac::Val::Var(condition),
+                ..
+            } if condition == dst && read_once.contains(dst) => Some(src.clone()),
+            _ => None,
+        }
+    }
+
+    /// Lowers `jump` on the negation of `condition`, `!x` is zero
+    /// exactly when `x` isn't.
+    fn parse_negated_jump(&mut self, condition: tac::Val, jump: tac::Instruction) -> Instructions {
+        let (cond, target) = match jump {
+            tac::Instruction::JumpIfZero { target, .. } => (CondCode::NE, target),
+            tac::Instruction::JumpIfNotZero { target, .. } => (CondCode::E, target),
+            _ => unreachable!("Only conditional jumps are fused"),
+        };
+
+        vec![
+            Instruction::Cmp(Operand::Imm(0), self.parse_operand(&condition)),
+            Instruction::JumpCC(cond, target),
+        ]
+    }
+
     fn parse_instruction(&mut self, instruction: tac::Instruction) -> Instructions {
         match instruction {
-            tac::Instruction::Return(val) => {
+            tac::Instruction::Load {
+                array,
+                index,
+                scale,
+                dst,
+            } => {
+                let mut instructions = self.parse_element_address(array, &index);
+                instructions.push(Instruction::Mov {
+                    src: Self::element(scale),
+                    dst: self.parse_operand(&dst),
+                });
+                instructions
+            }
+            tac::Instruction::Store {
+                src,
+                array,
+                index,
+                scale,
+            } => {
+                let mut instructions = self.parse_element_address(array, &index);
+                instructions.push(Instruction::Mov {
+                    src: self.parse_operand(&src),
+                    dst: Self::element(scale),
+                });
+                instructions
+            }
+            tac::Instruction::Return(None) => vec![Instruction::Ret],
+            tac::Instruction::Return(Some(val)) => {
                 vec![
                     Instruction::Mov {
                         src: self.parse_operand(&val),
@@ -412,33 +1161,38 @@ impl Assembly {
                 src_1,
                 src_2,
                 dst,
+                ty,
             } => match binary_operator {
-                ast::BinaryOperator::Divide => {
-                    vec![
-                        Instruction::Mov {
-                            src: self.parse_operand(&src_1),
-                            dst: Operand::Register(Reg::AX),
-                        },
-                        Instruction::Cdq,
-                        Instruction::Idiv(self.parse_operand(&src_2)),
-                        Instruction::Mov {
-                            src: Operand::Register(Reg::AX),
-                            dst: self.parse_operand(&dst),
-                        },
-                    ]
-                }
-                ast::BinaryOperator::Remainder => vec![
-                    Instruction::Mov {
+                // The quotient is left in `EAX` and the remainder in `EDX`.
+                ast::BinaryOperator::Divide | ast::BinaryOperator::Remainder => {
+                    let result = match binary_operator {
+                        ast::BinaryOperator::Divide => Reg::AX,
+                        _ => Reg::DX,
+                    };
+                    let mut instructions = vec![Instruction::Mov {
                         src: self.parse_operand(&src_1),
                         dst: Operand::Register(Reg::AX),
-                    },
-                    Instruction::Cdq,
-                    Instruction::Idiv(self.parse_operand(&src_2)),
-                    Instruction::Mov {
-                        src: Operand::Register(Reg::DX),
+                    }];
+
+                    // Unsigned division zero extends `EAX` into
+                    // `EDX` rather than sign extending it.
+                    if ty.is_signed() {
+                        instructions.push(Instruction::Cdq);
+                        instructions.push(Instruction::Idiv(self.parse_operand(&src_2)));
+                    } else {
+                        instructions.push(Instruction::Mov {
+                            src: Operand::Imm(0),
+                            dst: Operand::Register(Reg::DX),
+                        });
+                        instructions.push(Instruction::Div(self.parse_operand(&src_2)));
+                    }
+
+                    instructions.push(Instruction::Mov {
+                        src: Operand::Register(result),
                         dst: self.parse_operand(&dst),
-                    },
-                ],
+                    });
+                    instructions
+                }
                 ast::BinaryOperator::Equal
                 | ast::BinaryOperator::NotEqual
                 | ast::BinaryOperator::LessThan
@@ -452,7 +1206,7 @@ impl Assembly {
                             dst: self.parse_operand(&dst),
                         },
                         Instruction::SetCC(
-                            self.parse_relational_operator(&binary_operator),
+                            self.parse_relational_operator(&binary_operator, ty),
                             self.parse_operand(&dst),
                         ),
                     ]
@@ -464,7 +1218,7 @@ impl Assembly {
                             dst: self.parse_operand(&dst),
                         },
                         Instruction::Binary(
-                            self.parse_binary_operator(binary_operator),
+                            self.parse_binary_operator(binary_operator, ty),
                             self.parse_operand(&src_2),
                             self.parse_operand(&dst),
                         ),
@@ -484,22 +1238,176 @@ impl Assembly {
                     Instruction::JumpCC(CondCode::NE, target),
                 ]
             }
+            tac::Instruction::Copy { src, dst } if self.is_pointer(&dst) => {
+                vec![Instruction::Movq {
+                    src: self.parse_operand(&src),
+                    dst: self.parse_operand(&dst),
+                }]
+            }
             tac::Instruction::Copy { src, dst } => vec![Instruction::Mov {
                 src: self.parse_operand(&src),
                 dst: self.parse_operand(&dst),
             }],
+            tac::Instruction::GetAddress { src, dst } => vec![
+                Instruction::Lea {
+                    src: self.parse_operand(&tac::Val::Var(src)),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Movq {
+                    src: Operand::Register(Reg::AX),
+                    dst: self.parse_operand(&dst),
+                },
+            ],
+            tac::Instruction::LoadThrough { ptr, dst } => vec![
+                Instruction::Movq {
+                    src: self.parse_operand(&ptr),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Mov {
+                    src: Operand::Memory(Reg::AX),
+                    dst: self.parse_operand(&dst),
+                },
+            ],
+            tac::Instruction::StoreThrough { src, ptr } => vec![
+                Instruction::Movq {
+                    src: self.parse_operand(&ptr),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Mov {
+                    src: self.parse_operand(&src),
+                    dst: Operand::Memory(Reg::AX),
+                },
+            ],
             tac::Instruction::Label(id) => vec![Instruction::Label(id)],
+            tac::Instruction::Line(line) => vec![Instruction::Comment(format!("line {line}"))],
+            tac::Instruction::FunCall {
+                name,
+                arguments,
+                dst,
+            } => self.parse_function_call(name, arguments, dst),
+            tac::Instruction::Select {
+                condition,
+                src_1,
+                src_2,
+                dst,
+            } => vec![
+                Instruction::Mov {
+                    src: self.parse_operand(&src_2),
+                    dst: self.parse_operand(&dst),
+                },
+                Instruction::Cmp(Operand::Imm(0), self.parse_operand(&condition)),
+                Instruction::CmovCC(
+                    CondCode::NE,
+                    self.parse_operand(&src_1),
+                    self.parse_operand(&dst),
+                ),
+            ],
         }
     }
 
-    fn parse_relational_operator(&self, binary_operator: &ast::BinaryOperator) -> CondCode {
-        match binary_operator {
-            ast::BinaryOperator::Equal => CondCode::E,
-            ast::BinaryOperator::NotEqual => CondCode::NE,
-            ast::BinaryOperator::LessThan => CondCode::L,
-            ast::BinaryOperator::LessOrEqual => CondCode::LE,
-            ast::BinaryOperator::GreaterThan => CondCode::G,
-            ast::BinaryOperator::GreaterOrEqual => CondCode::GE,
+    /// Lowers a call following the System V calling convention:
+    /// the first six arguments are passed in registers and the
+    /// rest on the stack, in reverse order. The stack is padded
+    /// so it stays 16 byte aligned at the `call` instruction.
+    fn parse_function_call(
+        &mut self,
+        name: Identifier,
+        arguments: Vec<tac::Val>,
+        dst: tac::Val,
+    ) -> Instructions {
+        let mut instructions = Vec::new();
+
+        let stack_arguments = arguments.len().saturating_sub(ARGUMENT_REGISTERS.len());
+        let padding = if stack_arguments % 2 == 1 { 8 } else { 0 };
+        if padding != 0 {
+            instructions.push(Instruction::AllocateStack(padding));
+        }
+
+        for (argument, register) in arguments.iter().zip(ARGUMENT_REGISTERS) {
+            instructions.push(Instruction::Mov {
+                src: self.parse_operand(argument),
+                dst: Operand::Register(register),
+            });
+        }
+
+        for argument in arguments.iter().skip(ARGUMENT_REGISTERS.len()).rev() {
+            match self.parse_operand(argument) {
+                operand @ Operand::Register(_) => instructions.push(Instruction::Push(operand)),
+                // `pushq` sign extends a 32 bit immediate.
+                Operand::Imm(value) if i32::try_from(value).is_ok() => {
+                    instructions.push(Instruction::Push(Operand::Imm(value)))
+                }
+                // Pushing a 4 byte stack slot would read past it.
+                operand => {
+                    instructions.push(Instruction::Mov {
+                        src: operand,
+                        dst: Operand::Register(Reg::AX),
+                    });
+                    instructions.push(Instruction::Push(Operand::Register(Reg::AX)));
+                }
+            }
+        }
+
+        let external = !self.functions.contains(&name);
+        instructions.push(Instruction::Call { name, external });
+
+        let bytes_to_remove = 8 * stack_arguments as i64 + padding;
+        if bytes_to_remove != 0 {
+            instructions.push(Instruction::DeallocateStack(bytes_to_remove));
+        }
+
+        instructions.push(Instruction::Mov {
+            src: Operand::Register(Reg::AX),
+            dst: self.parse_operand(&dst),
+        });
+
+        instructions
+    }
+
+    /// Loads the address of `array` into `RAX` and `index` into
+    /// `RDX`, which [`Assembly::element`] then addresses.
+    ///
+    /// `movl` zeroes the upper half of `RDX`, which is only wrong
+    /// for negative indexes, already out of bounds.
+    fn parse_element_address(&mut self, array: Identifier, index: &tac::Val) -> Instructions {
+        vec![
+            Instruction::Lea {
+                src: Operand::Pseudo(array),
+                dst: Operand::Register(Reg::AX),
+            },
+            Instruction::Mov {
+                src: self.parse_operand(index),
+                dst: Operand::Register(Reg::DX),
+            },
+        ]
+    }
+
+    fn element(scale: i64) -> Operand {
+        Operand::Indexed {
+            base: Reg::AX,
+            index: Reg::DX,
+            scale,
+        }
+    }
+
+    /// Returns the condition code of a comparison between
+    /// operands of type `ty`.
+    fn parse_relational_operator(
+        &self,
+        binary_operator: &ast::BinaryOperator,
+        ty: ast::Type,
+    ) -> CondCode {
+        match (binary_operator, ty.is_signed()) {
+            (ast::BinaryOperator::Equal, _) => CondCode::E,
+            (ast::BinaryOperator::NotEqual, _) => CondCode::NE,
+            (ast::BinaryOperator::LessThan, true) => CondCode::L,
+            (ast::BinaryOperator::LessOrEqual, true) => CondCode::LE,
+            (ast::BinaryOperator::GreaterThan, true) => CondCode::G,
+            (ast::BinaryOperator::GreaterOrEqual, true) => CondCode::GE,
+            (ast::BinaryOperator::LessThan, false) => CondCode::B,
+            (ast::BinaryOperator::LessOrEqual, false) => CondCode::BE,
+            (ast::BinaryOperator::GreaterThan, false) => CondCode::A,
+            (ast::BinaryOperator::GreaterOrEqual, false) => CondCode::AE,
             _ => panic!("Not a relational operator, found {:?}", binary_operator),
         }
     }
@@ -511,26 +1419,45 @@ impl Assembly {
         }
     }
 
-    fn parse_binary_operator(&self, operator: ast::BinaryOperator) -> BinaryOperator {
+    /// Right shifts of unsigned values fill the upper bits with
+    /// zeroes rather than with the sign bit.
+    fn parse_binary_operator(
+        &self,
+        operator: ast::BinaryOperator,
+        ty: ast::Type,
+    ) -> BinaryOperator {
         match operator {
             ast::BinaryOperator::Add => BinaryOperator::Add,
             ast::BinaryOperator::Subtract => BinaryOperator::Sub,
             ast::BinaryOperator::Multiply => BinaryOperator::Mult,
             ast::BinaryOperator::Divide => BinaryOperator::Divide,
             ast::BinaryOperator::Remainder => BinaryOperator::Remainder,
+            ast::BinaryOperator::ShiftLeft => BinaryOperator::Sal,
+            ast::BinaryOperator::ShiftRight if ty.is_signed() => BinaryOperator::Sar,
+            ast::BinaryOperator::ShiftRight => BinaryOperator::Shr,
             _ => todo!(),
         }
     }
 
+    fn is_pointer(&self, val: &tac::Val) -> bool {
+        matches!(val, tac::Val::Var(name) if self.pointers.contains(name))
+    }
+
     fn parse_operand(&mut self, operand: &tac::Val) -> Operand {
         match operand {
             tac::Val::Constant(i) => Operand::Imm(*i),
+            tac::Val::Var(id) if self.statics.contains(id) => Operand::Data(id.clone()),
             tac::Val::Var(id) => {
                 // Update the offset whenever we encounter a new identifier.
                 if let std::collections::hash_map::Entry::Vacant(e) =
-                    self.pseudo_registers.entry(Operand::Pseudo(id.clone()))
+                    self.frame.entry(Operand::Pseudo(id.clone()))
                 {
-                    self.offset += 4;
+                    // Pointers are 8 bytes, aligned to 8.
+                    self.offset = if self.pointers.contains(id) {
+                        (self.offset + 8 + 7) / 8 * 8
+                    } else {
+                        self.offset + 4
+                    };
 
                     e.insert(self.offset);
                 }
@@ -6,6 +6,10 @@ use std::fmt::Debug;
 pub enum BlockItem {
     S(Statement),
     D(Declaration),
+    E(EnumDeclaration),
+    /// The source line the next block item starts on, only
+    /// recorded when the parser annotates lines.
+    Line(usize),
 }
 
 impl Debug for BlockItem {
@@ -13,20 +17,99 @@ impl Debug for BlockItem {
         match self {
             Self::S(arg0) => write!(f, "\n{arg0:?}"),
             Self::D(arg0) => write!(f, "\n{arg0:?}"),
+            Self::E(arg0) => write!(f, "\n{arg0:?}"),
+            Self::Line(line) => write!(f, "\nLine({line})"),
         }
     }
 }
 
 pub type BlockItems = Vec<BlockItem>;
 
+/// An enumeration, along with the value of each of its
+/// constants. The tag is optional, as in `enum { A, B };`.
 #[derive(Hash, Debug, PartialEq, Eq, Clone)]
-/// A declaration consists of a name
+pub struct EnumDeclaration {
+    pub tag: Option<Identifier>,
+    pub enumerators: Vec<(Identifier, i64)>,
+}
+
+impl EnumDeclaration {
+    /// Every enumerator is given its value explicitly.
+    pub fn to_c(&self) -> String {
+        let enumerators: Vec<String> = self
+            .enumerators
+            .iter()
+            .map(|(name, value)| format!("{} = {value}", name.0))
+            .collect();
+        let tag = self
+            .tag
+            .as_ref()
+            .map(|tag| format!(" {}", tag.0))
+            .unwrap_or_default();
+
+        format!("enum{tag} {{ {} }};", enumerators.join(", "))
+    }
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone)]
+/// A declaration consists of a name, its type
 /// and an optional initializer expression.
 pub struct Declaration {
     pub name: Identifier,
+    pub ty: Type,
     pub initializer: Option<Expression>,
 }
 
+/// Types of variables and expressions, both are 32 bits wide.
+#[derive(Hash, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Type {
+    #[default]
+    Int,
+    UnsignedInt,
+    /// Only the return type of functions returning no value.
+    Void,
+    /// A local array of `int` with the given number of elements,
+    /// which is only ever subscripted.
+    Array(usize),
+    /// A pointer to an `int`, 8 bytes wide.
+    Pointer,
+}
+
+impl Type {
+    /// Returns the type both operands of a binary operator are
+    /// converted to, which is unsigned as soon as one is.
+    pub fn common(self, other: Type) -> Type {
+        match (self, other) {
+            (Type::Int, Type::Int) => Type::Int,
+            _ => Type::UnsignedInt,
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        self == Type::Int
+    }
+
+    /// Size in bytes, as given by `sizeof`.
+    pub fn size(self) -> i64 {
+        match self {
+            Type::Int | Type::UnsignedInt => 4,
+            Type::Array(length) => Type::Int.size() * length as i64,
+            Type::Pointer => 8,
+            Type::Void => unreachable!("No value has the void type"),
+        }
+    }
+
+    pub fn to_c(self) -> String {
+        match self {
+            Type::Int => "int".into(),
+            Type::UnsignedInt => "unsigned int".into(),
+            Type::Void => "void".into(),
+            Type::Array(length) => format!("int[{length}]"),
+            Type::Pointer => "int *".into(),
+        }
+    }
+}
+
 #[derive(Hash, Debug, PartialEq, Eq, Clone)]
 pub enum BinaryOperator {
     Add,
@@ -34,6 +117,10 @@ pub enum BinaryOperator {
     Multiply,
     Divide,
     Remainder,
+    /// The result has the type of the left operand, the right one
+    /// being the number of bits to shift by.
+    ShiftLeft,
+    ShiftRight,
     And,
     Or,
     // Relational operators
@@ -45,6 +132,42 @@ pub enum BinaryOperator {
     GreaterOrEqual,
 }
 
+impl BinaryOperator {
+    /// Mirrors the precedence of the operator's token, see
+    /// `Token::precedence`.
+    pub fn precedence(&self) -> usize {
+        match self {
+            Self::Multiply | Self::Divide | Self::Remainder => 50,
+            Self::Add | Self::Subtract => 45,
+            Self::ShiftLeft | Self::ShiftRight => 40,
+            Self::LessThan | Self::LessOrEqual | Self::GreaterThan | Self::GreaterOrEqual => 35,
+            Self::Equal | Self::NotEqual => 30,
+            Self::And => 10,
+            Self::Or => 5,
+        }
+    }
+
+    fn to_c(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Subtract => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+            Self::Remainder => "%",
+            Self::ShiftLeft => "<<",
+            Self::ShiftRight => ">>",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::LessThan => "<",
+            Self::LessOrEqual => "<=",
+            Self::GreaterThan => ">",
+            Self::GreaterOrEqual => ">=",
+        }
+    }
+}
+
 #[derive(Hash, Debug, PartialEq, Eq, Clone)]
 pub enum UnaryOperator {
     Complement,
@@ -55,6 +178,8 @@ pub enum UnaryOperator {
 #[derive(Hash, Debug, PartialEq, Eq, Clone)]
 pub enum Expression {
     Constant(i64),
+    /// A constant with the `u` suffix.
+    UnsignedConstant(u32),
     /// This holds a variable name
     Var(Identifier),
     Unary(UnaryOperator, Box<Expression>),
@@ -67,11 +192,201 @@ pub enum Expression {
         exp1: Box<Expression>,
         exp2: Box<Expression>,
     },
+    /// Evaluates the left expression for its side effects,
+    /// then takes the value of the right one.
+    Comma(Box<Expression>, Box<Expression>),
+    FunctionCall {
+        name: Identifier,
+        arguments: Vec<Expression>,
+    },
+    /// The size of a type in bytes, as in `sizeof(int)`.
+    SizeOfType(Type),
+    /// The size of the type of an expression, which is never
+    /// evaluated.
+    SizeOf(Box<Expression>),
+    /// An element of an array, `array[index]`.
+    Subscript(Box<Expression>, Box<Expression>),
+    /// The address of an lvalue, `&x`.
+    AddrOf(Box<Expression>),
+    /// The value a pointer points to, `*p`.
+    Deref(Box<Expression>),
+}
+
+impl UnaryOperator {
+    fn to_c(&self) -> &'static str {
+        match self {
+            Self::Complement => "~",
+            Self::Negate => "-",
+            Self::Not => "!",
+        }
+    }
+}
+
+/// Precedence of factors, which never need parenthesis.
+const FACTOR_PRECEDENCE: usize = usize::MAX;
+
+impl Expression {
+    /// Returns true if the expression is a constant or a
+    /// variable, which evaluate without side effects.
+    pub fn is_simple(&self) -> bool {
+        matches!(
+            self,
+            Expression::Constant(_) | Expression::UnsignedConstant(_) | Expression::Var(_)
+        )
+    }
+
+    /// Returns the value of a constant expression, that is, an
+    /// integer literal possibly negated. Static variables can only
+    /// be initialized to constants.
+    pub fn constant_value(&self) -> Option<i64> {
+        match self {
+            Expression::Constant(value) => Some(*value),
+            Expression::UnsignedConstant(value) => Some((*value).into()),
+            Expression::SizeOfType(ty) => Some(ty.size()),
+            Expression::Unary(UnaryOperator::Negate, expression) => {
+                expression.constant_value().map(|value| -value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true if the expression can be assigned to, that
+    /// is, a variable, an array element or a dereferenced pointer.
+    pub fn is_lvalue(&self) -> bool {
+        matches!(
+            self,
+            Expression::Var(_) | Expression::Subscript(..) | Expression::Deref(_)
+        )
+    }
+
+    /// Reconstructs the C source of the expression, adding
+    /// parenthesis only where precedence requires them.
+    ///
+    /// ```
+    /// # use nous::ast::{BinaryOperator, Expression};
+    /// let product = Expression::Binary(
+    ///     BinaryOperator::Multiply,
+    ///     Box::new(Expression::Constant(2)),
+    ///     Box::new(Expression::Constant(3)),
+    /// );
+    /// let sum = Expression::Binary(
+    ///     BinaryOperator::Add,
+    ///     Box::new(product),
+    ///     Box::new(Expression::Constant(4)),
+    /// );
+    ///
+    /// assert_eq!(sum.to_c(), "2 * 3 + 4");
+    /// ```
+    pub fn to_c(&self) -> String {
+        match self {
+            Self::Constant(value) => value.to_string(),
+            Self::UnsignedConstant(value) => format!("{value}u"),
+            Self::Var(name) => name.0.clone(),
+            // Nested unary operators are parenthesized so `- -a`
+            // isn't read as a decrement.
+            Self::Unary(operator, expression) => format!(
+                "{}{}",
+                operator.to_c(),
+                expression.parenthesized_below(FACTOR_PRECEDENCE)
+            ),
+            // Binary operators are left associative.
+            Self::Binary(operator, left, right) => format!(
+                "{} {} {}",
+                left.parenthesized_below(operator.precedence()),
+                operator.to_c(),
+                right.parenthesized_below(operator.precedence() + 1)
+            ),
+            Self::Assignment(left, right) => format!(
+                "{} = {}",
+                left.to_c(),
+                right.parenthesized_below(self.precedence())
+            ),
+            Self::Conditional {
+                condition,
+                exp1,
+                exp2,
+            } => format!(
+                "{} ? {} : {}",
+                condition.parenthesized_below(self.precedence() + 1),
+                exp1.to_c(),
+                exp2.parenthesized_below(self.precedence())
+            ),
+            Self::Comma(left, right) => format!(
+                "{}, {}",
+                left.to_c(),
+                right.parenthesized_below(self.precedence() + 1)
+            ),
+            // Commas would separate arguments.
+            Self::FunctionCall { name, arguments } => format!(
+                "{}({})",
+                name.0,
+                arguments
+                    .iter()
+                    .map(|argument| argument.parenthesized_below(1))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::SizeOfType(ty) => format!("sizeof({})", ty.to_c()),
+            Self::SizeOf(expression) => format!(
+                "sizeof {}",
+                expression.parenthesized_below(FACTOR_PRECEDENCE)
+            ),
+            Self::Subscript(array, index) => format!(
+                "{}[{}]",
+                array.parenthesized_below(FACTOR_PRECEDENCE),
+                index.to_c()
+            ),
+            Self::AddrOf(expression) => {
+                format!("&{}", expression.parenthesized_below(FACTOR_PRECEDENCE))
+            }
+            Self::Deref(expression) => {
+                format!("*{}", expression.parenthesized_below(FACTOR_PRECEDENCE))
+            }
+        }
+    }
+
+    fn precedence(&self) -> usize {
+        match self {
+            Self::Constant(_)
+            | Self::UnsignedConstant(_)
+            | Self::Var(_)
+            | Self::FunctionCall { .. }
+            | Self::Subscript(..) => FACTOR_PRECEDENCE,
+            Self::Unary(..)
+            | Self::SizeOfType(_)
+            | Self::SizeOf(_)
+            | Self::AddrOf(_)
+            | Self::Deref(_) => FACTOR_PRECEDENCE - 1,
+            Self::Binary(operator, ..) => operator.precedence(),
+            Self::Conditional { .. } => 3,
+            Self::Assignment(..) => 1,
+            Self::Comma(..) => 0,
+        }
+    }
+
+    /// Formats the expression, within parenthesis if it binds
+    /// looser than `precedence`.
+    fn parenthesized_below(&self, precedence: usize) -> String {
+        if self.precedence() < precedence {
+            format!("({})", self.to_c())
+        } else {
+            self.to_c()
+        }
+    }
+}
+
+/// The initial clause of a `for` loop.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ForInit {
+    /// Variables declared by a single declaration, in order.
+    InitDecl(Vec<Declaration>),
+    InitExp(Option<Expression>),
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Statement {
-    Return(Expression),
+    /// Returns no value from a `void` function.
+    Return(Option<Expression>),
     /// Takes an expression node.
     Expression(Expression),
     If {
@@ -79,12 +394,169 @@ pub enum Statement {
         then: Box<Statement>,
         else_statement: Option<Box<Statement>>,
     },
+    /// A block of items delimited by braces, introducing
+    /// a new scope.
+    Compound(BlockItems),
+    /// The label of `break` and `continue` statements is the one of
+    /// their enclosing loop, it is assigned by `LoopLabeling`.
+    Break(Option<Identifier>),
+    Continue(Option<Identifier>),
+    While {
+        condition: Expression,
+        body: Box<Statement>,
+        label: Option<Identifier>,
+    },
+    DoWhile {
+        body: Box<Statement>,
+        condition: Expression,
+        label: Option<Identifier>,
+    },
+    For {
+        init: ForInit,
+        condition: Option<Expression>,
+        post: Option<Expression>,
+        body: Box<Statement>,
+        label: Option<Identifier>,
+    },
     /// Represents null statements, which are expression
     /// statements without the expression.
     Null,
 }
 
-#[derive(PartialEq, Clone, Hash, Eq)]
+impl Statement {
+    /// Reconstructs the C source of the statement.
+    pub fn to_c(&self) -> String {
+        match self {
+            Self::Return(Some(expression)) => format!("return {};", expression.to_c()),
+            Self::Return(None) => "return;".into(),
+            Self::Expression(expression) => format!("{};", expression.to_c()),
+            Self::If {
+                condition,
+                then,
+                else_statement: Some(else_statement),
+            } => {
+                // An `else` following an `if` without one would
+                // belong to the inner `if` when reparsed.
+                let then = match then.as_ref() {
+                    Self::If {
+                        else_statement: None,
+                        ..
+                    } => block_to_c(&vec![BlockItem::S(*then.clone())]),
+                    then => then.to_c(),
+                };
+
+                format!(
+                    "if ({}) {then} else {}",
+                    condition.to_c(),
+                    else_statement.to_c()
+                )
+            }
+            Self::If {
+                condition, then, ..
+            } => format!("if ({}) {}", condition.to_c(), then.to_c()),
+            Self::Compound(block_items) => block_to_c(block_items),
+            Self::Break(_) => "break;".into(),
+            Self::Continue(_) => "continue;".into(),
+            Self::While {
+                condition, body, ..
+            } => format!("while ({}) {}", condition.to_c(), body.to_c()),
+            Self::DoWhile {
+                body, condition, ..
+            } => format!("do {} while ({});", body.to_c(), condition.to_c()),
+            Self::For {
+                init,
+                condition,
+                post,
+                body,
+                ..
+            } => {
+                let init = match init {
+                    ForInit::InitDecl(declarations) => Declaration::list_to_c(declarations),
+                    ForInit::InitExp(expression) => format!("{};", optional_to_c(expression)),
+                };
+
+                format!(
+                    "for ({init} {}; {}) {}",
+                    optional_to_c(condition),
+                    optional_to_c(post),
+                    body.to_c()
+                )
+            }
+            Self::Null => ";".into(),
+        }
+    }
+}
+
+impl Declaration {
+    pub fn to_c(&self) -> String {
+        Self::list_to_c(std::slice::from_ref(self))
+    }
+
+    /// Formats declarations sharing their type specifiers as a
+    /// single declaration, e.g. `int a = 1, *p;`.
+    pub fn list_to_c(declarations: &[Declaration]) -> String {
+        let specifiers = match declarations.first().map(|declaration| declaration.ty) {
+            Some(Type::Array(_) | Type::Pointer) | None => Type::Int,
+            Some(ty) => ty,
+        };
+        let declarators: Vec<String> = declarations.iter().map(Self::declarator_to_c).collect();
+
+        format!("{} {};", specifiers.to_c(), declarators.join(", "))
+    }
+
+    fn declarator_to_c(&self) -> String {
+        let declarator = match self.ty {
+            // Arrays are never initialized.
+            Type::Array(length) => return format!("{}[{length}]", self.name.0),
+            Type::Pointer => format!("*{}", self.name.0),
+            _ => self.name.0.clone(),
+        };
+
+        match &self.initializer {
+            // Commas would separate declarators.
+            Some(initializer) => {
+                format!("{declarator} = {}", initializer.parenthesized_below(1))
+            }
+            None => declarator,
+        }
+    }
+}
+
+impl BlockItem {
+    pub fn to_c(&self) -> String {
+        match self {
+            Self::S(statement) => statement.to_c(),
+            Self::D(declaration) => declaration.to_c(),
+            Self::E(enumeration) => enumeration.to_c(),
+            Self::Line(line) => format!("// line {line}"),
+        }
+    }
+}
+
+/// Formats a block within braces, one item per line.
+fn block_to_c(block_items: &BlockItems) -> String {
+    let mut block = String::from("{\n");
+
+    for block_item in block_items {
+        for line in block_item.to_c().lines() {
+            block.push_str("    ");
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+
+    block.push('}');
+    block
+}
+
+fn optional_to_c(expression: &Option<Expression>) -> String {
+    expression
+        .as_ref()
+        .map(Expression::to_c)
+        .unwrap_or_default()
+}
+
+#[derive(PartialEq, Clone, Hash, Eq, Default)]
 pub struct Identifier(pub String);
 
 impl From<&str> for Identifier {
@@ -120,25 +592,362 @@ impl Debug for Identifier {
 #[derive(PartialEq, Clone)]
 pub struct Function {
     pub name: Identifier,
+    pub return_type: Type,
+    /// Parameters, declarations without an initializer that
+    /// are in the scope of the body.
+    pub params: Vec<Declaration>,
     pub body: BlockItems,
 }
 
+impl Function {
+    pub fn to_c(&self) -> String {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|param| format!("{} {}", param.ty.to_c(), param.name.0))
+            .collect();
+
+        format!(
+            "{} {}({}) {}",
+            self.return_type.to_c(),
+            self.name.0,
+            params_to_c(params),
+            block_to_c(&self.body)
+        )
+    }
+}
+
 impl Debug for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Function(\n\t\tname: {:?} \n\t\tbody: \n\t\t{:#?}\n\t)",
-            &self.name.0, &self.body
+            "Function(\n\t\tname: {:?} \n\t\tparams: {:?} \n\t\tbody: \n\t\t{:#?}\n\t)",
+            &self.name.0, &self.params, &self.body
         )
     }
 }
 
+/// A function declared without a body, `int f(int);`, which
+/// is defined later on or in another file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionDeclaration {
+    pub name: Identifier,
+    pub return_type: Type,
+    /// Types of the parameters, their names don't matter.
+    pub params: Vec<Type>,
+}
+
+impl FunctionDeclaration {
+    pub fn to_c(&self) -> String {
+        format!(
+            "{} {}({});",
+            self.return_type.to_c(),
+            self.name.0,
+            params_to_c(self.params.iter().copied().map(Type::to_c).collect())
+        )
+    }
+}
+
+/// Joins the parameters of a function, `void` if it has none.
+fn params_to_c(params: Vec<String>) -> String {
+    if params.is_empty() {
+        "void".into()
+    } else {
+        params.join(", ")
+    }
+}
+
+/// The functions of a program along with its file scope
+/// variables, enumerations and function declarations, which
+/// are visible from every function.
 #[derive(PartialEq, Clone)]
-pub struct Program(pub Function);
+pub struct Program(
+    pub Vec<Function>,
+    pub Vec<Declaration>,
+    pub Vec<EnumDeclaration>,
+    pub Vec<FunctionDeclaration>,
+);
 
 impl Debug for Program {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Program(\n\t{:?}\n)", &self.0)
+        write!(f, "Program(")?;
+        for enumeration in &self.2 {
+            write!(f, "\n\t{:?}", enumeration)?;
+        }
+        for global in &self.1 {
+            write!(f, "\n\t{:?}", global)?;
+        }
+        for declaration in &self.3 {
+            write!(f, "\n\t{:?}", declaration)?;
+        }
+        for function in &self.0 {
+            write!(f, "\n\t{:?}", function)?;
+        }
+        write!(f, "\n)")
+    }
+}
+
+impl Program {
+    /// Reconstructs the C source of the program, which parses
+    /// back into the same program. File scope enumerations come
+    /// first, then file scope variables and function declarations.
+    pub fn to_c(&self) -> String {
+        let globals: String = self
+            .2
+            .iter()
+            .map(|enumeration| format!("{}\n", enumeration.to_c()))
+            .chain(self.1.iter().map(|global| format!("{}\n", global.to_c())))
+            .chain(
+                self.3
+                    .iter()
+                    .map(|declaration| format!("{}\n", declaration.to_c())),
+            )
+            .collect();
+        let functions = self
+            .0
+            .iter()
+            .map(|function| format!("{}\n", function.to_c()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if globals.is_empty() {
+            functions
+        } else {
+            format!("{globals}\n{functions}")
+        }
+    }
+
+    /// Formats the program as an indented tree, one node per
+    /// line with its children indented below it.
+    ///
+    /// ```
+    /// # use nous::parser::Parser;
+    /// let program = Parser::build("int main(void) { return 2; }")
+    ///     .to_ast_program()
+    ///     .expect("Should parse the program");
+    ///
+    /// assert_eq!(program.pretty(), "Program\n  Function main\n    Return\n      Constant 2\n");
+    /// ```
+    pub fn pretty(&self) -> String {
+        let mut tree = Tree::default();
+        tree.node("Program", |tree| {
+            for enumeration in &self.2 {
+                tree.enumeration(enumeration);
+            }
+            for global in &self.1 {
+                tree.declaration(global);
+            }
+            for declaration in &self.3 {
+                tree.leaf(format!("Prototype {}", declaration.to_c()));
+            }
+            for function in &self.0 {
+                let label = match function.return_type {
+                    Type::Int => format!("Function {}", function.name.0),
+                    ty => format!("Function {} ({})", function.name.0, ty.to_c()),
+                };
+
+                tree.node(label, |tree| {
+                    for param in &function.params {
+                        tree.declaration(param);
+                    }
+                    tree.block(&function.body)
+                });
+            }
+        });
+
+        tree.output
+    }
+}
+
+/// Builds the output of [`Program::pretty`].
+#[derive(Default)]
+struct Tree {
+    output: String,
+    depth: usize,
+}
+
+impl Tree {
+    /// Writes a line for a node, then its children one
+    /// level deeper.
+    fn node(&mut self, text: impl AsRef<str>, children: impl FnOnce(&mut Self)) {
+        self.output.push_str(&"  ".repeat(self.depth));
+        self.output.push_str(text.as_ref());
+        self.output.push('\n');
+
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    fn leaf(&mut self, text: impl AsRef<str>) {
+        self.node(text, |_| {});
+    }
+
+    fn block(&mut self, block_items: &BlockItems) {
+        for block_item in block_items {
+            match block_item {
+                BlockItem::S(statement) => self.statement(statement),
+                BlockItem::D(declaration) => self.declaration(declaration),
+                BlockItem::E(enumeration) => self.enumeration(enumeration),
+                BlockItem::Line(line) => self.leaf(format!("Line {line}")),
+            }
+        }
+    }
+
+    fn enumeration(&mut self, enumeration: &EnumDeclaration) {
+        let label = match &enumeration.tag {
+            Some(tag) => format!("Enum {}", tag.0),
+            None => "Enum".to_string(),
+        };
+
+        self.node(label, |tree| {
+            for (name, value) in &enumeration.enumerators {
+                tree.leaf(format!("{} = {value}", name.0));
+            }
+        });
+    }
+
+    fn declaration(&mut self, declaration: &Declaration) {
+        let label = match declaration.ty {
+            Type::Int => format!("Declaration {}", declaration.name.0),
+            ty => format!("Declaration {} ({})", declaration.name.0, ty.to_c()),
+        };
+
+        self.node(label, |tree| {
+            if let Some(initializer) = &declaration.initializer {
+                tree.expression(initializer);
+            }
+        });
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Return(expression) => {
+                self.node("Return", |tree| tree.optional_expression(expression))
+            }
+            Statement::Expression(expression) => {
+                self.node("Expression", |tree| tree.expression(expression))
+            }
+            Statement::If {
+                condition,
+                then,
+                else_statement,
+            } => self.node("If", |tree| {
+                tree.expression(condition);
+                tree.node("Then", |tree| tree.statement(then));
+                if let Some(else_statement) = else_statement {
+                    tree.node("Else", |tree| tree.statement(else_statement));
+                }
+            }),
+            Statement::Compound(block_items) => {
+                self.node("Compound", |tree| tree.block(block_items))
+            }
+            Statement::Break(label) => self.leaf(Self::labeled("Break", label)),
+            Statement::Continue(label) => self.leaf(Self::labeled("Continue", label)),
+            Statement::While {
+                condition,
+                body,
+                label,
+            } => self.node(Self::labeled("While", label), |tree| {
+                tree.expression(condition);
+                tree.statement(body);
+            }),
+            Statement::DoWhile {
+                body,
+                condition,
+                label,
+            } => self.node(Self::labeled("DoWhile", label), |tree| {
+                tree.statement(body);
+                tree.expression(condition);
+            }),
+            Statement::For {
+                init,
+                condition,
+                post,
+                body,
+                label,
+            } => self.node(Self::labeled("For", label), |tree| {
+                tree.node("Init", |tree| match init {
+                    ForInit::InitDecl(declarations) => {
+                        for declaration in declarations {
+                            tree.declaration(declaration);
+                        }
+                    }
+                    ForInit::InitExp(expression) => tree.optional_expression(expression),
+                });
+                tree.node("Condition", |tree| tree.optional_expression(condition));
+                tree.node("Post", |tree| tree.optional_expression(post));
+                tree.statement(body);
+            }),
+            Statement::Null => self.leaf("Null"),
+        }
+    }
+
+    fn labeled(text: &str, label: &Option<Identifier>) -> String {
+        match label {
+            Some(label) => format!("{text} {}", label.0),
+            None => text.into(),
+        }
+    }
+
+    fn optional_expression(&mut self, expression: &Option<Expression>) {
+        if let Some(expression) = expression {
+            self.expression(expression);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Constant(value) => self.leaf(format!("Constant {value}")),
+            Expression::UnsignedConstant(value) => self.leaf(format!("Constant {value}u")),
+            Expression::Var(name) => self.leaf(format!("Var {}", name.0)),
+            Expression::Unary(operator, expression) => self
+                .node(format!("Unary {operator:?}"), |tree| {
+                    tree.expression(expression)
+                }),
+            Expression::Binary(operator, left, right) => {
+                self.node(format!("Binary {operator:?}"), |tree| {
+                    tree.expression(left);
+                    tree.expression(right);
+                })
+            }
+            Expression::Assignment(left, right) => self.node("Assignment", |tree| {
+                tree.expression(left);
+                tree.expression(right);
+            }),
+            Expression::Conditional {
+                condition,
+                exp1,
+                exp2,
+            } => self.node("Conditional", |tree| {
+                tree.expression(condition);
+                tree.expression(exp1);
+                tree.expression(exp2);
+            }),
+            Expression::Comma(left, right) => self.node("Comma", |tree| {
+                tree.expression(left);
+                tree.expression(right);
+            }),
+            Expression::FunctionCall { name, arguments } => {
+                self.node(format!("Call {}", name.0), |tree| {
+                    for argument in arguments {
+                        tree.expression(argument);
+                    }
+                })
+            }
+            Expression::SizeOfType(ty) => self.leaf(format!("SizeOf {}", ty.to_c())),
+            Expression::SizeOf(expression) => {
+                self.node("SizeOf", |tree| tree.expression(expression))
+            }
+            Expression::Subscript(array, index) => self.node("Subscript", |tree| {
+                tree.expression(array);
+                tree.expression(index);
+            }),
+            Expression::AddrOf(expression) => {
+                self.node("AddrOf", |tree| tree.expression(expression))
+            }
+            Expression::Deref(expression) => self.node("Deref", |tree| tree.expression(expression)),
+        }
     }
 }
 
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+    ast::Identifier,
+    tac::{Instruction, Instructions},
+};
+
+/// Where control goes after a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Successor {
+    /// The block at this index of [`Cfg::blocks`].
+    Block(usize),
+    /// Control leaves the function.
+    Exit,
+}
+
+/// A stretch of instructions only entered through its first one
+/// and only left after its last one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub instructions: Instructions,
+    pub successors: Vec<Successor>,
+    /// Indexes of the blocks control may come from. The first
+    /// block is also entered when the function is called.
+    pub predecessors: Vec<usize>,
+}
+
+/// The control flow graph of a function, its blocks laid out in
+/// the order of the instructions they came from. The first block
+/// is the entry of the function.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    /// Returns every edge of the graph, as a block along with one
+    /// of its successors.
+    pub fn edges(&self) -> Vec<(usize, Successor)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(index, block)| {
+                block
+                    .successors
+                    .iter()
+                    .map(move |successor| (index, *successor))
+            })
+            .collect()
+    }
+}
+
+/// Partitions the instructions of a function into basic blocks.
+/// A block starts at each `Label` and ends after each jump or
+/// `Return`, conditional jumps also fall through to the next
+/// block.
+///
+/// ```
+/// # use nous::cfg::{build_cfg, linearize, Successor};
+/// # use nous::tac::TAC;
+/// let program = TAC::from(String::from("int main(void) { return 2; }")).to_tac_program();
+/// let cfg = build_cfg(&program.0[0].body);
+///
+/// assert_eq!(cfg.edges(), vec![(0, Successor::Exit)]);
+/// assert_eq!(linearize(cfg), program.0[0].body);
+/// ```
+pub fn build_cfg(instructions: &Instructions) -> Cfg {
+    let mut blocks: Vec<Instructions> = Vec::new();
+    let mut current = Vec::new();
+
+    for instruction in instructions {
+        if matches!(instruction, Instruction::Label(_)) && !current.is_empty() {
+            blocks.push(mem::take(&mut current));
+        }
+        current.push(instruction.clone());
+
+        if matches!(
+            instruction,
+            Instruction::Jump { .. }
+                | Instruction::JumpIfZero { .. }
+                | Instruction::JumpIfNotZero { .. }
+                | Instruction::Return(_)
+        ) {
+            blocks.push(mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    let labels: HashMap<&Identifier, usize> = blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, block)| match block.first() {
+            Some(Instruction::Label(label)) => Some((label, index)),
+            _ => None,
+        })
+        .collect();
+    let block_of = |label: &Identifier| {
+        Successor::Block(
+            *labels
+                .get(label)
+                .expect("Jumps target a label of the function"),
+        )
+    };
+
+    let successors: Vec<Vec<Successor>> = (0..blocks.len())
+        .map(|index| {
+            let fallthrough = if index + 1 < blocks.len() {
+                Successor::Block(index + 1)
+            } else {
+                Successor::Exit
+            };
+
+            match blocks[index].last() {
+                Some(Instruction::Return(_)) => vec![Successor::Exit],
+                Some(Instruction::Jump { target }) => vec![block_of(target)],
+                Some(
+                    Instruction::JumpIfZero { target, .. }
+                    | Instruction::JumpIfNotZero { target, .. },
+                ) => {
+                    let mut successors = vec![block_of(target), fallthrough];
+                    successors.dedup();
+                    successors
+                }
+                _ => vec![fallthrough],
+            }
+        })
+        .collect();
+
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); blocks.len()];
+    for (index, block_successors) in successors.iter().enumerate() {
+        for successor in block_successors {
+            if let Successor::Block(successor) = successor {
+                predecessors[*successor].push(index);
+            }
+        }
+    }
+
+    Cfg {
+        blocks: blocks
+            .into_iter()
+            .zip(successors)
+            .zip(predecessors)
+            .map(|((instructions, successors), predecessors)| BasicBlock {
+                instructions,
+                successors,
+                predecessors,
+            })
+            .collect(),
+    }
+}
+
+/// Lays the blocks back out into an instruction stream, in their
+/// order within the graph. A block falling through to the next
+/// one must still be followed by it.
+pub fn linearize(cfg: Cfg) -> Instructions {
+    cfg.blocks
+        .into_iter()
+        .flat_map(|block| block.instructions)
+        .collect()
+}
@@ -0,0 +1,383 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    assembly::{self, Assembly, Target},
+    ast::{self, Identifier},
+    errors::{Error, Result},
+    optimizations,
+    parser::Parser,
+    tac::{self, TAC},
+    visitor::{
+        AssemblyPass, LoopLabeling, ReturnAnalysis, UnusedValueAnalysis, VariableResolution,
+    },
+};
+
+/// Kind of artifact produced by the compiler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Emit {
+    /// Formatted assembly code.
+    #[default]
+    Assembly,
+    /// An object file, assembled by the assembler with `-c`.
+    Object,
+    /// An executable, assembled and linked by the linker.
+    Executable,
+}
+
+/// Result of a compilation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    Assembly(String),
+    Object(PathBuf),
+    Executable(PathBuf),
+}
+
+/// Configures and runs the whole compilation pipeline.
+///
+/// ```
+/// # use nous::Compiler;
+/// # use nous::compiler::{Emit, Output};
+/// # use nous::assembly::Target;
+/// let output = Compiler::new()
+///     .opt_level(1)
+///     .target(Target::Linux)
+///     .emit(Emit::Assembly)
+///     .compile("int main(void) { return 2; }")
+///     .expect("Should compile the program");
+///
+/// assert!(matches!(output, Output::Assembly(_)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Compiler {
+    opt_level: u8,
+    target: Target,
+    emit: Emit,
+    output: Option<PathBuf>,
+    input: Option<PathBuf>,
+    keep_intermediate: bool,
+    dump_passes: bool,
+    verbosity: u8,
+    annotate: bool,
+    annotate_lines: bool,
+    warn_unused: bool,
+    tmpdir: Option<PathBuf>,
+    assembler: Option<PathBuf>,
+    linker: Option<PathBuf>,
+    extern_functions: Vec<(Identifier, usize)>,
+}
+
+impl Compiler {
+    /// Returns a compiler emitting assembly for the host
+    /// platform, without optimizations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables the optimization passes on the three address
+    /// code and register allocation, `0` disables them.
+    pub fn opt_level(mut self, opt_level: u8) -> Self {
+        self.opt_level = opt_level;
+        self
+    }
+
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn emit(mut self, emit: Emit) -> Self {
+        self.emit = emit;
+        self
+    }
+
+    /// Path of the produced object file or executable, defaults
+    /// to `a.o` and `a.out` respectively. Ignored when emitting
+    /// assembly.
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Path of the compiled source file, whose stem names the
+    /// assembly file written when emitting an object file or
+    /// executable. Defaults to the stem of the output.
+    pub fn input(mut self, input: impl Into<PathBuf>) -> Self {
+        self.input = Some(input.into());
+        self
+    }
+
+    /// Keeps the assembly file written next to the output when
+    /// emitting an object file or executable.
+    pub fn keep_intermediate(mut self, keep_intermediate: bool) -> Self {
+        self.keep_intermediate = keep_intermediate;
+        self
+    }
+
+    /// Prints the instructions to the standard error after
+    /// each assembly pass, headed by the name of the pass.
+    pub fn dump_passes(mut self, dump_passes: bool) -> Self {
+        self.dump_passes = dump_passes;
+        self
+    }
+
+    /// Prints diagnostics to the standard error: `1` names each
+    /// stage as it runs, `2` also prints the three address code
+    /// and the instructions after each assembly pass. `0`, the
+    /// default, prints nothing but warnings.
+    pub fn verbosity(mut self, verbosity: u8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Annotates the assembly with comments naming the three
+    /// address code instruction each group of instructions
+    /// comes from.
+    pub fn annotate(mut self, annotate: bool) -> Self {
+        self.annotate = annotate;
+        self
+    }
+
+    /// Comments the assembly with the source line each block
+    /// item starts on, e.g. `# line 4`.
+    pub fn annotate_lines(mut self, annotate_lines: bool) -> Self {
+        self.annotate_lines = annotate_lines;
+        self
+    }
+
+    /// Warns about expression statements without side effects,
+    /// such as `x;`, and assignments of a variable to itself.
+    pub fn warn_unused(mut self, warn_unused: bool) -> Self {
+        self.warn_unused = warn_unused;
+        self
+    }
+
+    /// Directory the assembly file is written to when emitting
+    /// an object file or executable, defaults to the directory
+    /// of the output.
+    pub fn tmpdir(mut self, tmpdir: impl Into<PathBuf>) -> Self {
+        self.tmpdir = Some(tmpdir.into());
+        self
+    }
+
+    /// Program assembling object files, which takes `gcc`
+    /// style arguments. Defaults to `gcc`.
+    pub fn assembler(mut self, assembler: impl Into<PathBuf>) -> Self {
+        self.assembler = Some(assembler.into());
+        self
+    }
+
+    /// Program linking executables, which takes `gcc` style
+    /// arguments. Defaults to `gcc`.
+    pub fn linker(mut self, linker: impl Into<PathBuf>) -> Self {
+        self.linker = Some(linker.into());
+        self
+    }
+
+    /// Allows calls to an external function taking `arity`
+    /// arguments, besides the ones in
+    /// [`EXTERN_FUNCTIONS`](crate::visitor::EXTERN_FUNCTIONS).
+    pub fn extern_function(mut self, name: impl Into<Identifier>, arity: usize) -> Self {
+        self.extern_functions.push((name.into(), arity));
+        self
+    }
+
+    /// Runs every stage of the pipeline over `source`, producing
+    /// the configured kind of artifact.
+    pub fn compile(&self, source: &str) -> Result<Output> {
+        let assembly = self.assembly_program(source)?.try_format_for(self.target)?;
+
+        match self.emit {
+            Emit::Assembly => Ok(Output::Assembly(assembly)),
+            Emit::Object => {
+                let output = self.output.clone().unwrap_or(PathBuf::from("a.o"));
+                self.assemble(&assembly, &output, self.assembler_program(), &["-c"])?;
+                Ok(Output::Object(output))
+            }
+            Emit::Executable => {
+                let output = self.output.clone().unwrap_or(PathBuf::from("a.out"));
+                self.assemble(&assembly, &output, self.linker_program(), &[])?;
+                Ok(Output::Executable(output))
+            }
+        }
+    }
+
+    /// Runs every stage of the pipeline up to (and including)
+    /// the assembly passes.
+    pub fn assembly_program(&self, source: &str) -> Result<assembly::Program> {
+        let tac_program = self.tac_program(source)?;
+
+        self.trace(1, || "Generating assembly".into());
+        let mut assembly = Assembly::from(tac_program).annotate(self.annotate);
+        assembly.parse_program();
+
+        // Register allocation is an optimization as well.
+        let mut visitor = AssemblyPass::build(assembly).register_allocation(self.opt_level > 0);
+        visitor.run_passes(|name, program| {
+            if self.dump_passes || self.verbosity >= 2 {
+                eprintln!("After {name}:\n{program:?}");
+            }
+        });
+
+        Ok(visitor.modify_program())
+    }
+
+    /// Runs every stage of the pipeline up to (and including)
+    /// the optimizations of the three address code.
+    pub fn tac_program(&self, source: &str) -> Result<tac::Program> {
+        let program = self.analyzed_program(source)?;
+
+        self.trace(1, || {
+            format!(
+                "Generating three address code, optimized at -O{}",
+                self.opt_level
+            )
+        });
+        let tac_program = TAC::from(program).try_to_tac_program()?;
+        let tac_program = optimizations::optimize(tac_program, self.opt_level);
+        self.trace(2, || {
+            format!("Three address code:\n{}", tac_program.format())
+        });
+
+        Ok(tac_program)
+    }
+
+    /// Parses `source` and runs the semantic analysis over it,
+    /// resolving variables, labeling loops and checking returns.
+    pub fn analyzed_program(&self, source: &str) -> Result<ast::Program> {
+        self.trace(1, || "Parsing".into());
+        let program = Parser::from(source.to_string())
+            .annotate_lines(self.annotate_lines)
+            .to_ast_program()?;
+        self.trace(1, || "Running the semantic analysis".into());
+        let unused_values = if self.warn_unused {
+            let mut unused_values = UnusedValueAnalysis::from(program.clone());
+            unused_values.pass()?;
+            unused_values.take_warnings()
+        } else {
+            Vec::new()
+        };
+        let program = self
+            .extern_functions
+            .iter()
+            .fold(
+                VariableResolution::from(program),
+                |resolution, (name, arity)| resolution.extern_function(name.clone(), *arity),
+            )
+            .get_updated_program()?;
+        // Only valid programs get warnings.
+        report_warnings(unused_values);
+        let program = LoopLabeling::from(program).get_updated_program()?;
+
+        let mut return_analysis = ReturnAnalysis::from(program);
+        let program = return_analysis.get_updated_program()?;
+        report_warnings(return_analysis.take_warnings());
+
+        Ok(program)
+    }
+
+    /// Links the given object files into a single executable.
+    pub fn link(&self, objects: &[PathBuf], output: &Path) -> Result<()> {
+        let mut command = Command::new(self.linker_program());
+        command.args(objects).arg("-o").arg(output);
+        self.trace(1, || format!("Running {command:?}"));
+
+        run_tool(command)
+    }
+
+    /// Prints `message` to the standard error when the verbosity
+    /// is at least `level`.
+    fn trace(&self, level: u8, message: impl FnOnce() -> String) {
+        if self.verbosity >= level {
+            eprintln!("{}", message());
+        }
+    }
+
+    fn assembler_program(&self) -> &Path {
+        self.assembler.as_deref().unwrap_or(Path::new("gcc"))
+    }
+
+    fn linker_program(&self) -> &Path {
+        self.linker.as_deref().unwrap_or(Path::new("gcc"))
+    }
+
+    /// Returns the path of the assembly file written while
+    /// producing `output`, named after the stem of the input.
+    ///
+    /// It never is `output` itself, which the assembler or the
+    /// linker would overwrite while reading it.
+    pub fn assembly_path(&self, output: &Path) -> PathBuf {
+        let directory = match &self.tmpdir {
+            Some(tmpdir) => tmpdir.as_path(),
+            None => output.parent().unwrap_or(Path::new("")),
+        };
+        let stem = self
+            .input
+            .as_deref()
+            .unwrap_or(output)
+            .file_stem()
+            .unwrap_or("a".as_ref());
+
+        let mut file_name = stem.to_os_string();
+        file_name.push(".s");
+        let assembly_file = directory.join(&file_name);
+        if assembly_file != output {
+            return assembly_file;
+        }
+
+        file_name.push(".s");
+        directory.join(file_name)
+    }
+
+    /// Writes `assembly` next to `output`, or into the temporary
+    /// directory, and hands it over to `program`.
+    fn assemble(&self, assembly: &str, output: &Path, program: &Path, args: &[&str]) -> Result<()> {
+        let assembly_file = self.assembly_path(output);
+        fs::write(&assembly_file, assembly).map_err(|error| {
+            Error::IoError(io::Error::other(format!(
+                "Can't write {}: {error}",
+                assembly_file.display()
+            )))
+        })?;
+
+        let mut command = Command::new(program);
+        command.args(args).arg(&assembly_file).arg("-o").arg(output);
+        self.trace(1, || format!("Running {command:?}"));
+        let result = run_tool(command);
+
+        if !self.keep_intermediate {
+            fs::remove_file(&assembly_file)?;
+        }
+
+        result
+    }
+}
+
+/// Prints warnings to the standard error, rendered by miette
+/// along with their severity.
+pub(crate) fn report_warnings(warnings: Vec<Error>) {
+    for warning in warnings {
+        eprintln!("{:?}", miette::Report::new(warning));
+    }
+}
+
+/// Runs an assembler or linker command, reporting its standard
+/// error on failure.
+pub(crate) fn run_tool(mut command: Command) -> Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let result = command.output().map_err(|error| {
+        Error::IoError(io::Error::other(format!("Can't run {program}: {error}")))
+    })?;
+
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(Error::IoError(io::Error::other(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&result.stderr)
+        ))))
+    }
+}
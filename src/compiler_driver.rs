@@ -1,27 +1,121 @@
-use crate::assembly::Assembly;
+use crate::assembly::{Assembly, Target};
+use crate::compiler::{report_warnings, Compiler, Emit, Output};
 use crate::errors::Result;
 use crate::lexer::Token;
 use crate::parser::Parser;
-use crate::tac;
-use crate::tac::TAC;
-use crate::visitor::{AssemblyPass, VariableResolution};
+use crate::utils::read_file;
+use crate::visitor::{
+    AssemblyPass, LoopLabeling, ReturnAnalysis, UnusedValueAnalysis, VariableResolution,
+};
 use clap::{Parser as ClapParser, Subcommand};
 use logos::Logos;
 use miette::Result as MResult;
-use std::fs::{self, File};
+use std::fs;
 use std::io::{self, prelude::*};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// Path given to a program read from the standard input, outputs
+/// are named after it, e.g. `stdin.o`.
+const STDIN_PATH: &str = "stdin.c";
+
 #[derive(ClapParser)]
 #[clap(author, version, about)]
 pub struct CompilerDriver {
-    /// Path of the C program.
-    #[clap(short = 'f', long)]
-    file_path: PathBuf,
-
+    /// Path of a C program, can be given multiple times.
+    #[clap(short = 'f', long = "file-path", required_unless_present = "stdin")]
+    files: Vec<PathBuf>,
+
+    /// Read a single program from the standard input instead
+    /// of `--file-path`.
+    #[clap(long, conflicts_with = "files")]
+    stdin: bool,
+
+    /// Path of the output file. This is the assembly file when
+    /// running `emit-code`, the object file with `-c` and the
+    /// executable otherwise. Defaults to the (first) input file
+    /// with the matching extension.
+    #[clap(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// Compile and assemble each input file into its own object
+    /// file, but do not link.
+    #[clap(short = 'c')]
+    compile_only: bool,
+
+    /// Optimization level, `-O1` enables the optimization
+    /// passes on the three address code and register allocation,
+    /// `-O2` also propagates copies.
+    #[clap(short = 'O', default_value_t = 0)]
+    opt_level: u8,
+
+    /// Keep intermediate files (assembly and object files)
+    /// instead of deleting them.
+    #[clap(long)]
+    keep_intermediate: bool,
+
+    /// Program assembling object files, e.g. `clang` or a cross
+    /// compiler. Takes `gcc` style arguments. Defaults to `gcc`.
+    #[clap(long)]
+    assembler: Option<PathBuf>,
+
+    /// Program linking the executable, with `gcc` style
+    /// arguments. Defaults to `gcc`.
+    #[clap(long)]
+    linker: Option<PathBuf>,
+
+    /// Platform to emit assembly for, `linux` or `macos`.
+    /// Defaults to the host platform.
+    #[clap(long)]
+    target: Option<Target>,
+
+    /// Annotate the assembly with comments naming the three
+    /// address code instruction each group comes from.
+    #[clap(long)]
+    annotate: bool,
+
+    /// Annotate the assembly with comments holding the source
+    /// line each statement or declaration starts on.
+    #[clap(long)]
+    annotate_lines: bool,
+
+    /// Warn about statements without effect, such as `x;` or
+    /// `x = x;`.
+    #[clap(long)]
+    warn_unused: bool,
+
+    /// Print the instructions after each assembly pass to the
+    /// standard error.
+    #[clap(long)]
+    dump_passes: bool,
+
+    /// Print diagnostics to the standard error, `-v` names each
+    /// stage and `-vv` also prints the intermediate representation
+    /// after each pass.
+    #[clap(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Run the linked executable, exiting with its exit code. The
+    /// executable is deleted afterwards unless
+    /// `--keep-intermediate` is given. Ignored along a stage.
+    #[clap(long, conflicts_with = "compile_only")]
+    run: bool,
+
+    /// Directory for intermediate files (assembly and object
+    /// files), created if missing. Defaults to the directory of
+    /// each input file. The final output still follows `-o`.
+    #[clap(long)]
+    tmpdir: Option<PathBuf>,
+
+    /// External function that can be called without being
+    /// declared, as `name:arity`. Can be given multiple times.
+    #[clap(long = "extern", value_parser = parse_extern)]
+    externs: Vec<(String, usize)>,
+
+    /// Stage to stop at. When absent every input file is compiled
+    /// and linked into a single executable.
     #[command(subcommand)]
-    cmd: Commands,
+    cmd: Option<Commands>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -31,7 +125,11 @@ enum Commands {
     Lex,
     /// Directs preprocessor to run the lexer and parser,
     /// but stop before assembly generation.
-    Parse,
+    Parse {
+        /// Prints the AST as an indented tree.
+        #[clap(long)]
+        pretty: bool,
+    },
     /// Runs the compiler through the semantic analysis
     /// stage, stopping before tacky generation.
     Validate,
@@ -47,307 +145,321 @@ enum Commands {
     EmitCode,
 }
 
-#[allow(dead_code)]
 impl CompilerDriver {
     pub fn build() -> CompilerDriver {
         CompilerDriver::parse()
     }
 
-    fn preprocess_file(&self) -> Result<()> {
-        if self.file_path.exists() {
-            let mut output_file = self.file_path.clone();
-            output_file.set_extension("i");
-
-            Command::new("gcc")
-                .args([
-                    "-E",
-                    "-P",
-                    &self
-                        .file_path
-                        .clone()
-                        .into_os_string()
-                        .into_string()
-                        .unwrap(),
-                    "-o",
-                    &output_file.into_os_string().into_string().unwrap(),
-                ])
-                .output()
-                .expect("Failed file preprocessing");
-
-            Ok(())
+    /// Returns the path given by `--output`, or `file_path`
+    /// with its extension replaced by `extension`.
+    ///
+    /// Since each input file produces its own output, `--output`
+    /// can't be used along multiple input files.
+    fn output_path(&self, file_path: &Path, extension: &str) -> Result<PathBuf> {
+        match &self.output {
+            Some(_) if self.files.len() > 1 => Err(crate::errors::Error::IoError(
+                io::Error::other("Can't specify --output with multiple input files"),
+            )),
+            Some(output) => Ok(output.clone()),
+            None => Ok(file_path.with_extension(extension)),
+        }
+    }
+
+    /// Deletes an intermediate file, unless `--keep-intermediate`
+    /// is given, in which case its path is printed.
+    fn remove_intermediate(&self, path: &Path) -> Result<()> {
+        if self.keep_intermediate {
+            println!("Keeping {}", path.display());
         } else {
-            // Err(format!(
-            //     "The file {} does not exists",
-            //     self.file_path.display()
-            // ))
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "No such file",
-            )))?
-            // Err(crate::errors::Error::IoError(io::Error::last_os_error()))
+            fs::remove_file(path)?;
         }
+
+        Ok(())
     }
 
-    fn compile_preproc_file(&self) -> Result<()> {
-        let mut preproc_file = self.file_path.clone();
-        preproc_file.set_extension("i");
-
-        if preproc_file.exists() {
-            let mut output_assembler = PathBuf::from(&self.file_path);
-            output_assembler.set_extension("s");
-
-            /*
-            Command::new("gcc")
-            .args([
-                "-S",
-                "-O",
-                    "-fno-asynchronous-unwind-tables",
-                    "-fcf-protection=none",
-                    &preproc_file.clone().into_os_string().into_string().unwrap(),
-                    "-o",
-                    &output_assembler.into_os_string().into_string().unwrap(),
-                ])
-                .output()
-                .expect("Error compiling file");
-            */
-
-            // Basic compiler implementation
-            let path = preproc_file.clone().into_os_string().into_string().unwrap();
-            let file = fs::read_to_string(path).expect("Unable to read file");
-            let mut assembly = Assembly::from(file);
-            // Parsing the assembly program.
-            assembly.parse_program();
-            // Realizing the assembly passes.
-            let mut assembly_pass = AssemblyPass::build(assembly);
-            assembly_pass
-                .replace_pseudo_registers()
-                .rewrite_binop()
-                .rewrite_mov()
-                .allocate_stack();
-
-            let assembly_program = assembly_pass.modify_program();
-            println!("{:?}", assembly_program);
-            let output_path = output_assembler
-                .clone()
-                .into_os_string()
-                .into_string()
-                .unwrap();
-            println!("Writing: ");
-
-            let path = Path::new(&output_path);
-            let display = path.display();
-
-            // Open a file in write-only mode, returns `io::Result<File>`
-            let mut file = match File::create(path) {
-                Err(why) => panic!("couldn't create {}: {}", display, why),
-                Ok(file) => file,
-            };
+    /// Returns the path of an intermediate file named after
+    /// `file_path`, placed in `--tmpdir` when given.
+    fn intermediate_path(&self, file_path: &Path, extension: &str) -> PathBuf {
+        let path = file_path.with_extension(extension);
 
-            match file.write_all(assembly_program.format().as_bytes()) {
-                Err(why) => panic!("couldn't write to {}: {}", display, why),
-                Ok(_) => println!("successfully wrote to {}", display),
-            }
+        match (&self.tmpdir, path.file_name()) {
+            (Some(tmpdir), Some(file_name)) => tmpdir.join(file_name),
+            _ => path,
+        }
+    }
 
-            // Deleting the preprocessed file
-            Command::new("rm")
-                .arg(preproc_file.into_os_string().into_string().unwrap())
-                .output()
-                .expect("Error deleting preprocessed file");
+    /// Creates `--tmpdir` when it doesn't exist yet.
+    fn create_tmpdir(&self) -> Result<()> {
+        let Some(tmpdir) = &self.tmpdir else {
+            return Ok(());
+        };
+
+        fs::create_dir_all(tmpdir).map_err(|error| {
+            crate::errors::Error::IoError(io::Error::other(format!(
+                "Can't create {}: {error}",
+                tmpdir.display()
+            )))
+        })
+    }
 
-            Ok(())
-        } else {
-            // Err(format!(
-            //     "Error in compilation. The file {} does not exists",
-            //     preproc_file.display()
-            // ))
-            //
-            //
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "No such file",
-            )))?
-            // Err(crate::errors::Error::IoError(io::Error::last_os_error()))
+    /// Returns the path of the linked executable.
+    fn executable_path(&self) -> PathBuf {
+        match (&self.output, self.files.first()) {
+            (Some(output), _) => output.clone(),
+            (None, Some(file_path)) => file_path.with_extension(""),
+            (None, None) => PathBuf::from("a.out"),
         }
     }
 
-    #[allow(dead_code)]
-    fn assemble_file(&self) -> Result<()> {
-        let mut assembly_file = self.file_path.clone();
-        assembly_file.set_extension("s");
-
-        if assembly_file.exists() {
-            dbg!("Assembly exists at {:?}", &assembly_file);
-            let mut output_file = self.file_path.clone();
-            output_file.set_extension("");
-
-            Command::new("gcc")
-                .args([
-                    &assembly_file
-                        .clone()
-                        .into_os_string()
-                        .into_string()
-                        .unwrap(),
-                    "-o",
-                    &output_file.into_os_string().into_string().unwrap(),
-                ])
-                .output()
-                .expect("Failed assembling file");
-
-            Command::new("rm")
-                .arg(assembly_file.into_os_string().into_string().unwrap())
-                .output()
-                .expect("Error deleting assembly file");
-
-            Ok(())
-        } else {
-            // Err(format!(
-            //     "Assembly file {} doesnt exist.",
-            //     assembly_file.display()
-            // ))
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "Assembly file doesnt exist.",
-            )))?
-            // Err(crate::errors::Error::IoError(io::Error::last_os_error()))
+    /// Reads every input program along with its path. A program
+    /// read from `--stdin` is named after [`STDIN_PATH`].
+    fn sources(&self) -> Result<Vec<(PathBuf, String)>> {
+        if self.stdin {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+
+            return Ok(vec![(PathBuf::from(STDIN_PATH), source)]);
         }
+
+        self.files
+            .iter()
+            .map(|file_path| Ok((file_path.clone(), read_file(file_path)?)))
+            .collect()
     }
 
     /// Outputs the token stream.
-    fn lex_file(&self) -> Result<()> {
-        if self.file_path.exists() {
-            let file = fs::read_to_string(&self.file_path).expect("Unable to read file.");
-            let lexer = Token::lexer(&file);
-            let tokn = Vec::from_iter(lexer);
-            // let tokens: Vec<Token> = Vec::from_iter(lexer.clone().map(|x| x.unwrap()));
-            // println!("{:?}", lexer);
-            // println!("{:?}", tokens);
-            println!("{:?}", tokn);
-            Ok(())
-        } else {
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "Failed lexing file, no such file",
-            )))?
-            // Err(crate::errors::Error::IoError(io::Error::last_os_error()))
-            // Err("Failed lexing file, no such file".to_string())
-        }
+    fn lex_file(&self, source: &str) -> Result<()> {
+        let lexer = Token::lexer(source);
+        let tokn = Vec::from_iter(lexer);
+        println!("{:?}", tokn);
+
+        Ok(())
     }
 
     /// Outputs the AST generated by the parser.
-    fn parse_file(&self) -> Result<()> {
-        if self.file_path.exists() {
-            let mut parser = Parser::from(self.file_path.clone());
-            //let ast_program: ast::Program = (&mut parser).into();
-            let ast = parser.to_ast_program()?;
-            println!("{:?}", ast);
+    fn parse_file(&self, source: &str, pretty: bool) -> Result<()> {
+        let mut parser = Parser::from(source.to_string());
+        let ast = parser.to_ast_program()?;
 
-            Ok(())
+        if pretty {
+            print!("{}", ast.pretty());
         } else {
-            // Err("Failed parsing file, no such file".to_string())
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "Failed parsing file, no such file",
-            )))?
-            // Err(crate::errors::Error::IoError(io::Error::last_os_error()))
+            println!("{:?}", ast);
         }
+
+        Ok(())
     }
 
     /// Output the three adress code intermediate representation.
-    fn tac_gen(&self) -> Result<()> {
-        if self.file_path.exists() {
-            let mut tac = TAC::from(self.file_path.clone());
-            let tac_program: tac::Program = (&mut tac).into();
-            println!("{:?}", tac_program);
+    fn tac_gen(&self, source: &str) -> Result<()> {
+        let tac_program = self.compiler().tac_program(source)?;
+        print!("{}", tac_program.format());
 
-            Ok(())
-        } else {
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "Failed TAC generation, no such file",
-            )))?
-            // Err(crate::errors::Error::IoError(io::Error::last_os_error()))
-            // Err("Failed finding file, no such file".to_string())
-        }
+        Ok(())
     }
 
-    fn code_gen(&self) -> Result<()> {
-        if self.file_path.exists() {
-            let mut assembly = Assembly::from(self.file_path.clone());
-            // Parsing the program
-            assembly.parse_program();
-
-            // Visiting the program
-            let mut visitor = AssemblyPass::build(assembly);
-            visitor.print_instructions(Some("Original instructions"));
-            visitor.replace_pseudo_registers();
-            visitor.print_instructions(Some("Replacing pseudo registers"));
-            visitor.rewrite_mov();
-            visitor.print_instructions(Some("Rewriting move instructions"));
-            visitor.rewrite_binop();
-            visitor.print_instructions(Some("Rewriting binary operators"));
-            visitor.rewrite_cmp();
-            visitor.print_instructions(Some("Rewriting cmp operators"));
-
-            Ok(())
-        } else {
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "Failed code generation, no such file",
-            )))?
-            // Err(crate::errors::Error::IoError(io::Error::last_os_error()))
-            // Err("Failed parsing file, no such file".to_string())
-        }
+    fn code_gen(&self, source: &str) -> Result<()> {
+        let tac_program = self.compiler().tac_program(source)?;
+        let mut assembly = Assembly::from(tac_program);
+        // Parsing the program
+        assembly.parse_program();
+
+        // Visiting the program
+        let mut visitor = AssemblyPass::build(assembly);
+        visitor.print_instructions(Some("Original instructions"));
+        visitor.replace_pseudo_registers();
+        visitor.print_instructions(Some("Replacing pseudo registers"));
+        visitor.rewrite_mov();
+        visitor.print_instructions(Some("Rewriting move instructions"));
+        visitor.remove_redundant_mov();
+        visitor.print_instructions(Some("Removing redundant move instructions"));
+        visitor.rewrite_binop();
+        visitor.print_instructions(Some("Rewriting binary operators"));
+        visitor.rewrite_cmp();
+        visitor.print_instructions(Some("Rewriting cmp operators"));
+
+        Ok(())
+    }
+
+    /// Builds a `Compiler` configured from the command line flags.
+    fn compiler(&self) -> Compiler {
+        let compiler = Compiler::new()
+            .opt_level(self.opt_level)
+            .target(self.target.unwrap_or_default())
+            .emit(Emit::Assembly)
+            .keep_intermediate(self.keep_intermediate)
+            .dump_passes(self.dump_passes)
+            .verbosity(self.verbose)
+            .annotate(self.annotate)
+            .annotate_lines(self.annotate_lines)
+            .warn_unused(self.warn_unused);
+        let compiler = match &self.tmpdir {
+            Some(tmpdir) => compiler.tmpdir(tmpdir),
+            None => compiler,
+        };
+        let compiler = match &self.assembler {
+            Some(assembler) => compiler.assembler(assembler),
+            None => compiler,
+        };
+        let compiler = match &self.linker {
+            Some(linker) => compiler.linker(linker),
+            None => compiler,
+        };
+
+        self.externs
+            .iter()
+            .fold(compiler, |compiler, (name, arity)| {
+                compiler.extern_function(name, *arity)
+            })
     }
 
     /// Emmits final assembly code
-    fn emit_code(&self) -> Result<()> {
-        if self.file_path.exists() {
-            let mut assembly = Assembly::from(self.file_path.clone());
-            assembly.parse_program();
-            let mut visitor = AssemblyPass::build(assembly);
-            visitor
-                .replace_pseudo_registers()
-                .rewrite_mov()
-                .rewrite_binop()
-                .rewrite_cmp()
-                .allocate_stack();
-
-            let assembly_program = visitor.modify_program();
-            println!("{}", assembly_program.format());
-
-            Ok(())
+    fn emit_code(&self, file_path: &Path, source: &str) -> Result<()> {
+        let Output::Assembly(assembly) = self.compiler().compile(source)? else {
+            unreachable!("The compiler is configured to emit assembly")
+        };
+
+        if self.output.is_some() {
+            fs::write(self.output_path(file_path, "s")?, assembly)?;
         } else {
-            // Err("Failed parsing file, no such file".to_string())
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "Failed code emission, no such file",
-            )))?
+            println!("{}", assembly);
         }
+
+        Ok(())
     }
 
-    fn validate(&self) -> Result<()> {
-        if self.file_path.exists() {
-            let mut parser = Parser::from(self.file_path.clone());
-            let ast = parser.to_ast_program()?;
+    fn validate(&self, source: &str) -> Result<()> {
+        let mut parser = Parser::from(source.to_string());
+        let ast = parser.to_ast_program()?;
 
-            let mut semantic_analysis = VariableResolution::from(ast);
-            semantic_analysis.pass()?;
+        let unused_values = if self.warn_unused {
+            let mut unused_values = UnusedValueAnalysis::from(ast.clone());
+            unused_values.pass()?;
+            unused_values.take_warnings()
+        } else {
+            Vec::new()
+        };
+        let mut semantic_analysis = self.externs.iter().fold(
+            VariableResolution::from(ast),
+            |resolution, (name, arity)| resolution.extern_function(name, *arity),
+        );
+        let program = semantic_analysis.get_updated_program()?;
 
-            println!("{semantic_analysis:?}");
+        println!("{semantic_analysis:?}");
+        report_warnings(unused_values);
 
-            Ok(())
-        } else {
-            Err(crate::errors::Error::IoError(io::Error::other(
-                "Failed code emission, no such file",
-            )))?
+        let mut loop_labeling = LoopLabeling::from(program);
+        let program = loop_labeling.get_updated_program()?;
+
+        println!("{loop_labeling:?}");
+
+        let mut return_analysis = ReturnAnalysis::from(program);
+        return_analysis.pass()?;
+        report_warnings(return_analysis.take_warnings());
+
+        Ok(())
+    }
+
+    /// Compiles every input file into an object file and,
+    /// unless `-c` is given, links them into a single executable.
+    fn compile(&self) -> Result<()> {
+        let mut objects = Vec::new();
+        self.create_tmpdir()?;
+
+        for (file_path, source) in self.sources()? {
+            let object = if self.compile_only {
+                self.output_path(&file_path, "o")?
+            } else {
+                self.intermediate_path(&file_path, "o")
+            };
+
+            let compiler = self
+                .compiler()
+                .input(&file_path)
+                .emit(Emit::Object)
+                .output(&object);
+            compiler.compile(&source)?;
+
+            if self.keep_intermediate {
+                let assembly_file = compiler.assembly_path(&object);
+                println!("Keeping {}", assembly_file.display());
+            }
+            objects.push(object);
         }
+
+        if !self.compile_only {
+            let result = self.compiler().link(&objects, &self.executable_path());
+
+            for object in &objects {
+                self.remove_intermediate(object)?;
+            }
+
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the linked executable, forwarding its standard
+    /// streams, and returns its exit code.
+    fn run_binary(&self) -> Result<i32> {
+        let executable = self.executable_path();
+        // A bare file name would be looked up in `PATH`.
+        let program = if executable.is_relative() {
+            Path::new(".").join(&executable)
+        } else {
+            executable.clone()
+        };
+
+        let status = Command::new(&program).status().map_err(|error| {
+            crate::errors::Error::IoError(io::Error::other(format!(
+                "Can't run {}: {error}",
+                program.display()
+            )))
+        })?;
+        self.remove_intermediate(&executable)?;
+
+        status.code().ok_or_else(|| {
+            crate::errors::Error::IoError(io::Error::other(format!(
+                "{} was terminated by a signal",
+                program.display()
+            )))
+        })
     }
 
     pub fn run(self) -> MResult<()> {
-        match self.cmd {
-            Commands::Lex => self.lex_file()?,
-            Commands::Parse => self.parse_file()?,
-            Commands::CodeGen => self.code_gen()?,
-            Commands::Tac => self.tac_gen()?,
-            Commands::EmitCode => self.emit_code()?,
-            Commands::Validate => self.validate()?,
+        let Some(cmd) = &self.cmd else {
+            self.compile()?;
+
+            if self.run {
+                std::process::exit(self.run_binary()?);
+            }
+            return Ok(());
+        };
+
+        for (file_path, source) in self.sources()? {
+            match cmd {
+                Commands::Lex => self.lex_file(&source)?,
+                Commands::Parse { pretty } => self.parse_file(&source, *pretty)?,
+                Commands::CodeGen => self.code_gen(&source)?,
+                Commands::Tac => self.tac_gen(&source)?,
+                Commands::EmitCode => self.emit_code(&file_path, &source)?,
+                Commands::Validate => self.validate(&source)?,
+            }
         }
-        // self.preprocess_file()?;
-        // self.compile_preproc_file()?;
-        // //self.assemble_file()?;
 
         Ok(())
     }
 }
+
+/// Parses an `--extern` value of the form `name:arity`.
+fn parse_extern(value: &str) -> std::result::Result<(String, usize), String> {
+    let (name, arity) = value
+        .split_once(':')
+        .ok_or_else(|| format!("Expected `name:arity`, found {value:?}"))?;
+    let arity = arity
+        .parse()
+        .map_err(|error| format!("Invalid arity {arity:?}: {error}"))?;
+
+    Ok((name.to_string(), arity))
+}
@@ -0,0 +1,50 @@
+use crate::{
+    ast::{BinaryOperator, Expression, UnaryOperator},
+    errors::{Error, Result},
+    optimizations::ConstantFolding,
+};
+
+/// Evaluates an integer constant expression, such as the length
+/// of an array or the value of an enumerator, with the 32 bit
+/// arithmetic of `int`. Anything else than constants combined by
+/// unary and binary operators is rejected, as are overflows and
+/// divisions by zero.
+///
+/// ```
+/// # use nous::ast::{BinaryOperator, Expression};
+/// # use nous::const_eval::eval_const;
+/// let expression = Expression::Binary(
+///     BinaryOperator::Add,
+///     Box::new(Expression::Constant(2)),
+///     Box::new(Expression::Constant(3)),
+/// );
+///
+/// assert_eq!(eval_const(&expression).ok(), Some(5));
+/// ```
+pub fn eval_const(expression: &Expression) -> Result<i64> {
+    let value = match expression {
+        Expression::Constant(value) => i32::try_from(*value).ok(),
+        // `-2147483648` negates a literal `int` can't hold.
+        Expression::Unary(operator @ UnaryOperator::Negate, src) => match **src {
+            Expression::Constant(value) => i32::try_from(-value).ok(),
+            _ => ConstantFolding::fold_unary(operator, eval_const(src)?),
+        },
+        Expression::Unary(operator, src) => ConstantFolding::fold_unary(operator, eval_const(src)?),
+        Expression::Binary(operator @ (BinaryOperator::And | BinaryOperator::Or), left, right) => {
+            let (left, right) = (eval_const(left)? != 0, eval_const(right)? != 0);
+
+            Some(match operator {
+                BinaryOperator::And => (left && right).into(),
+                _ => (left || right).into(),
+            })
+        }
+        Expression::Binary(operator, left, right) => {
+            ConstantFolding::fold_binary(operator, eval_const(left)?, eval_const(right)?)
+        }
+        _ => None,
+    };
+
+    value.map(i64::from).ok_or_else(|| Error::NotAConstant {
+        value: expression.clone(),
+    })
+}
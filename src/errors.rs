@@ -2,7 +2,7 @@ use crate::{
     ast::{self, Expression},
     lexer::Token,
 };
-use miette::Diagnostic;
+use miette::{Diagnostic, SourceSpan};
 use thiserror::Error as ThisError;
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -11,6 +11,13 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug, ThisError, Diagnostic)]
 #[error("Error")]
 pub enum Error {
+    /// Lexer errors
+    #[error("Invalid token {slice:?}")]
+    InvalidToken { slice: String },
+
+    #[error("Integer literal out of range: {literal}")]
+    IntegerOutOfRange { literal: String },
+
     /// Parser errors
     #[error("{message:?}. \n\tUnexpected token. Expected {expected:?}, but found {found:?}")]
     UnexpectedToken {
@@ -34,16 +41,109 @@ pub enum Error {
     #[error("{found:?} is not a unary operator")]
     NotUnop { found: Token },
 
+    #[error("Unsupported feature: {feature}")]
+    Unsupported { feature: &'static str },
+
+    #[error("Invalid type specifiers: {specifiers:?}")]
+    InvalidType { specifiers: Vec<Token> },
+
+    #[error("The parser made no progress at token {found:?}")]
+    ParserStuck { found: Token },
+
+    #[error("{delimiter:?} opened on line {line} is never closed")]
+    UnterminatedBlock {
+        delimiter: Token,
+        line: usize,
+        #[label("opened here")]
+        span: SourceSpan,
+    },
+
+    #[error("{value:?} is not an integer constant expression")]
+    NotAConstant { value: Expression },
+
     /// Variable resolution errors
     #[error("Variable resolution error, duplicate variable declaration: {var:#?}")]
     DuplicateVarDeclaration { var: ast::Identifier },
 
+    #[error("The file scope variable {var:?} is initialized to {initializer:?}, which is not a constant")]
+    NonConstantInitializer {
+        var: ast::Identifier,
+        initializer: Expression,
+    },
+
     #[error("Invalid left value: {value:?}")]
     InvalidLVal { value: Expression },
 
     #[error("Undeclared variable: {value:?}")]
     UndeclaredVar { value: ast::Identifier },
 
+    #[error("{value:?} is not an array, it can't be subscripted")]
+    NotAnArray { value: Expression },
+
+    #[error("The array {name:?} can only be subscripted or given to `sizeof`")]
+    ArrayValue { name: ast::Identifier },
+
+    #[error("{value:?} is a pointer where an integer is expected, or the other way around")]
+    PointerMismatch { value: Expression },
+
+    #[error("The divisor of `{}` is always zero", expression.to_c())]
+    DivisionByZero { expression: Expression },
+
+    #[error("Conflicting declarations of the function {name:?}")]
+    ConflictingDeclaration { name: ast::Identifier },
+
+    #[error("Undeclared function: {name:?}")]
+    UndeclaredFunction { name: ast::Identifier },
+
+    #[error("{name:?} is a variable shadowing the function of the same name, it can't be called")]
+    NotAFunction { name: ast::Identifier },
+
+    #[error("The function {name:?} takes {expected} arguments, but {found} were given")]
+    ArgumentCount {
+        name: ast::Identifier,
+        expected: usize,
+        found: usize,
+    },
+
+    /// Loop labeling errors
+    #[error("`break` statement outside of a loop, in the function {function:?}")]
+    BreakOutsideLoop { function: ast::Identifier },
+
+    #[error("`continue` statement outside of a loop, in the function {function:?}")]
+    ContinueOutsideLoop { function: ast::Identifier },
+
+    /// Return analysis errors
+    #[error("The function {name:?} returns {}, every `return` must match it", return_type.to_c())]
+    InvalidReturn {
+        name: ast::Identifier,
+        return_type: ast::Type,
+    },
+
+    /// Return analysis warnings
+    #[error("The function {name:?} can reach its end without returning a value, it returns 0")]
+    #[diagnostic(severity(Warning))]
+    MissingReturn { name: ast::Identifier },
+
+    /// Unused value warnings
+    #[error("The value of `{}` is unused, the statement has no effect", value.to_c())]
+    #[diagnostic(severity(Warning))]
+    UnusedValue { value: Expression },
+
+    #[error("`{}` is assigned to itself, the statement has no effect", value.to_c())]
+    #[diagnostic(severity(Warning))]
+    SelfAssignment { value: Expression },
+
+    /// Three address code errors
+    #[error("A loop of the function {function:?} is not labeled, `LoopLabeling` must run first")]
+    UnlabeledLoop { function: ast::Identifier },
+
+    /// Code emission errors
+    #[error("The function {function:?} still holds a pseudo register in `{instruction}`, it was not replaced by any pass")]
+    UnreplacedPseudoRegister {
+        function: ast::Identifier,
+        instruction: String,
+    },
+
     /// Io errors
     // TODO: This may be an OS error
     #[diagnostic()]
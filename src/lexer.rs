@@ -1,17 +1,56 @@
 use crate::errors::{Error, Result};
-use logos::Logos;
+use logos::{FilterResult, Lexer, Logos};
+
+/// Options of the lexer, given through
+/// [`Token::lexer_with_extras`].
+///
+/// ```
+/// # use logos::Logos;
+/// # use nous::lexer::{LexerOptions, Token};
+/// let options = LexerOptions {
+///     nested_comments: true,
+/// };
+/// let tokens: Vec<_> = Token::lexer_with_extras("/* a /* b */ c */ 1", options).collect();
+///
+/// assert_eq!(tokens, vec![Ok(Token::Constant(1))]);
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LexerOptions {
+    /// Allows block comments to nest, as in `/* /* */ */`. By
+    /// default the first `*/` closes the comment, as in C.
+    pub nested_comments: bool,
+}
 
 #[derive(Hash, Eq, Logos, Debug, PartialEq, Clone)]
-// TODO: Skip block comments #[logos(skip r"\/*(?:[^*]|\*[^/])*\*\/")]
+#[logos(extras = LexerOptions)]
 #[logos(skip r"[ \t\n\f]+")]
 #[logos(skip r"//[^\n]*")] // Skips comments
 pub enum Token {
-    #[regex("[a-zA-Z][a-zA-Z0-9_-]*", |lex| lex.slice().to_string())]
+    /// Block comments are skipped, this token is never emitted.
+    #[token("/*", block_comment)]
+    BlockComment,
+
+    #[regex("[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
     Identifier(String),
 
-    #[regex(r"[0-9]+", |lex| lex.slice().parse::<i64>().unwrap())]
+    /// Character literals are lexed as the constant of their
+    /// code point, e.g. `'A'` is `65`.
+    /// Literals wider than 64 bits are a lexer error.
+    #[regex(r"[0-9]+", |lex| lex.slice().parse::<i64>().ok())]
+    #[regex(r"'([^'\\\n]|\\.)*'", character)]
+    // A constant can't run into an identifier, as in `1foo`.
+    #[regex(r"[0-9]+[a-zA-Z_][a-zA-Z0-9_]*", |_| None)]
     Constant(i64),
 
+    /// Constants with a `u` suffix, which must fit in 32 bits.
+    #[regex(r"[0-9]+[uU]", |lex| lex.slice()[..lex.slice().len() - 1].parse::<u32>().ok(), priority = 10)]
+    UnsignedConstant(u32),
+
+    /// Floating point literals aren't supported, they are only
+    /// lexed to report them.
+    #[regex(r"[0-9]+\.[0-9]+", |lex| lex.slice().to_string())]
+    Float(String),
+
     #[token("(")]
     LParen,
 
@@ -24,6 +63,12 @@ pub enum Token {
     #[token("}")]
     RBrace,
 
+    #[token("[")]
+    LBracket,
+
+    #[token("]")]
+    RBracket,
+
     #[token(";")]
     Semicolon,
 
@@ -43,15 +88,24 @@ pub enum Token {
     #[token("void")]
     Void,
 
+    #[token("unsigned")]
+    Unsigned,
+
     #[token("return")]
     Return,
 
+    #[token("sizeof")]
+    Sizeof,
+
+    #[token("enum")]
+    Enum,
+
     // Arithmetic operators
     /// Addition
     #[token("+")]
     Add,
 
-    /// Multiplication
+    /// Multiplication, or the dereference of a pointer.
     #[token("*")]
     Mul,
 
@@ -70,6 +124,10 @@ pub enum Token {
     #[token("&&")]
     And,
 
+    /// The address of an lvalue, there is no bitwise and yet.
+    #[token("&")]
+    Ampersand,
+
     #[token("||")]
     Or,
 
@@ -91,6 +149,13 @@ pub enum Token {
     #[token(">=")]
     GreaterThanOrEq,
 
+    // Bitwise shift operators
+    #[token("<<")]
+    ShiftLeft,
+
+    #[token(">>")]
+    ShiftRight,
+
     /// Assignment operator
     #[token("=")]
     Assign,
@@ -102,6 +167,22 @@ pub enum Token {
     #[token("else")]
     Else,
 
+    /// Loops
+    #[token("while")]
+    While,
+
+    #[token("do")]
+    Do,
+
+    #[token("for")]
+    For,
+
+    #[token("break")]
+    Break,
+
+    #[token("continue")]
+    Continue,
+
     /// A question mark, the delimiter between the first and second
     /// operands in a conditional expression
     #[token("?")]
@@ -111,29 +192,106 @@ pub enum Token {
     /// in a conditional expression
     #[token(":")]
     Colon,
+
+    /// Separates the operands of a comma expression.
+    #[token(",")]
+    Comma,
+
+    /// The end of the source, never lexed but appended by the
+    /// parser after the last token.
+    Eof,
 }
 
+/// Binding power of each binary operator, higher binds tighter.
+///
+/// The conditional operator binds tighter than assignment, so
+/// `a = b ? c : d` assigns the whole conditional. Assignment and
+/// the conditional operator are right associative, every other
+/// operator is left associative.
+pub const PRECEDENCE_TABLE: &[(Token, usize)] = &[
+    (Token::Mul, 50),
+    (Token::Div, 50),
+    (Token::Remainder, 50),
+    (Token::Add, 45),
+    (Token::Negation, 45),
+    (Token::ShiftLeft, 40),
+    (Token::ShiftRight, 40),
+    (Token::LessThan, 35),
+    (Token::LessThanOrEq, 35),
+    (Token::GreaterThan, 35),
+    (Token::GreaterThanOrEq, 35),
+    (Token::EqualTo, 30),
+    (Token::NotEqualTo, 30),
+    (Token::And, 10),
+    (Token::Or, 5),
+    (Token::QuestionMark, 3),
+    (Token::Assign, 1),
+    (Token::Comma, 0),
+];
+
 impl Token {
+    /// Returns the precedence of a binary operator, see
+    /// [`PRECEDENCE_TABLE`].
     pub fn precedence(&self) -> Result<usize> {
-        match self {
-            Token::Mul => Ok(50),
-            Token::Div => Ok(50),
-            Token::Remainder => Ok(50),
-            Token::Add => Ok(45),
-            Token::Negation => Ok(45),
-            Token::LessThan => Ok(35),
-            Token::LessThanOrEq => Ok(35),
-            Token::GreaterThan => Ok(35),
-            Token::GreaterThanOrEq => Ok(35),
-            Token::EqualTo => Ok(30),
-            Token::NotEqualTo => Ok(30),
-            Token::And => Ok(10),
-            Token::Or => Ok(5),
-            Token::Assign => Ok(1),
-            Token::QuestionMark => Ok(3),
-            token => Err(Error::Precedence {
-                found: token.clone(),
-            }),
+        PRECEDENCE_TABLE
+            .iter()
+            .find(|(token, _)| token == self)
+            .map(|(_, precedence)| *precedence)
+            .ok_or_else(|| Error::Precedence {
+                found: self.clone(),
+            })
+    }
+}
+
+/// Skips a block comment, tracking its nesting depth if
+/// `LexerOptions::nested_comments` is set. Unterminated
+/// comments are a lexer error.
+fn block_comment(lex: &mut Lexer<Token>) -> FilterResult<(), ()> {
+    let remainder = lex.remainder().as_bytes();
+    let mut depth = 1;
+    let mut index = 0;
+
+    while index + 1 < remainder.len() {
+        match &remainder[index..index + 2] {
+            b"*/" => {
+                depth -= 1;
+                index += 2;
+
+                if depth == 0 {
+                    lex.bump(index);
+                    return FilterResult::Skip;
+                }
+            }
+            b"/*" if lex.extras.nested_comments => {
+                depth += 1;
+                index += 2;
+            }
+            _ => index += 1,
         }
     }
+
+    lex.bump(remainder.len());
+    FilterResult::Error(())
+}
+
+/// Returns the code point of a character literal, or `None`
+/// (a lexer error) if it is empty, holds more than one
+/// character or an unknown escape sequence.
+fn character(lex: &mut Lexer<Token>) -> Option<i64> {
+    let slice = lex.slice();
+    let mut chars = slice[1..slice.len() - 1].chars();
+
+    let character = match chars.next()? {
+        '\\' => match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            '0' => '\0',
+            escaped @ ('\\' | '\'' | '"') => escaped,
+            _ => return None,
+        },
+        character => character,
+    };
+
+    // Wider characters need more than one byte.
+    (chars.next().is_none() && character.is_ascii()).then_some(u32::from(character).into())
 }
@@ -1,9 +1,37 @@
 pub mod assembly;
 pub mod ast;
+pub mod cfg;
+pub mod compiler;
 pub mod compiler_driver;
+pub mod const_eval;
 pub mod errors;
 pub mod lexer;
+pub mod optimizations;
 pub mod parser;
+pub mod register_allocation;
 pub mod tac;
 pub mod utils;
 pub mod visitor;
+
+pub use compiler::Compiler;
+
+use assembly::Target;
+use errors::Result;
+
+/// Compiles `source` without optimizations into assembly for
+/// `target`, running every stage of the pipeline. Use
+/// [`Compiler`] for anything else.
+///
+/// ```
+/// # use nous::assembly::Target;
+/// let assembly = nous::compile_to_assembly("int main(void) { return 2; }", Target::Linux)
+///     .expect("Should compile the program");
+///
+/// assert!(assembly.contains("\tmovl\t$2, %eax\n"));
+/// ```
+pub fn compile_to_assembly(source: &str, target: Target) -> Result<String> {
+    Compiler::new()
+        .target(target)
+        .assembly_program(source)?
+        .try_format_for(target)
+}
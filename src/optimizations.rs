@@ -0,0 +1,503 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use crate::{
+    ast::{BinaryOperator, Identifier, Type, UnaryOperator},
+    cfg::{build_cfg, linearize, Successor},
+    tac::{self, Instruction, Instructions, Val},
+};
+
+/// An optimization pass over a three address code
+/// instruction stream.
+pub trait Optimization {
+    fn optimize(&self, instructions: Instructions) -> Instructions;
+}
+
+/// Runs the optimizations enabled at `opt_level` over
+/// every function in the program: `1` folds constants,
+/// simplifies branches and eliminates unreachable blocks and
+/// dead code, `2` also propagates copies before simplifying
+/// branches.
+///
+/// ```
+/// # use nous::optimizations::optimize;
+/// # use nous::tac::{Instruction, TAC, Val};
+/// let program = TAC::from(String::from("int main(void) { return 2 + 3; }")).to_tac_program();
+/// let program = optimize(program, 1);
+///
+/// assert_eq!(
+///     program.0[0].body[0],
+///     Instruction::Copy {
+///         src: Val::Constant(5),
+///         dst: Val::Var("tmp.1".into()),
+///     }
+/// );
+/// ```
+pub fn optimize(mut program: tac::Program, opt_level: u8) -> tac::Program {
+    let statics = program.1.iter().map(|variable| variable.name.clone());
+    let passes: Vec<Box<dyn Optimization>> = match opt_level {
+        0 => vec![],
+        1 => vec![
+            Box::new(ConstantFolding),
+            Box::new(JumpThreading),
+            Box::new(UnreachableBlockElimination),
+            Box::new(DeadCodeElimination),
+        ],
+        _ => vec![
+            Box::new(ConstantFolding),
+            Box::new(CopyPropagation::new(statics)),
+            Box::new(JumpThreading),
+            Box::new(UnreachableBlockElimination),
+            Box::new(DeadCodeElimination),
+        ],
+    };
+
+    for function in &mut program.0 {
+        for pass in &passes {
+            function.body = pass.optimize(mem::take(&mut function.body));
+        }
+    }
+
+    program
+}
+
+/// Replaces `Unary` and `Binary` instructions whose operands
+/// are all constants with a `Copy` of the computed value, and
+/// `Select` instructions on a constant condition with a `Copy`
+/// of the selected value.
+///
+/// Instructions that would overflow or divide by zero
+/// are left untouched, so their behaviour is kept at runtime.
+pub struct ConstantFolding;
+
+impl Optimization for ConstantFolding {
+    fn optimize(&self, instructions: Instructions) -> Instructions {
+        instructions
+            .into_iter()
+            .map(|instruction| self.fold(instruction))
+            .collect()
+    }
+}
+
+impl ConstantFolding {
+    fn fold(&self, instruction: Instruction) -> Instruction {
+        if let Instruction::Select {
+            condition: Val::Constant(condition),
+            src_1,
+            src_2,
+            dst,
+        } = instruction
+        {
+            return Instruction::Copy {
+                src: if condition != 0 { src_1 } else { src_2 },
+                dst,
+            };
+        }
+
+        let folded = match &instruction {
+            Instruction::Unary {
+                operator,
+                src: Val::Constant(src),
+                dst,
+            } => Self::fold_unary(operator, *src)
+                .map(i64::from)
+                .map(|value| (value, dst)),
+            Instruction::Binary {
+                binary_operator,
+                src_1: Val::Constant(src_1),
+                src_2: Val::Constant(src_2),
+                dst,
+                ty: Type::Int,
+            } => Self::fold_binary(binary_operator, *src_1, *src_2)
+                .map(i64::from)
+                .map(|value| (value, dst)),
+            Instruction::Binary {
+                binary_operator,
+                src_1: Val::Constant(src_1),
+                src_2: Val::Constant(src_2),
+                dst,
+                ty: Type::UnsignedInt,
+            } => Self::fold_unsigned_binary(binary_operator, *src_1, *src_2)
+                .map(|value| (value, dst)),
+            _ => None,
+        };
+
+        match folded {
+            Some((value, dst)) => Instruction::Copy {
+                src: Val::Constant(value),
+                dst: dst.clone(),
+            },
+            None => instruction,
+        }
+    }
+
+    /// Values are 32 bit integers at runtime.
+    pub(crate) fn fold_unary(operator: &UnaryOperator, src: i64) -> Option<i32> {
+        let src = i32::try_from(src).ok()?;
+
+        match operator {
+            UnaryOperator::Complement => Some(!src),
+            UnaryOperator::Negate => src.checked_neg(),
+            UnaryOperator::Not => Some((src == 0).into()),
+        }
+    }
+
+    pub(crate) fn fold_binary(operator: &BinaryOperator, src_1: i64, src_2: i64) -> Option<i32> {
+        let a = i32::try_from(src_1).ok()?;
+        let b = i32::try_from(src_2).ok()?;
+
+        match operator {
+            BinaryOperator::Add => a.checked_add(b),
+            BinaryOperator::Subtract => a.checked_sub(b),
+            BinaryOperator::Multiply => a.checked_mul(b),
+            BinaryOperator::Divide => a.checked_div(b),
+            BinaryOperator::Remainder => a.checked_rem(b),
+            // Shifting by a negative count or past the width of
+            // `int` is left to the runtime.
+            BinaryOperator::ShiftLeft => a.checked_shl(u32::try_from(b).ok()?),
+            BinaryOperator::ShiftRight => a.checked_shr(u32::try_from(b).ok()?),
+            BinaryOperator::Equal => Some((a == b).into()),
+            BinaryOperator::NotEqual => Some((a != b).into()),
+            BinaryOperator::LessThan => Some((a < b).into()),
+            BinaryOperator::LessOrEqual => Some((a <= b).into()),
+            BinaryOperator::GreaterThan => Some((a > b).into()),
+            BinaryOperator::GreaterOrEqual => Some((a >= b).into()),
+            // Logical operators are lowered into jumps.
+            BinaryOperator::And | BinaryOperator::Or => None,
+        }
+    }
+
+    /// Unsigned arithmetic wraps around, the operands may be
+    /// signed constants converted to unsigned.
+    fn fold_unsigned_binary(operator: &BinaryOperator, src_1: i64, src_2: i64) -> Option<i64> {
+        let a = Self::to_unsigned(src_1)?;
+        let b = Self::to_unsigned(src_2)?;
+
+        let value = match operator {
+            BinaryOperator::Add => a.wrapping_add(b),
+            BinaryOperator::Subtract => a.wrapping_sub(b),
+            BinaryOperator::Multiply => a.wrapping_mul(b),
+            BinaryOperator::Divide => a.checked_div(b)?,
+            BinaryOperator::Remainder => a.checked_rem(b)?,
+            BinaryOperator::ShiftLeft => a.checked_shl(b)?,
+            BinaryOperator::ShiftRight => a.checked_shr(b)?,
+            BinaryOperator::Equal => (a == b).into(),
+            BinaryOperator::NotEqual => (a != b).into(),
+            BinaryOperator::LessThan => (a < b).into(),
+            BinaryOperator::LessOrEqual => (a <= b).into(),
+            BinaryOperator::GreaterThan => (a > b).into(),
+            BinaryOperator::GreaterOrEqual => (a >= b).into(),
+            BinaryOperator::And | BinaryOperator::Or => return None,
+        };
+
+        Some(value.into())
+    }
+
+    /// Returns the 32 bit pattern of a constant, signed or not.
+    fn to_unsigned(value: i64) -> Option<u32> {
+        u32::try_from(value)
+            .ok()
+            .or_else(|| i32::try_from(value).ok().map(|value| value as u32))
+    }
+}
+
+/// Replaces the variables read after a `Copy` by the copied
+/// value, then drops the copies into variables nothing reads.
+///
+/// Copies are only tracked within a basic block, every label
+/// and jump forgets them. Calls forget the copies involving
+/// static variables, which the callee may write, and copies
+/// into static variables are never dropped. Variables whose
+/// address is taken are treated the same way by stores through
+/// a pointer.
+pub struct CopyPropagation {
+    statics: HashSet<Identifier>,
+}
+
+impl Optimization for CopyPropagation {
+    fn optimize(&self, instructions: Instructions) -> Instructions {
+        let mut copies: HashMap<Identifier, Val> = HashMap::new();
+        let aliased: HashSet<Identifier> = instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::GetAddress { src, .. } => Some(src.clone()),
+                _ => None,
+            })
+            .collect();
+        let is_aliased = |val: &Val| matches!(val, Val::Var(name) if aliased.contains(name));
+
+        let instructions = instructions
+            .into_iter()
+            .map(|mut instruction| {
+                if matches!(instruction, Instruction::Label(_)) {
+                    copies.clear();
+                }
+
+                for src in instruction.sources_mut() {
+                    if let Val::Var(name) = src {
+                        if let Some(value) = copies.get(name) {
+                            *src = value.clone();
+                        }
+                    }
+                }
+
+                if matches!(instruction, Instruction::FunCall { .. }) {
+                    copies.retain(|dst, src| !self.is_static(dst) && !self.is_static_val(src));
+                }
+                if matches!(instruction, Instruction::StoreThrough { .. }) {
+                    copies.retain(|dst, src| !aliased.contains(dst) && !is_aliased(src));
+                }
+                if let Some(Val::Var(dst)) = instruction.destination().cloned() {
+                    copies.remove(&dst);
+                    copies.retain(|_, src| !matches!(src, Val::Var(name) if *name == dst));
+                }
+
+                match &instruction {
+                    Instruction::Copy {
+                        src,
+                        dst: Val::Var(dst),
+                    } if !matches!(src, Val::Var(name) if name == dst) => {
+                        copies.insert(dst.clone(), src.clone());
+                    }
+                    Instruction::Return(_)
+                    | Instruction::Jump { .. }
+                    | Instruction::JumpIfZero { .. }
+                    | Instruction::JumpIfNotZero { .. } => copies.clear(),
+                    _ => {}
+                }
+
+                instruction
+            })
+            .collect();
+
+        self.remove_unused_copies(instructions, &aliased)
+    }
+}
+
+impl CopyPropagation {
+    pub fn new(statics: impl IntoIterator<Item = Identifier>) -> Self {
+        Self {
+            statics: statics.into_iter().collect(),
+        }
+    }
+
+    fn is_static(&self, name: &Identifier) -> bool {
+        self.statics.contains(name)
+    }
+
+    fn is_static_val(&self, val: &Val) -> bool {
+        matches!(val, Val::Var(name) if self.is_static(name))
+    }
+
+    /// Dropping a copy may leave the copy it read from unused,
+    /// so this is repeated until nothing changes.
+    fn remove_unused_copies(
+        &self,
+        mut instructions: Instructions,
+        aliased: &HashSet<Identifier>,
+    ) -> Instructions {
+        loop {
+            let read: HashSet<Identifier> = instructions
+                .iter()
+                .flat_map(Instruction::sources)
+                .filter_map(|val| match val {
+                    Val::Var(name) => Some(name.clone()),
+                    Val::Constant(_) => None,
+                })
+                .collect();
+
+            let length = instructions.len();
+            instructions.retain(|instruction| match instruction {
+                Instruction::Copy {
+                    dst: Val::Var(dst), ..
+                } => read.contains(dst) || self.is_static(dst) || aliased.contains(dst),
+                _ => true,
+            });
+
+            if instructions.len() == length {
+                return instructions;
+            }
+        }
+    }
+}
+
+/// Simplifies branches: conditional jumps on a constant become a
+/// `Jump` when taken and are dropped otherwise, and jumps to a
+/// label directly followed by a `Jump` go to the target of that
+/// jump instead, following chains of them.
+///
+/// The jumps left with no label to go through are then removed
+/// by `UnreachableBlockElimination` and `DeadCodeElimination`.
+pub struct JumpThreading;
+
+impl Optimization for JumpThreading {
+    fn optimize(&self, instructions: Instructions) -> Instructions {
+        let instructions: Instructions = instructions
+            .into_iter()
+            .filter_map(Self::fold_branch)
+            .collect();
+        let forwards = Self::forwards(&instructions);
+
+        instructions
+            .into_iter()
+            .map(|mut instruction| {
+                if let Instruction::Jump { target }
+                | Instruction::JumpIfZero { target, .. }
+                | Instruction::JumpIfNotZero { target, .. } = &mut instruction
+                {
+                    *target = Self::thread(&forwards, target.clone());
+                }
+
+                instruction
+            })
+            .collect()
+    }
+}
+
+impl JumpThreading {
+    fn fold_branch(instruction: Instruction) -> Option<Instruction> {
+        match instruction {
+            Instruction::JumpIfZero {
+                condition: Val::Constant(condition),
+                target,
+            } => (condition == 0).then_some(Instruction::Jump { target }),
+            Instruction::JumpIfNotZero {
+                condition: Val::Constant(condition),
+                target,
+            } => (condition != 0).then_some(Instruction::Jump { target }),
+            _ => Some(instruction),
+        }
+    }
+
+    /// Maps each label directly followed by a `Jump`, past other
+    /// labels and line markers, to the target of that jump.
+    fn forwards(instructions: &Instructions) -> HashMap<Identifier, Identifier> {
+        let mut forwards = HashMap::new();
+
+        for (position, instruction) in instructions.iter().enumerate() {
+            let Instruction::Label(label) = instruction else {
+                continue;
+            };
+            let next = instructions[position + 1..].iter().find(|instruction| {
+                !matches!(instruction, Instruction::Label(_) | Instruction::Line(_))
+            });
+
+            if let Some(Instruction::Jump { target }) = next {
+                forwards.insert(label.clone(), target.clone());
+            }
+        }
+
+        forwards
+    }
+
+    /// An infinite loop of jumps stops at the first label seen
+    /// twice.
+    fn thread(forwards: &HashMap<Identifier, Identifier>, mut target: Identifier) -> Identifier {
+        let mut seen = HashSet::new();
+
+        while let Some(next) = forwards.get(&target) {
+            if !seen.insert(target.clone()) {
+                break;
+            }
+            target = next.clone();
+        }
+
+        target
+    }
+}
+
+/// Drops the basic blocks no path from the entry of the function
+/// reaches, such as a branch whose condition was folded to a
+/// constant. The remaining blocks keep their order, so each one
+/// still falls through to the block it did before.
+pub struct UnreachableBlockElimination;
+
+impl Optimization for UnreachableBlockElimination {
+    fn optimize(&self, instructions: Instructions) -> Instructions {
+        let mut cfg = build_cfg(&instructions);
+        if cfg.blocks.is_empty() {
+            return instructions;
+        }
+
+        let mut reachable = vec![false; cfg.blocks.len()];
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            if mem::replace(&mut reachable[index], true) {
+                continue;
+            }
+
+            for successor in &cfg.blocks[index].successors {
+                if let Successor::Block(successor) = successor {
+                    stack.push(*successor);
+                }
+            }
+        }
+
+        let mut reachable = reachable.into_iter();
+        cfg.blocks.retain(|_| reachable.next().unwrap_or(false));
+
+        linearize(cfg)
+    }
+}
+
+/// Drops the instructions following a `Return` or `Jump`
+/// up to the next `Label`, and the labels no jump targets.
+///
+/// Removing a label can make the code after it unreachable,
+/// so both steps are repeated until nothing changes.
+pub struct DeadCodeElimination;
+
+impl Optimization for DeadCodeElimination {
+    fn optimize(&self, mut instructions: Instructions) -> Instructions {
+        loop {
+            let length = instructions.len();
+            instructions = Self::remove_untargeted_labels(Self::remove_unreachable(instructions));
+
+            if instructions.len() == length {
+                return instructions;
+            }
+        }
+    }
+}
+
+impl DeadCodeElimination {
+    fn remove_unreachable(instructions: Instructions) -> Instructions {
+        let mut reachable = true;
+
+        instructions
+            .into_iter()
+            .filter(|instruction| {
+                match instruction {
+                    Instruction::Label(_) => reachable = true,
+                    _ if !reachable => return false,
+                    Instruction::Return(_) | Instruction::Jump { .. } => reachable = false,
+                    _ => {}
+                }
+
+                true
+            })
+            .collect()
+    }
+
+    fn remove_untargeted_labels(instructions: Instructions) -> Instructions {
+        let targets: HashSet<Identifier> = instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Jump { target }
+                | Instruction::JumpIfZero { target, .. }
+                | Instruction::JumpIfNotZero { target, .. } => Some(target.clone()),
+                _ => None,
+            })
+            .collect();
+
+        instructions
+            .into_iter()
+            .filter(|instruction| match instruction {
+                Instruction::Label(label) => targets.contains(label),
+                _ => true,
+            })
+            .collect()
+    }
+}
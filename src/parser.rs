@@ -1,6 +1,7 @@
 use std::{
     collections::VecDeque,
     fs::{self},
+    mem,
     path::PathBuf,
 };
 
@@ -8,8 +9,9 @@ use logos::{Lexer, Logos};
 
 use crate::{
     ast::{self, BlockItems, Identifier},
+    const_eval::eval_const,
     errors::{Error, Result},
-    lexer::Token,
+    lexer::{Token, PRECEDENCE_TABLE},
 };
 
 /// Turns a stream of Tokens into a Parser object.
@@ -31,22 +33,26 @@ pub struct Parser {
     current_token: Token,
     /// Next token in token stream
     peek_token: Token,
+    /// Byte offset of each token still to be consumed, starting
+    /// with the current one.
+    offsets: VecDeque<usize>,
+    /// Byte offset of the start of each source line.
+    line_starts: Vec<usize>,
+    /// Whether block items are preceded by the line they start on.
+    annotate_lines: bool,
+    /// First lexer error, reported instead of parsing.
+    error: Option<Error>,
+}
+
+/// A function found at file scope by `Parser::parse_function`.
+enum Function {
+    Definition(ast::Function),
+    Declaration(ast::FunctionDeclaration),
 }
 
 impl From<String> for Parser {
     fn from(value: String) -> Self {
-        let mut tokens: VecDeque<Token> = VecDeque::from_iter(
-            Token::lexer(&value).map(|token| token.expect("Should return token")),
-        );
-
-        let current_token = tokens.pop_front().unwrap();
-        let peek_token = tokens.pop_front().unwrap();
-
-        Self {
-            tokens,
-            current_token,
-            peek_token,
-        }
+        Parser::from_lexer(&mut Token::lexer(&value))
     }
 }
 
@@ -64,33 +70,181 @@ impl Parser {
     // token in the token stream.
     //
 
-    /// Returns a Parser given a lexer.
+    /// Returns a Parser over the given source code.
+    pub fn build(source: &str) -> Self {
+        Parser::from(source.to_string())
+    }
+
+    /// Returns a Parser given a lexer. Tokens are read up to the
+    /// first lexer error, which `to_ast_program` returns.
     pub fn from_lexer(lexer: &mut Lexer<Token>) -> Self {
-        let mut tokens: VecDeque<Token> =
-            VecDeque::from_iter(lexer.into_iter().map(|x| x.expect("Building token queue")));
+        let mut tokens = VecDeque::new();
+        let mut offsets = VecDeque::new();
+        let mut error = None;
+
+        while let Some(token) = lexer.next() {
+            match token {
+                Ok(token) => {
+                    tokens.push_back(token);
+                    offsets.push_back(lexer.span().start);
+                }
+                Err(()) => {
+                    error = Some(Self::lexer_error(lexer.slice()));
+                    break;
+                }
+            }
+        }
+
+        tokens.push_back(Token::Eof);
+        offsets.push_back(lexer.source().len());
 
-        let current_token = tokens.pop_front().unwrap();
-        let peek_token = tokens.pop_front().unwrap();
+        let current_token = tokens.pop_front().unwrap_or(Token::Eof);
+        let peek_token = tokens.pop_front().unwrap_or(Token::Eof);
+        let line_starts = std::iter::once(0)
+            .chain(
+                lexer
+                    .source()
+                    .match_indices('\n')
+                    .map(|(index, _)| index + 1),
+            )
+            .collect();
 
         Self {
             tokens,
             current_token,
             peek_token,
+            offsets,
+            line_starts,
+            annotate_lines: false,
+            error,
+        }
+    }
+
+    /// Precedes each block item with a [`ast::BlockItem::Line`]
+    /// holding the source line it starts on.
+    pub fn annotate_lines(mut self, annotate_lines: bool) -> Self {
+        self.annotate_lines = annotate_lines;
+        self
+    }
+
+    /// Returns the error for a slice the lexer rejected.
+    fn lexer_error(slice: &str) -> Error {
+        let digits = slice.strip_suffix(['u', 'U']).unwrap_or(slice);
+
+        if !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            Error::IntegerOutOfRange {
+                literal: slice.to_string(),
+            }
+        } else {
+            Error::InvalidToken {
+                slice: slice.to_string(),
+            }
         }
     }
 
     /// Generates and AST from the constructed parser.
     pub fn to_ast_program(&mut self) -> Result<ast::Program> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
         self.parse_program()
     }
 
-    /// Consumes the next token in token stream
+    /// Parses `source` as a single expression, which must span
+    /// all of it.
+    ///
+    /// ```
+    /// # use nous::parser::Parser;
+    /// # use nous::ast::{BinaryOperator, Expression};
+    /// let expression = Parser::parse_expression_only("1 + 2").expect("Should parse the sum");
+    ///
+    /// assert_eq!(
+    ///     expression,
+    ///     Expression::Binary(
+    ///         BinaryOperator::Add,
+    ///         Box::new(Expression::Constant(1)),
+    ///         Box::new(Expression::Constant(2)),
+    ///     )
+    /// );
+    /// assert!(Parser::parse_expression_only("1 + 2;").is_err());
+    /// ```
+    pub fn parse_expression_only(source: &str) -> Result<ast::Expression> {
+        let mut parser = Self::build(source);
+        if let Some(error) = parser.error.take() {
+            return Err(error);
+        }
+
+        let expression = parser.parse_expression(0)?;
+        // `parse_expression` leaves the current token on the last
+        // one of the expression.
+        parser.next_token();
+
+        if parser.current_token_is(&Token::Eof) {
+            Ok(expression)
+        } else {
+            Err(Error::UnexpectedToken {
+                message: Some("Within `parse_expression_only`".into()),
+                expected: Token::Eof,
+                found: parser.current_token.clone(),
+            })
+        }
+    }
+
+    /// Returns the token the parser is on, [`Token::Eof`] once
+    /// every token has been consumed.
+    pub fn current_token(&self) -> &Token {
+        &self.current_token
+    }
+
+    /// Consumes the next token in token stream, the parser stays
+    /// on [`Token::Eof`] once it reaches it.
     fn next_token(&mut self) {
-        self.current_token = self.peek_token.clone();
-        self.peek_token = self
-            .tokens
-            .pop_front()
-            .unwrap_or(self.current_token.clone());
+        // The offset of `Eof` stays, as `Eof` does.
+        if self.offsets.len() > 1 {
+            self.offsets.pop_front();
+        }
+        self.current_token = mem::replace(
+            &mut self.peek_token,
+            self.tokens.pop_front().unwrap_or(Token::Eof),
+        );
+    }
+
+    /// Returns the position within the token stream, which
+    /// changes whenever a token is consumed.
+    fn position(&self) -> (usize, Token, Token) {
+        (
+            self.tokens.len(),
+            self.current_token.clone(),
+            self.peek_token.clone(),
+        )
+    }
+
+    /// Returns the byte offset of the current token.
+    fn current_offset(&self) -> usize {
+        self.offsets.front().copied().unwrap_or_default()
+    }
+
+    /// Returns the line the current token is on, starting at 1.
+    fn current_line(&self) -> usize {
+        let offset = self.current_offset();
+
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+
+    /// Returns `error`, unless the tokens ran out before the
+    /// delimiter opened at `opening` was closed, which gets an
+    /// `Error::UnterminatedBlock` of its own.
+    fn unterminated(&self, delimiter: Token, opening: usize, error: Error) -> Error {
+        if !self.current_token_is(&Token::Eof) {
+            return error;
+        }
+
+        Error::UnterminatedBlock {
+            delimiter,
+            line: self.line_starts.partition_point(|&start| start <= opening),
+            span: (opening, 1).into(),
+        }
     }
 
     /// Compares current token with a given token
@@ -98,143 +252,461 @@ impl Parser {
         self.current_token == *token
     }
 
-    fn peek_token_is(&self, token: &Token) -> bool {
-        &self.peek_token == token
-    }
-
-    #[allow(dead_code)]
     fn next_token_is(&self, token: &Token) -> bool {
         self.peek_token == *token
     }
 
     /// Returns an AST Program or an Error string.
     ///
-    /// <program> ::== <function>
+    /// <program> ::== { <function> | <declaration> | <enum> }
     fn parse_program(&mut self) -> Result<ast::Program> {
-        let function = self.parse_function()?;
-        Ok(ast::Program(function))
+        let mut functions = Vec::new();
+        let mut globals = Vec::new();
+        let mut enumerations = Vec::new();
+        let mut declarations = Vec::new();
+
+        while !self.current_token_is(&Token::Eof) {
+            if self.current_token_is(&Token::Enum) {
+                enumerations.push(self.parse_enum()?);
+            } else if self.current_token_starts_function() {
+                match self.parse_function()? {
+                    Function::Definition(function) => functions.push(function),
+                    Function::Declaration(declaration) => declarations.push(declaration),
+                }
+
+                // `parse_function` leaves the current token on the
+                // closing brace of the function, or on the semicolon
+                // ending its declaration.
+                self.next_token();
+            } else {
+                // `parse_declaration` consumes the semicolon.
+                globals.extend(self.parse_declaration()?);
+            }
+        }
+
+        Ok(ast::Program(functions, globals, enumerations, declarations))
     }
 
-    /// Returns an ast::Function or an Error String.
+    /// Returns true if the tokens ahead read `int <identifier> (`
+    /// or `void <identifier> (`, which starts a function rather than
+    /// a variable declaration.
     ///
-    /// <function> ::== "int" <identifier> "(" "void" ")" "{" { <block-item> } "}"
-    fn parse_function(&mut self) -> Result<ast::Function> {
-        if self.current_token_is(&Token::Int) {
-            self.next_token();
+    /// The identifier is the peeked token, so the parenthesis is
+    /// the first one still in `tokens`.
+    fn current_token_starts_function(&self) -> bool {
+        matches!(self.current_token, Token::Int | Token::Void)
+            && matches!(self.peek_token, Token::Identifier(_))
+            && self.tokens.front() == Some(&Token::LParen)
+    }
 
-            let identifier = self.parse_identifier()?;
-            let expected_structure = vec![Token::LParen, Token::Void, Token::RParen, Token::LBrace];
+    /// Parses a function definition, or a declaration of a
+    /// function defined later on.
+    ///
+    /// <function> ::== ( "int" | "void" ) <identifier> "(" <params> ")" ( "{" { <block-item> } "}" | ";" )
+    fn parse_function(&mut self) -> Result<Function> {
+        if !matches!(self.current_token, Token::Int | Token::Void) {
+            return Err(Error::UnexpectedToken {
+                expected: Token::Int,
+                found: self.current_token.clone(),
+                message: Some("Within `parse_function`".into()),
+            });
+        }
 
-            // Check if incoming token stream matches the expected_structure
-            for token in expected_structure {
-                if !self.current_token_is(&token) {
-                    return Err(Error::UnexpectedToken {
-                        expected: token.clone(),
-                        found: self.current_token.clone(),
-                        message: Some("within `parse_function`".into()),
-                    });
-                } else {
-                    self.next_token();
-                }
-            }
+        let return_type = match self.current_token {
+            Token::Void => ast::Type::Void,
+            _ => ast::Type::Int,
+        };
+        self.next_token();
+        let name = self.parse_identifier()?;
 
-            let mut function_body: BlockItems = Vec::new();
+        self.expect(&Token::LParen)?;
+        let params = self.parse_params()?;
+        self.expect(&Token::RParen)?;
 
-            while !self.peek_token_is(&Token::RBrace) {
-                // parse_block_item() advances the token stream
-                function_body.push(self.parse_block_item()?);
-            }
+        if self.current_token_is(&Token::Semicolon) {
+            return Ok(Function::Declaration(ast::FunctionDeclaration {
+                name,
+                return_type,
+                params: params.into_iter().map(|(_, ty)| ty).collect(),
+            }));
+        }
 
-            if self.current_token_is(&Token::RBrace) {
-                // self.next_token();
-                Ok(ast::Function {
-                    name: identifier,
-                    body: function_body,
-                })
-            } else {
-                Err(Error::UnexpectedToken {
-                    expected: Token::RBrace,
-                    found: self.current_token.clone(),
-                    message: Some("Within `parse_function`".into()),
-                })
-            }
+        let opening = self.current_offset();
+        self.expect(&Token::LBrace)?;
+        let params = params
+            .into_iter()
+            .map(|(name, ty)| match name {
+                Some(name) => Ok(ast::Declaration {
+                    name,
+                    ty,
+                    initializer: None,
+                }),
+                None => Err(Error::Unsupported {
+                    feature: "unnamed parameters in a function definition",
+                }),
+            })
+            .collect::<Result<_>>()?;
+        let body = self.parse_block_items(opening)?;
+
+        if self.current_token_is(&Token::RBrace) {
+            Ok(Function::Definition(ast::Function {
+                name,
+                return_type,
+                params,
+                body,
+            }))
         } else {
             Err(Error::UnexpectedToken {
-                expected: Token::Int,
+                expected: Token::RBrace,
                 found: self.current_token.clone(),
                 message: Some("Within `parse_function`".into()),
             })
         }
     }
 
-    /// <block-item> ::== <statement> | <declaration>
-    fn parse_block_item(&mut self) -> Result<ast::BlockItem> {
+    /// Parses the parameters of a function, leaving the current
+    /// token on the closing parenthesis. Their names are optional
+    /// until the function turns out to be defined.
+    ///
+    /// <params> ::== "void" | <type> [ <identifier> ] { "," <type> [ <identifier> ] }
+    fn parse_params(&mut self) -> Result<Vec<(Option<Identifier>, ast::Type)>> {
+        if self.current_token_is(&Token::Void) && self.next_token_is(&Token::RParen) {
+            self.next_token();
+            return Ok(Vec::new());
+        }
+        if !self.current_token_is_type_specifier() {
+            return Err(Error::UnexpectedToken {
+                expected: Token::Void,
+                found: self.current_token.clone(),
+                message: Some("within `parse_function`".into()),
+            });
+        }
+
+        let mut params = Vec::new();
+        loop {
+            let ty = self.parse_type()?;
+            let name = match self.current_token {
+                Token::Identifier(_) => Some(self.parse_identifier()?),
+                _ => None,
+            };
+            params.push((name, ty));
+
+            if !self.current_token_is(&Token::Comma) {
+                return Ok(params);
+            }
+            self.next_token();
+        }
+    }
+
+    /// Parses block items until the closing brace, leaving
+    /// the current token on it. The opening brace, found at the
+    /// `opening` offset, was already consumed.
+    ///
+    /// Blocks nested right within a block are parsed through an
+    /// explicit stack rather than recursion, so deeply nested
+    /// blocks can't overflow the stack.
+    ///
+    /// { <block-item> }
+    fn parse_block_items(&mut self, opening: usize) -> Result<BlockItems> {
+        let mut blocks: Vec<BlockItems> = vec![Vec::new()];
+        let mut openings = vec![opening];
+
+        loop {
+            let position = self.position();
+
+            match self.current_token {
+                Token::RBrace => {
+                    let block_items = blocks.pop().unwrap_or_default();
+                    openings.pop();
+
+                    match blocks.last_mut() {
+                        Some(outer) => {
+                            // Consumes the closing brace of the inner block.
+                            self.next_token();
+                            outer.push(ast::BlockItem::S(ast::Statement::Compound(block_items)));
+                        }
+                        None => return Ok(block_items),
+                    }
+                    continue;
+                }
+                Token::LBrace => {
+                    openings.push(self.current_offset());
+                    self.next_token();
+                    blocks.push(Vec::new());
+                }
+                Token::Eof => {
+                    let error = Error::UnexpectedToken {
+                        expected: Token::RBrace,
+                        found: Token::Eof,
+                        message: Some("Within `parse_block_items`".into()),
+                    };
+                    // The innermost block is the first one left open.
+                    let opening = openings.last().copied().unwrap_or(opening);
+                    return Err(self.unterminated(Token::LBrace, opening, error));
+                }
+                _ => {
+                    let line = self.current_line();
+                    let items = self.parse_block_item()?;
+                    if let Some(block_items) = blocks.last_mut() {
+                        if self.annotate_lines {
+                            block_items.push(ast::BlockItem::Line(line));
+                        }
+                        block_items.extend(items);
+                    }
+                }
+            }
+
+            // Opening a block or parsing a block item advances the
+            // token stream, if it doesn't we would loop forever.
+            if self.position() == position {
+                return Err(Error::ParserStuck {
+                    found: self.current_token.clone(),
+                });
+            }
+        }
+    }
+
+    /// Parses a block item, a declaration of several variables
+    /// giving an item for each of them.
+    ///
+    /// <block-item> ::== <statement> | <declaration> | <enum>
+    fn parse_block_item(&mut self) -> Result<BlockItems> {
+        if self.current_token_is(&Token::Enum) {
+            return Ok(vec![ast::BlockItem::E(self.parse_enum()?)]);
+        }
+
         // We need a way to tell wether the current block
         // item is a statement or a declaration.
         // To do this, we look at the first token; if it is
-        // `Token::Int`, then it's a declaration, otherwise
+        // a type specifier, then it's a declaration, otherwise
         // it's a statement.
-        if self.current_token_is(&Token::Int) {
+        if self.current_token_is_type_specifier() {
             // This is a declaration
-            Ok(ast::BlockItem::D(self.parse_declaration()?))
+            Ok(self
+                .parse_declaration()?
+                .into_iter()
+                .map(ast::BlockItem::D)
+                .collect())
         } else {
             // This is a statement
-            Ok(ast::BlockItem::S(self.parse_statement()?))
+            Ok(vec![ast::BlockItem::S(self.parse_statement()?)])
         }
     }
 
-    /// <declaration> ::== "int" <identifier> [ "=" <exp> ] ";"
-    fn parse_declaration(&mut self) -> Result<ast::Declaration> {
-        // This should check whether the identifier in the grammar
-        // rule is followed by an `=` token, which means the
-        // initializer is present, or a `;` token, which means
-        // the initilizar is absent.
-        if let Token::Int = self.current_token {
+    /// Returns true if the current token starts a type.
+    fn current_token_is_type_specifier(&self) -> bool {
+        matches!(self.current_token, Token::Int | Token::Unsigned)
+    }
+
+    /// Parses the type specifiers starting a declaration, leaving
+    /// the current token after them.
+    ///
+    /// <type> ::== "int" | "unsigned" | "unsigned" "int" | "int" "unsigned"
+    fn parse_type(&mut self) -> Result<ast::Type> {
+        let mut specifiers = Vec::new();
+
+        while self.current_token_is_type_specifier() {
+            specifiers.push(self.current_token.clone());
             self.next_token();
-            // We must have an identifier now.
-            let name = self.parse_identifier()?;
+        }
 
-            // we check if the initializer is present
-            if let Token::Assign = self.current_token {
-                self.next_token();
-                // We now parse an expression.
-                // FIX: Should this be zero?
-                let initializer = Some(self.parse_expression(0)?);
+        match specifiers.as_slice() {
+            [Token::Int] => Ok(ast::Type::Int),
+            [Token::Unsigned] | [Token::Unsigned, Token::Int] | [Token::Int, Token::Unsigned] => {
+                Ok(ast::Type::UnsignedInt)
+            }
+            _ => Err(Error::InvalidType { specifiers }),
+        }
+    }
+
+    /// Parses a declaration of one or more variables sharing the
+    /// same type specifiers, consuming the semicolon. Each
+    /// declarator becomes its own `ast::Declaration`, in order.
+    ///
+    /// <declaration> ::== <type> <declarator> { "," <declarator> } ";"
+    fn parse_declaration(&mut self) -> Result<Vec<ast::Declaration>> {
+        if !self.current_token_is_type_specifier() {
+            return Err(Error::UnexpectedToken {
+                message: Some("Within `parse_declaration`".into()),
+                expected: Token::Int,
+                found: self.current_token.clone(),
+            });
+        }
+
+        let ty = self.parse_type()?;
+        let mut declarations = vec![self.parse_declarator(ty)?];
+        while self.current_token_is(&Token::Comma) {
+            self.next_token();
+            declarations.push(self.parse_declarator(ty)?);
+        }
+
+        if self.current_token_is(&Token::Semicolon) {
+            self.next_token();
+            Ok(declarations)
+        } else {
+            Err(Error::UnexpectedToken {
+                message: Some("Within `parse_declaration`".into()),
+                expected: Token::Semicolon,
+                found: self.current_token.clone(),
+            })
+        }
+    }
+
+    /// Parses a single variable of a declaration, leaving the
+    /// current token past it.
+    ///
+    /// <declarator> ::== [ "*" ] <identifier> [ "[" <exp> "]" ] [ "=" <exp> ]
+    fn parse_declarator(&mut self, mut ty: ast::Type) -> Result<ast::Declaration> {
+        if self.current_token_is(&Token::Mul) {
+            ty = self.parse_pointer_declarator(ty)?;
+        }
+        // We must have an identifier now.
+        let name = self.parse_identifier()?;
+
+        if self.current_token_is(&Token::LBracket) {
+            ty = self.parse_array_declarator(ty)?;
+        }
+
+        let initializer = if self.current_token_is(&Token::Assign) {
+            self.next_token();
+            // We now parse an expression, stopping before any
+            // comma since it would separate declarators.
+            let initializer = self.parse_expression(Token::Assign.precedence()?)?;
+            self.next_token();
+            Some(initializer)
+        } else {
+            None
+        };
+
+        Ok(ast::Declaration {
+            name,
+            ty,
+            initializer,
+        })
+    }
+
+    /// Parses an enumeration, consuming the semicolon. Values not
+    /// given follow the previous one, starting at 0.
+    ///
+    /// <enum> ::== "enum" [ <identifier> ] "{" <enumerator> { "," <enumerator> } [ "," ] "}" ";"
+    /// <enumerator> ::== <identifier> [ "=" [ "-" ] <int> ]
+    fn parse_enum(&mut self) -> Result<ast::EnumDeclaration> {
+        self.next_token();
+        let tag = match self.current_token {
+            Token::Identifier(_) => Some(self.parse_identifier()?),
+            _ => None,
+        };
+        if !self.current_token_is(&Token::LBrace) {
+            return Err(Error::Unsupported {
+                feature: "variables of enumerated types",
+            });
+        }
+        self.next_token();
+
+        let mut enumerators = Vec::new();
+        let mut value = 0;
+        loop {
+            let name = self.parse_identifier()?;
+            if self.current_token_is(&Token::Assign) {
                 self.next_token();
-                if self.current_token_is(&Token::Semicolon) {
-                    self.next_token();
-                    return Ok(ast::Declaration { name, initializer });
-                } else {
+                value = self.parse_constant_expression()?;
+            }
+            if i32::try_from(value).is_err() {
+                return Err(Error::IntegerOutOfRange {
+                    literal: value.to_string(),
+                });
+            }
+            enumerators.push((name, value));
+            value += 1;
+
+            match self.current_token {
+                Token::Comma => self.next_token(),
+                Token::RBrace => {}
+                _ => {
                     return Err(Error::UnexpectedToken {
-                        message: Some("Within `parse_declaration`".into()),
-                        expected: Token::Semicolon,
+                        message: Some("Within `parse_enum`".into()),
+                        expected: Token::RBrace,
                         found: self.current_token.clone(),
-                    });
+                    })
                 }
             }
+            if self.current_token_is(&Token::RBrace) {
+                break;
+            }
+        }
 
-            // The initializer is absent
-            // We check if the ";" is present
-            if self.current_token_is(&Token::Semicolon) {
-                self.next_token();
-                Ok(ast::Declaration {
-                    name,
-                    initializer: None,
-                })
-            } else {
-                Err(Error::UnexpectedToken {
-                    message: Some("Within `parse_declaration`".into()),
-                    expected: Token::Semicolon,
-                    found: self.current_token.clone(),
+        self.next_token();
+        self.expect(&Token::Semicolon)?;
+
+        Ok(ast::EnumDeclaration { tag, enumerators })
+    }
+
+    /// Parses and evaluates an integer constant expression, leaving
+    /// the current token past it. A comma ends the expression, as
+    /// it would separate enumerators.
+    fn parse_constant_expression(&mut self) -> Result<i64> {
+        let expression = self.parse_expression(Token::Assign.precedence()?)?;
+        self.next_token();
+
+        eval_const(&expression)
+    }
+
+    /// Parses the `*` of a pointer to `pointee`.
+    fn parse_pointer_declarator(&mut self, pointee: ast::Type) -> Result<ast::Type> {
+        self.next_token();
+
+        if self.current_token_is(&Token::Mul) {
+            return Err(Error::Unsupported {
+                feature: "pointers to pointers",
+            });
+        }
+        if pointee != ast::Type::Int {
+            return Err(Error::Unsupported {
+                feature: "pointers to unsigned int",
+            });
+        }
+
+        Ok(ast::Type::Pointer)
+    }
+
+    /// Parses the length of an array of `element` starting on the
+    /// opening bracket, and leaves the current token past the
+    /// closing one.
+    fn parse_array_declarator(&mut self, element: ast::Type) -> Result<ast::Type> {
+        self.next_token();
+        if element == ast::Type::Pointer {
+            return Err(Error::Unsupported {
+                feature: "arrays of pointers",
+            });
+        }
+        // The length of an array must be positive.
+        let length = match self.parse_constant_expression()? {
+            length if length > 0 => length as usize,
+            length => {
+                return Err(Error::IntegerOutOfRange {
+                    literal: length.to_string(),
                 })
             }
-        } else {
-            Err(Error::UnexpectedToken {
-                message: Some("Within `parse_declaration`".into()),
-                expected: Token::Int,
-                found: self.current_token.clone(),
-            })
+        };
+        self.expect(&Token::RBracket)?;
+
+        if self.current_token_is(&Token::LBracket) {
+            return Err(Error::Unsupported {
+                feature: "multidimensional arrays",
+            });
         }
+        if element != ast::Type::Int {
+            return Err(Error::Unsupported {
+                feature: "arrays of unsigned int",
+            });
+        }
+        if self.current_token_is(&Token::Assign) {
+            return Err(Error::Unsupported {
+                feature: "array initializers",
+            });
+        }
+
+        Ok(ast::Type::Array(length))
     }
 
     /// Matches on the current token, if it is
@@ -267,12 +739,15 @@ impl Parser {
     /// binary operation
     fn parse_binaryop(&mut self) -> Result<ast::BinaryOperator> {
         match self.current_token {
-            Token::Assign => Ok(ast::BinaryOperator::Equal),
+            // Assignments, conditionals and commas are handled
+            // structurally by `parse_expression`, see `Token::Assign`.
             Token::Add => Ok(ast::BinaryOperator::Add),
             Token::Negation => Ok(ast::BinaryOperator::Subtract),
             Token::Mul => Ok(ast::BinaryOperator::Multiply),
             Token::Div => Ok(ast::BinaryOperator::Divide),
             Token::Remainder => Ok(ast::BinaryOperator::Remainder),
+            Token::ShiftLeft => Ok(ast::BinaryOperator::ShiftLeft),
+            Token::ShiftRight => Ok(ast::BinaryOperator::ShiftRight),
             Token::LessThan => Ok(ast::BinaryOperator::LessThan),
             Token::LessThanOrEq => Ok(ast::BinaryOperator::LessOrEqual),
             Token::GreaterThan => Ok(ast::BinaryOperator::GreaterThan),
@@ -307,6 +782,10 @@ impl Parser {
     /// Parses the grammar:
     ///
     /// <exp> ::== <factor> | <exp> <binop> <exp> | <exp> "?" <exp> ":" <exp>
+    ///
+    /// A `-` is only read as `Subtract` after a complete operand,
+    /// every operand starts with `parse_factor` which reads it as
+    /// `Negate`, so `1 - -2` parses as expected.
     fn parse_expression(&mut self, min_precedence: usize) -> Result<ast::Expression> {
         let mut left = self.parse_factor()?;
 
@@ -319,6 +798,12 @@ impl Parser {
                 self.next_token();
                 let right = self.parse_expression(next_token.precedence()?)?;
                 left = ast::Expression::Assignment(Box::new(left), Box::new(right));
+            } else if matches!(next_token, Token::Comma) {
+                self.next_token();
+                self.next_token();
+                // The comma operator is left associative.
+                let right = self.parse_expression(next_token.precedence()? + 1)?;
+                left = ast::Expression::Comma(Box::new(left), Box::new(right));
             } else if matches!(next_token, Token::QuestionMark) {
                 self.next_token();
                 let middle = self.parse_conditional_middle()?;
@@ -368,13 +853,53 @@ impl Parser {
         }
     }
 
-    /// <factor> ::== <int> \ <identifier> \ <unop> <factor> \ "(" <exp> ")"
+    /// <factor> ::== <primary> { "[" <exp> "]" }
+    ///
+    /// Subscripts bind tighter than any prefix operator, so
+    /// `-a[1]` negates an element.
     fn parse_factor(&mut self) -> Result<ast::Expression> {
+        let mut factor = self.parse_primary()?;
+
+        while self.next_token_is(&Token::LBracket) {
+            self.next_token();
+            self.next_token();
+            let index = self.parse_expression(0)?;
+            self.next_token();
+
+            if !self.current_token_is(&Token::RBracket) {
+                return Err(Error::MalformedFactor {
+                    missing: Some(Token::RBracket),
+                    found: self.current_token.clone(),
+                });
+            }
+            factor = ast::Expression::Subscript(Box::new(factor), Box::new(index));
+        }
+
+        Ok(factor)
+    }
+
+    /// <primary> ::== <int> \ <identifier> \ <unop> <factor> \ "(" <exp> ")"
+    ///     \ "sizeof" "(" <type> ")" \ "sizeof" <factor> \ "&" <factor> \ "*" <factor>
+    fn parse_primary(&mut self) -> Result<ast::Expression> {
         match &self.current_token {
             // <int>
             Token::Constant(i) => Ok(ast::Expression::Constant(*i)),
+            Token::UnsignedConstant(i) => Ok(ast::Expression::UnsignedConstant(*i)),
+            Token::Float(_) => Err(Error::Unsupported {
+                feature: "floating point",
+            }),
+            // <identifier> [ "(" [ <argument-list> ] ")" ]
             Token::Identifier(identifier) => {
-                Ok(ast::Expression::Var(Identifier(identifier.clone())))
+                let name = Identifier(identifier.clone());
+
+                if self.next_token_is(&Token::LParen) {
+                    self.next_token();
+                    let arguments = self.parse_argument_list()?;
+
+                    Ok(ast::Expression::FunctionCall { name, arguments })
+                } else {
+                    Ok(ast::Expression::Var(name))
+                }
             }
             // If token is "~" or "-"
             // <unop> <factor>
@@ -384,18 +909,53 @@ impl Parser {
 
                 Ok(ast::Expression::Unary(operator, Box::new(inner_expression)))
             }
+            Token::Ampersand => {
+                self.next_token();
+                Ok(ast::Expression::AddrOf(Box::new(self.parse_factor()?)))
+            }
+            Token::Mul => {
+                self.next_token();
+                Ok(ast::Expression::Deref(Box::new(self.parse_factor()?)))
+            }
+            // "sizeof" "(" <type> ")" | "sizeof" <factor>
+            Token::Sizeof => {
+                self.next_token();
+
+                if self.current_token_is(&Token::LParen)
+                    && matches!(self.peek_token, Token::Int | Token::Unsigned)
+                {
+                    self.next_token();
+                    let ty = self.parse_type()?;
+
+                    if self.current_token_is(&Token::RParen) {
+                        Ok(ast::Expression::SizeOfType(ty))
+                    } else {
+                        Err(Error::MalformedFactor {
+                            missing: Some(Token::RParen),
+                            found: self.current_token.clone(),
+                        })
+                    }
+                } else {
+                    // Parenthesized operands are parsed as factors.
+                    let operand = self.parse_factor()?;
+
+                    Ok(ast::Expression::SizeOf(Box::new(operand)))
+                }
+            }
             // "(" <exp> ")"
             Token::LParen => {
+                let opening = self.current_offset();
                 self.next_token();
                 let inner_expression = self.parse_expression(0);
                 self.next_token();
                 if self.current_token_is(&Token::RParen) {
                     inner_expression
                 } else {
-                    Err(Error::MalformedFactor {
+                    let error = Error::MalformedFactor {
                         missing: Some(Token::RParen),
                         found: self.current_token.clone(),
-                    })
+                    };
+                    Err(self.unterminated(Token::LParen, opening, error))
                 }
             }
             _ => Err(Error::MalformedFactor {
@@ -410,15 +970,49 @@ impl Parser {
     /// <statement> ::== "return" <exp> ";"
     ///             | <exp> ";"
     ///             | "if" "(" <exp> ")" <statement> ["else" <statement>]
+    ///             | "{" { <block-item> } "}"
+    ///             | "break" ";"
+    ///             | "continue" ";"
+    ///             | "while" "(" <exp> ")" <statement>
+    ///             | "do" <statement> "while" "(" <exp> ")" ";"
+    ///             | "for" "(" <for-init> [ <exp> ] ";" [ <exp> ] ")" <statement>
     ///             | ";"
     fn parse_statement(&mut self) -> Result<ast::Statement> {
         match &self.current_token {
-            Token::Return => {
+            Token::Break => {
+                self.next_token();
+                self.expect(&Token::Semicolon)?;
+                Ok(ast::Statement::Break(None))
+            }
+            Token::Continue => {
+                self.next_token();
+                self.expect(&Token::Semicolon)?;
+                Ok(ast::Statement::Continue(None))
+            }
+            Token::While => self.parse_while(),
+            Token::Do => self.parse_do_while(),
+            Token::For => self.parse_for(),
+            Token::LBrace => {
+                let opening = self.current_offset();
+                self.next_token();
+                let block_items = self.parse_block_items(opening)?;
+                // Consumes the closing brace.
                 self.next_token();
 
-                let expression = self.parse_expression(0)?;
+                Ok(ast::Statement::Compound(block_items))
+            }
+            Token::Return => {
                 self.next_token();
 
+                // A bare `return;` returns from a `void` function.
+                let expression = if self.current_token_is(&Token::Semicolon) {
+                    None
+                } else {
+                    let expression = self.parse_expression(0)?;
+                    self.next_token();
+                    Some(expression)
+                };
+
                 if self.current_token_is(&Token::Semicolon) {
                     self.next_token();
                     Ok(ast::Statement::Return(expression))
@@ -430,44 +1024,28 @@ impl Parser {
                     })
                 }
             }
-            Token::Semicolon => Ok(ast::Statement::Null),
+            Token::Semicolon => {
+                self.next_token();
+                Ok(ast::Statement::Null)
+            }
             Token::If => {
                 self.next_token();
+                let condition = self.parse_condition()?;
+                let then = Box::new(self.parse_statement()?);
 
-                if self.current_token_is(&Token::LParen) {
-                    let condition = self.parse_expression(0)?;
-
-                    if self.current_token_is(&Token::RParen) {
+                let else_statement: Option<Box<ast::Statement>> =
+                    if self.current_token_is(&Token::Else) {
                         self.next_token();
-                        let then = Box::new(self.parse_statement()?);
-
-                        let else_statement: Option<Box<ast::Statement>> =
-                            if self.current_token_is(&Token::Else) {
-                                self.next_token();
-                                Some(Box::new(self.parse_statement()?))
-                            } else {
-                                None
-                            };
-
-                        Ok(ast::Statement::If {
-                            condition,
-                            then,
-                            else_statement,
-                        })
+                        Some(Box::new(self.parse_statement()?))
                     } else {
-                        Err(Error::UnexpectedToken {
-                            message: Some("Within `parse_statement`".into()),
-                            expected: Token::RParen,
-                            found: self.current_token.clone(),
-                        })
-                    }
-                } else {
-                    Err(Error::UnexpectedToken {
-                        message: Some("Within `parse_statement`".into()),
-                        expected: Token::LParen,
-                        found: self.current_token.clone(),
-                    })
-                }
+                        None
+                    };
+
+                Ok(ast::Statement::If {
+                    condition,
+                    then,
+                    else_statement,
+                })
             }
             _ => {
                 let expression = self.parse_expression(0)?;
@@ -488,26 +1066,159 @@ impl Parser {
         }
     }
 
+    /// "while" "(" <exp> ")" <statement>
+    fn parse_while(&mut self) -> Result<ast::Statement> {
+        self.next_token();
+        let condition = self.parse_condition()?;
+        let body = Box::new(self.parse_statement()?);
+
+        Ok(ast::Statement::While {
+            condition,
+            body,
+            label: None,
+        })
+    }
+
+    /// "do" <statement> "while" "(" <exp> ")" ";"
+    fn parse_do_while(&mut self) -> Result<ast::Statement> {
+        self.next_token();
+        let body = Box::new(self.parse_statement()?);
+        self.expect(&Token::While)?;
+        let condition = self.parse_condition()?;
+        self.expect(&Token::Semicolon)?;
+
+        Ok(ast::Statement::DoWhile {
+            body,
+            condition,
+            label: None,
+        })
+    }
+
+    /// "for" "(" <for-init> [ <exp> ] ";" [ <exp> ] ")" <statement>
+    ///
+    /// Each clause consumes its delimiter, so absent clauses
+    /// leave the token stream in the same state as present ones.
+    fn parse_for(&mut self) -> Result<ast::Statement> {
+        self.next_token();
+        self.expect(&Token::LParen)?;
+
+        let init = self.parse_for_init()?;
+        let condition = self.parse_optional_expression(&Token::Semicolon)?;
+        let post = self.parse_optional_expression(&Token::RParen)?;
+        let body = Box::new(self.parse_statement()?);
+
+        Ok(ast::Statement::For {
+            init,
+            condition,
+            post,
+            body,
+            label: None,
+        })
+    }
+
+    /// Parses the arguments of a function call, starting on the
+    /// opening parenthesis and leaving the current token on the
+    /// closing one.
+    ///
+    /// <argument-list> ::== <exp> { "," <exp> }
+    fn parse_argument_list(&mut self) -> Result<Vec<ast::Expression>> {
+        let mut arguments = Vec::new();
+        let opening = self.current_offset();
+
+        if self.next_token_is(&Token::RParen) {
+            self.next_token();
+            return Ok(arguments);
+        }
+
+        loop {
+            self.next_token();
+            // Commas separate the arguments rather than
+            // being parsed as comma expressions.
+            arguments.push(self.parse_expression(Token::Assign.precedence()?)?);
+            self.next_token();
+
+            match self.current_token {
+                Token::Comma => continue,
+                Token::RParen => return Ok(arguments),
+                _ => {
+                    let error = Error::UnexpectedToken {
+                        message: Some("Within `parse_argument_list`".into()),
+                        expected: Token::RParen,
+                        found: self.current_token.clone(),
+                    };
+                    return Err(self.unterminated(Token::LParen, opening, error));
+                }
+            }
+        }
+    }
+
+    /// <for-init> ::== <declaration> | [ <exp> ] ";"
+    fn parse_for_init(&mut self) -> Result<ast::ForInit> {
+        if self.current_token_is_type_specifier() {
+            Ok(ast::ForInit::InitDecl(self.parse_declaration()?))
+        } else {
+            Ok(ast::ForInit::InitExp(
+                self.parse_optional_expression(&Token::Semicolon)?,
+            ))
+        }
+    }
+
+    /// Parses a parenthesized condition, leaving the current
+    /// token after the closing parenthesis.
+    ///
+    /// "(" <exp> ")"
+    fn parse_condition(&mut self) -> Result<ast::Expression> {
+        let opening = self.current_offset();
+        self.expect(&Token::LParen)?;
+        // The condition is parsed on its own rather than as a
+        // parenthesized factor, so the statement following it
+        // can't be read as the rest of the expression.
+        let condition = self.parse_expression(0)?;
+        self.next_token();
+        self.expect(&Token::RParen)
+            .map_err(|error| self.unterminated(Token::LParen, opening, error))?;
+
+        Ok(condition)
+    }
+
+    /// Parses an expression if the current token isn't `end`,
+    /// then consumes `end`.
+    ///
+    /// [ <exp> ] end
+    fn parse_optional_expression(&mut self, end: &Token) -> Result<Option<ast::Expression>> {
+        if self.current_token_is(end) {
+            self.next_token();
+            return Ok(None);
+        }
+
+        let expression = self.parse_expression(0)?;
+        self.next_token();
+        self.expect(end)?;
+
+        Ok(Some(expression))
+    }
+
+    /// Checks that the current token is `token`, then
+    /// advances the token stream.
+    fn expect(&mut self, token: &Token) -> Result<()> {
+        if self.current_token_is(token) {
+            self.next_token();
+            Ok(())
+        } else {
+            Err(Error::UnexpectedToken {
+                message: Some("Within `expect`".into()),
+                expected: token.clone(),
+                found: self.current_token.clone(),
+            })
+        }
+    }
+
     /// Returns true if the current token is a
     /// binary operator
     fn is_binary_operator(&self, token: &Token) -> bool {
-        matches!(
-            token,
-            Token::Add
-                | Token::Mul
-                | Token::Div
-                | Token::Negation
-                | Token::Remainder
-                | Token::And
-                | Token::Or
-                | Token::EqualTo
-                | Token::NotEqualTo
-                | Token::LessThan
-                | Token::LessThanOrEq
-                | Token::GreaterThan
-                | Token::GreaterThanOrEq
-                | Token::Assign
-                | Token::QuestionMark // This is a ternary op.
-        )
+        // `?` starts the ternary conditional operator.
+        PRECEDENCE_TABLE
+            .iter()
+            .any(|(operator, _)| operator == token)
     }
 }
@@ -0,0 +1,237 @@
+use std::{cmp::Reverse, collections::HashMap, ops::RangeInclusive};
+
+use crate::{
+    assembly::{Instruction, Instructions, Operand, Reg},
+    ast::Identifier,
+};
+
+/// Registers pseudo registers can be assigned to. `R10` and `R11`
+/// are left to the passes fixing up invalid operands, and `AX`
+/// to return values and divisions.
+pub const ALLOCATABLE_REGISTERS: &[Reg] = &[Reg::CX, Reg::SI, Reg::DI, Reg::R8, Reg::R9, Reg::DX];
+
+/// Registers a call may overwrite.
+const CALLER_SAVED_REGISTERS: &[Reg] = &[
+    Reg::AX,
+    Reg::CX,
+    Reg::DX,
+    Reg::DI,
+    Reg::SI,
+    Reg::R8,
+    Reg::R9,
+    Reg::R10,
+    Reg::R11,
+];
+
+/// Assigns the pseudo registers of a function to hardware
+/// registers, leaving the ones it can't assign to
+/// `AssemblyPass::replace_pseudo_registers`, which spills them
+/// to the stack.
+///
+/// The live range of a pseudo register is approximated by the
+/// instructions between its first and last occurrences, widened
+/// to cover every loop it overlaps, that is, every stretch
+/// between a label and a jump back to it. Pseudo registers are
+/// considered from the most used one, and each gets the first
+/// register of [`ALLOCATABLE_REGISTERS`] that no instruction nor
+/// other pseudo register uses within its range. Arrays, whose
+/// address is loaded by `lea`, always stay in memory.
+///
+/// Every allocatable register is caller saved, so the registers
+/// of pseudo registers living across a call are pushed right
+/// before it and popped right after it. Calls passing arguments
+/// on the stack would find them past the saved registers, so
+/// pseudo registers living across those are left to the stack.
+pub fn allocate_registers(instructions: &Instructions) -> Instructions {
+    let uses = register_uses(instructions);
+    let arrays: Vec<&Operand> = instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::Lea { src, .. } => Some(src),
+            _ => None,
+        })
+        .collect();
+    let mut candidates = live_ranges(instructions);
+    candidates.retain(|(name, _, _)| !arrays.contains(&&Operand::Pseudo(name.clone())));
+    // Sorting by name too keeps the allocation deterministic.
+    candidates
+        .sort_by_key(|(name, range, count)| (Reverse(*count), *range.start(), name.0.clone()));
+
+    let mut assigned: HashMap<Identifier, Reg> = HashMap::new();
+    let mut taken: HashMap<Reg, Vec<RangeInclusive<usize>>> = HashMap::new();
+
+    for (name, range, _) in candidates {
+        let free = ALLOCATABLE_REGISTERS.iter().find(|reg| {
+            let used = uses
+                .get(reg)
+                .is_some_and(|positions| positions.iter().any(|p| range.contains(p)));
+            let overlaps = taken.get(reg).is_some_and(|ranges| {
+                ranges
+                    .iter()
+                    .any(|other| other.start() <= range.end() && range.start() <= other.end())
+            });
+
+            !used && !overlaps
+        });
+
+        if let Some(reg) = free {
+            taken.entry(reg.clone()).or_default().push(range);
+            assigned.insert(name, reg.clone());
+        }
+    }
+
+    let mut new_instructions = Vec::new();
+    for (position, instruction) in instructions.iter().enumerate() {
+        let mut instruction = instruction.clone();
+        for operand in instruction.operands_mut() {
+            if let Operand::Pseudo(name) = operand {
+                if let Some(reg) = assigned.get(name) {
+                    *operand = Operand::Register(reg.clone());
+                }
+            }
+        }
+
+        if !matches!(instruction, Instruction::Call { .. }) {
+            new_instructions.push(instruction);
+            continue;
+        }
+
+        // Registers are saved in the order they are allocated in,
+        // so a given call always preserves them the same way.
+        let saved: Vec<Reg> = ALLOCATABLE_REGISTERS
+            .iter()
+            .filter(|reg| {
+                taken.get(reg).is_some_and(|ranges| {
+                    ranges
+                        .iter()
+                        .any(|range| *range.start() < position && position < *range.end())
+                })
+            })
+            .cloned()
+            .collect();
+        // Keeps the stack 16 byte aligned at the call.
+        let padding = if saved.len() % 2 == 1 { 8 } else { 0 };
+
+        if padding != 0 {
+            new_instructions.push(Instruction::AllocateStack(padding));
+        }
+        for reg in &saved {
+            new_instructions.push(Instruction::Push(Operand::Register(reg.clone())));
+        }
+        new_instructions.push(instruction);
+        for reg in saved.iter().rev() {
+            new_instructions.push(Instruction::Pop(Operand::Register(reg.clone())));
+        }
+        if padding != 0 {
+            new_instructions.push(Instruction::DeallocateStack(padding));
+        }
+    }
+
+    new_instructions
+}
+
+/// Returns every pseudo register along with its live range and
+/// number of occurrences.
+fn live_ranges(instructions: &Instructions) -> Vec<(Identifier, RangeInclusive<usize>, usize)> {
+    let mut ranges: HashMap<Identifier, (usize, usize, usize)> = HashMap::new();
+
+    for (position, instruction) in instructions.iter().enumerate() {
+        for operand in instruction.operands() {
+            if let Operand::Pseudo(name) = operand {
+                let (_, end, count) = ranges
+                    .entry(name.clone())
+                    .or_insert((position, position, 0));
+                *end = position;
+                *count += 1;
+            }
+        }
+    }
+
+    let labels: HashMap<&Identifier, usize> = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(position, instruction)| match instruction {
+            Instruction::Label(label) => Some((label, position)),
+            _ => None,
+        })
+        .collect();
+    let loops: Vec<(usize, usize)> = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(position, instruction)| match instruction {
+            Instruction::Jmp(label) | Instruction::JumpCC(_, label) => labels
+                .get(label)
+                .filter(|&&start| start <= position)
+                .map(|&start| (start, position)),
+            _ => None,
+        })
+        .collect();
+
+    // Widening a range over a loop can make it overlap an
+    // enclosing one.
+    for (start, end, _) in ranges.values_mut() {
+        loop {
+            let (old_start, old_end) = (*start, *end);
+
+            for &(loop_start, loop_end) in &loops {
+                if *start <= loop_end && loop_start <= *end {
+                    *start = (*start).min(loop_start);
+                    *end = (*end).max(loop_end);
+                }
+            }
+
+            if (*start, *end) == (old_start, old_end) {
+                break;
+            }
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(name, (start, end, count))| (name, start..=end, count))
+        .collect()
+}
+
+/// Returns the positions of the instructions using each hardware
+/// register, either as an operand or implicitly.
+fn register_uses(instructions: &Instructions) -> HashMap<Reg, Vec<usize>> {
+    let mut uses: HashMap<Reg, Vec<usize>> = HashMap::new();
+
+    for (position, instruction) in instructions.iter().enumerate() {
+        let implicit: &[Reg] = match instruction {
+            Instruction::Cdq | Instruction::Idiv(_) | Instruction::Div(_) => &[Reg::AX, Reg::DX],
+            // `RewriteBinaryOp` moves counts other than immediate
+            // bytes into `CL`.
+            Instruction::Binary(operator, count, _)
+                if operator.is_shift() && !matches!(count, Operand::Imm(0..=255)) =>
+            {
+                &[Reg::CX]
+            }
+            // Stack arguments are deallocated right after the call.
+            Instruction::Call { .. }
+                if matches!(
+                    instructions.get(position + 1),
+                    Some(Instruction::DeallocateStack(_))
+                ) =>
+            {
+                CALLER_SAVED_REGISTERS
+            }
+            _ => &[],
+        };
+        let explicit = instruction
+            .operands()
+            .into_iter()
+            .flat_map(|operand| match operand {
+                Operand::Register(reg) => vec![reg],
+                Operand::Indexed { base, index, .. } => vec![base, index],
+                Operand::Memory(reg) => vec![reg],
+                _ => vec![],
+            });
+
+        for reg in implicit.iter().chain(explicit) {
+            uses.entry(reg.clone()).or_default().push(position);
+        }
+    }
+
+    uses
+}
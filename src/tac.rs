@@ -1,13 +1,51 @@
-use std::{fmt::Debug, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    fs, mem,
+    path::PathBuf,
+};
 
 use crate::{
-    ast::{self, BinaryOperator, Declaration, Identifier},
+    ast::{self, Declaration, Identifier},
+    errors::{Error, Result},
     parser::Parser,
 };
 
-/// A three address code program representation.
+/// A three address code program representation, its functions
+/// along with its static variables.
 #[derive(Debug)]
-pub struct Program(pub Function);
+pub struct Program(pub Vec<Function>, pub Vec<StaticVariable>);
+
+impl Program {
+    /// Formats the program as readable three address code.
+    ///
+    /// ```
+    /// # use nous::tac::TAC;
+    /// let program = TAC::from(String::from("int main(void) { return -2; }")).to_tac_program();
+    ///
+    /// assert_eq!(program.format(), "main:\n\ttmp.1 = Negate 2\n\tReturn tmp.1\n");
+    /// ```
+    pub fn format(&self) -> String {
+        let statics: String = self.1.iter().map(StaticVariable::format).collect();
+        let functions: String = self.0.iter().map(Function::format).collect();
+
+        statics + &functions
+    }
+}
+
+/// A variable living for the whole program, such as a file
+/// scope one. Its initial value is `0` unless given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVariable {
+    pub name: Identifier,
+    pub init: i64,
+}
+
+impl StaticVariable {
+    pub fn format(&self) -> String {
+        format!("static {} = {}\n", self.name.0, self.init)
+    }
+}
 
 impl From<&mut TAC> for Program {
     fn from(value: &mut TAC) -> Self {
@@ -18,7 +56,36 @@ impl From<&mut TAC> for Program {
 #[derive(Clone)]
 pub struct Function {
     pub identifier: ast::Identifier,
+    /// Parameters, in the order arguments are passed in.
+    pub params: Vec<Identifier>,
     pub body: Instructions,
+    /// Local arrays along with their size in bytes, each one
+    /// needs a stack slot that large.
+    pub arrays: Vec<(Identifier, i64)>,
+    /// Variables and temporaries holding pointers, which are 8
+    /// bytes wide rather than 4.
+    pub pointers: HashSet<Identifier>,
+}
+
+impl Function {
+    pub fn format(&self) -> String {
+        let mut result = if self.params.is_empty() {
+            format!("{}:\n", self.identifier.0)
+        } else {
+            let params: Vec<&str> = self.params.iter().map(|param| param.0.as_str()).collect();
+            format!("{}({}):\n", self.identifier.0, params.join(", "))
+        };
+
+        for instruction in &self.body {
+            if matches!(instruction, Instruction::Label(_)) {
+                result.push_str(&format!("{}\n", instruction.format()));
+            } else {
+                result.push_str(&format!("\t{}\n", instruction.format()));
+            }
+        }
+
+        result
+    }
 }
 
 impl Debug for Function {
@@ -33,17 +100,22 @@ impl Debug for Function {
 
 #[derive(Clone, PartialEq, Eq)]
 pub enum Instruction {
-    Return(Val),
+    /// Returns from the function, without a value if it
+    /// returns `void`.
+    Return(Option<Val>),
     Unary {
         operator: ast::UnaryOperator,
         src: Val,
         dst: Val,
     },
+    /// Both operands are converted to `ty`, which tells signed
+    /// and unsigned division and comparisons apart.
     Binary {
         binary_operator: ast::BinaryOperator,
         src_1: Val,
         src_2: Val,
         dst: Val,
+        ty: ast::Type,
     },
     Copy {
         src: Val,
@@ -61,10 +133,237 @@ pub enum Instruction {
         target: Identifier,
     },
     Label(Identifier),
+    /// The source line the following instructions come from.
+    Line(usize),
+    FunCall {
+        name: Identifier,
+        arguments: Vec<Val>,
+        dst: Val,
+    },
+    /// Copies `src_1` into `dst` if `condition` is non-zero,
+    /// and `src_2` otherwise, without branching.
+    Select {
+        condition: Val,
+        src_1: Val,
+        src_2: Val,
+        dst: Val,
+    },
+    /// Copies the element of `array` found `index * scale` bytes
+    /// past its start into `dst`.
+    Load {
+        array: Identifier,
+        index: Val,
+        scale: i64,
+        dst: Val,
+    },
+    /// Copies `src` into the element of `array` found
+    /// `index * scale` bytes past its start.
+    Store {
+        src: Val,
+        array: Identifier,
+        index: Val,
+        scale: i64,
+    },
+    /// Copies the address of the variable `src` into `dst`.
+    GetAddress {
+        src: Identifier,
+        dst: Val,
+    },
+    /// Copies the value `ptr` points to into `dst`.
+    LoadThrough {
+        ptr: Val,
+        dst: Val,
+    },
+    /// Copies `src` to where `ptr` points.
+    StoreThrough {
+        src: Val,
+        ptr: Val,
+    },
 }
 
 pub type Instructions = Vec<Instruction>;
 
+impl Instruction {
+    /// Returns the values read by the instruction.
+    pub fn sources(&self) -> Vec<&Val> {
+        match self {
+            Instruction::Return(val) => val.iter().collect(),
+            Instruction::Unary { src, .. } | Instruction::Copy { src, .. } => vec![src],
+            Instruction::Binary { src_1, src_2, .. } => vec![src_1, src_2],
+            Instruction::JumpIfZero { condition, .. }
+            | Instruction::JumpIfNotZero { condition, .. } => vec![condition],
+            Instruction::FunCall { arguments, .. } => arguments.iter().collect(),
+            Instruction::Select {
+                condition,
+                src_1,
+                src_2,
+                ..
+            } => vec![condition, src_1, src_2],
+            Instruction::Load { index, .. } => vec![index],
+            Instruction::Store { src, index, .. } => vec![src, index],
+            Instruction::LoadThrough { ptr, .. } => vec![ptr],
+            Instruction::StoreThrough { src, ptr } => vec![src, ptr],
+            // The address of a variable doesn't depend on its value.
+            Instruction::GetAddress { .. }
+            | Instruction::Jump { .. }
+            | Instruction::Label(_)
+            | Instruction::Line(_) => {
+                vec![]
+            }
+        }
+    }
+
+    /// Returns mutable references to the values read by the
+    /// instruction, in the same order as [`Instruction::sources`].
+    pub fn sources_mut(&mut self) -> Vec<&mut Val> {
+        match self {
+            Instruction::Return(val) => val.iter_mut().collect(),
+            Instruction::Unary { src, .. } | Instruction::Copy { src, .. } => vec![src],
+            Instruction::Binary { src_1, src_2, .. } => vec![src_1, src_2],
+            Instruction::JumpIfZero { condition, .. }
+            | Instruction::JumpIfNotZero { condition, .. } => vec![condition],
+            Instruction::FunCall { arguments, .. } => arguments.iter_mut().collect(),
+            Instruction::Select {
+                condition,
+                src_1,
+                src_2,
+                ..
+            } => vec![condition, src_1, src_2],
+            Instruction::Load { index, .. } => vec![index],
+            Instruction::Store { src, index, .. } => vec![src, index],
+            Instruction::LoadThrough { ptr, .. } => vec![ptr],
+            Instruction::StoreThrough { src, ptr } => vec![src, ptr],
+            // The address of a variable doesn't depend on its value.
+            Instruction::GetAddress { .. }
+            | Instruction::Jump { .. }
+            | Instruction::Label(_)
+            | Instruction::Line(_) => {
+                vec![]
+            }
+        }
+    }
+
+    /// Returns the value written by the instruction, if any.
+    /// Stores write to memory instead.
+    pub fn destination(&self) -> Option<&Val> {
+        match self {
+            Instruction::Unary { dst, .. }
+            | Instruction::Binary { dst, .. }
+            | Instruction::Copy { dst, .. }
+            | Instruction::FunCall { dst, .. }
+            | Instruction::Select { dst, .. }
+            | Instruction::Load { dst, .. }
+            | Instruction::GetAddress { dst, .. }
+            | Instruction::LoadThrough { dst, .. } => Some(dst),
+            Instruction::Return(_)
+            | Instruction::Store { .. }
+            | Instruction::StoreThrough { .. }
+            | Instruction::Jump { .. }
+            | Instruction::JumpIfZero { .. }
+            | Instruction::JumpIfNotZero { .. }
+            | Instruction::Label(_)
+            | Instruction::Line(_) => None,
+        }
+    }
+
+    pub fn format(&self) -> String {
+        match self {
+            Instruction::Return(Some(val)) => format!("Return {}", val.format()),
+            Instruction::Return(None) => "Return".into(),
+            Instruction::Unary { operator, src, dst } => {
+                format!("{} = {:?} {}", dst.format(), operator, src.format())
+            }
+            Instruction::Binary {
+                binary_operator,
+                src_1,
+                src_2,
+                dst,
+                ty,
+            } => {
+                let binary = format!(
+                    "{} = {} {:?} {}",
+                    dst.format(),
+                    src_1.format(),
+                    binary_operator,
+                    src_2.format()
+                );
+
+                match ty {
+                    ast::Type::Int => binary,
+                    ty => format!("{binary} ({})", ty.to_c()),
+                }
+            }
+            Instruction::Copy { src, dst } => format!("{} = {}", dst.format(), src.format()),
+            Instruction::Jump { target } => format!("Jump {}", target.0),
+            Instruction::JumpIfZero { condition, target } => {
+                format!("JumpIfZero {}, {}", condition.format(), target.0)
+            }
+            Instruction::JumpIfNotZero { condition, target } => {
+                format!("JumpIfNotZero {}, {}", condition.format(), target.0)
+            }
+            Instruction::Label(label) => format!("{}:", label.0),
+            Instruction::Line(line) => format!("# line {line}"),
+            Instruction::FunCall {
+                name,
+                arguments,
+                dst,
+            } => format!(
+                "{} = Call {}({})",
+                dst.format(),
+                name.0,
+                arguments
+                    .iter()
+                    .map(Val::format)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Instruction::Select {
+                condition,
+                src_1,
+                src_2,
+                dst,
+            } => format!(
+                "{} = Select {}, {}, {}",
+                dst.format(),
+                condition.format(),
+                src_1.format(),
+                src_2.format()
+            ),
+            Instruction::Load {
+                array,
+                index,
+                scale,
+                dst,
+            } => format!(
+                "{} = Load {} + {} * {scale}",
+                dst.format(),
+                array.0,
+                index.format()
+            ),
+            Instruction::Store {
+                src,
+                array,
+                index,
+                scale,
+            } => format!(
+                "Store {}, {} + {} * {scale}",
+                src.format(),
+                array.0,
+                index.format()
+            ),
+            Instruction::GetAddress { src, dst } => {
+                format!("{} = GetAddress {}", dst.format(), src.0)
+            }
+            Instruction::LoadThrough { ptr, dst } => {
+                format!("{} = Load *{}", dst.format(), ptr.format())
+            }
+            Instruction::StoreThrough { src, ptr } => {
+                format!("Store {}, *{}", src.format(), ptr.format())
+            }
+        }
+    }
+}
+
 impl Debug for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -77,11 +376,12 @@ impl Debug for Instruction {
                 src_1,
                 src_2,
                 dst,
+                ty,
             } => {
                 write!(
                     f,
-                    "\n\t\tBinary({:?}, {:?}, {:?}, {:?})",
-                    binary_operator, src_1, src_2, dst
+                    "\n\t\tBinary({:?}, {:?}, {:?}, {:?}, {:?})",
+                    binary_operator, src_1, src_2, dst, ty
                 )
             }
             Self::Copy { src, dst } => write!(f, "\n\t\tCopy({:?}, {:?})", src, dst),
@@ -90,9 +390,52 @@ impl Debug for Instruction {
                 write!(f, "\n\t\tJumpIfZero({:?}, {:?})", condition, target)
             }
             Self::Label(identifier) => write!(f, "\n\t\tLabel({:?})", identifier),
+            Self::Line(line) => write!(f, "\n\t\tLine({line})"),
             Self::JumpIfNotZero { condition, target } => {
                 write!(f, "\n\t\tJumpIfNotZero({:?}, {:?})", condition, target)
             }
+            Self::FunCall {
+                name,
+                arguments,
+                dst,
+            } => write!(f, "\n\t\tFunCall({:?}, {:?}, {:?})", name, arguments, dst),
+            Self::Select {
+                condition,
+                src_1,
+                src_2,
+                dst,
+            } => write!(
+                f,
+                "\n\t\tSelect({:?}, {:?}, {:?}, {:?})",
+                condition, src_1, src_2, dst
+            ),
+            Self::Load {
+                array,
+                index,
+                scale,
+                dst,
+            } => write!(
+                f,
+                "\n\t\tLoad({:?}, {:?}, {:?}, {:?})",
+                array, index, scale, dst
+            ),
+            Self::Store {
+                src,
+                array,
+                index,
+                scale,
+            } => write!(
+                f,
+                "\n\t\tStore({:?}, {:?}, {:?}, {:?})",
+                src, array, index, scale
+            ),
+            Self::GetAddress { src, dst } => write!(f, "\n\t\tGetAddress({:?}, {:?})", src, dst),
+            Self::LoadThrough { ptr, dst } => {
+                write!(f, "\n\t\tLoadThrough({:?}, {:?})", ptr, dst)
+            }
+            Self::StoreThrough { src, ptr } => {
+                write!(f, "\n\t\tStoreThrough({:?}, {:?})", src, ptr)
+            }
         }
     }
 }
@@ -103,6 +446,15 @@ pub enum Val {
     Var(ast::Identifier),
 }
 
+impl Val {
+    pub fn format(&self) -> String {
+        match self {
+            Val::Constant(i) => i.to_string(),
+            Val::Var(identifier) => identifier.0.clone(),
+        }
+    }
+}
+
 /// Constructs TAC intermediate representation from an ast
 ///
 /// ```
@@ -126,17 +478,24 @@ pub struct TAC {
     temp_count: usize,
     label_count: usize,
     instructions: Instructions,
+    /// Types of the declared variables, names are unique once
+    /// resolved.
+    types: HashMap<Identifier, ast::Type>,
+    /// Arrays declared in the current function.
+    arrays: Vec<(Identifier, i64)>,
+    /// Pointers of the current function.
+    pointers: HashSet<Identifier>,
+    /// Name of the current function.
+    function: Identifier,
+    /// First function holding a loop `LoopLabeling` didn't label.
+    unlabeled: Option<Identifier>,
 }
 
 impl From<String> for TAC {
     fn from(value: String) -> Self {
         let source = Parser::from(value).to_ast_program().expect("XD");
-        Self {
-            source,
-            temp_count: 0,
-            label_count: 0,
-            instructions: Vec::new(),
-        }
+
+        TAC::from(source)
     }
 }
 
@@ -144,11 +503,22 @@ impl From<&mut Parser> for TAC {
     fn from(value: &mut Parser) -> Self {
         let source = value.to_ast_program().expect("SOmething");
 
+        TAC::from(source)
+    }
+}
+
+impl From<ast::Program> for TAC {
+    fn from(source: ast::Program) -> Self {
         Self {
             source,
             temp_count: 0,
             label_count: 0,
             instructions: Vec::new(),
+            types: HashMap::new(),
+            arrays: Vec::new(),
+            pointers: HashSet::new(),
+            function: Identifier::from("main"),
+            unlabeled: None,
         }
     }
 }
@@ -163,14 +533,65 @@ impl From<PathBuf> for TAC {
 
 #[allow(unreachable_code, unused)]
 impl TAC {
+    /// Returns a TAC generator over the given source code.
+    pub fn build(source: &str) -> Self {
+        TAC::from(source.to_string())
+    }
+
+    /// Generates the three address code of the program.
+    ///
+    /// Panics if a loop is not labeled, see
+    /// [`TAC::try_to_tac_program`].
     pub fn to_tac_program(&mut self) -> Program {
-        self.parse_program()
+        self.try_to_tac_program()
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Generates the three address code of the program, or
+    /// reports the first function holding a loop, `break` or
+    /// `continue` that `LoopLabeling` didn't label.
+    pub fn try_to_tac_program(&mut self) -> Result<Program> {
+        let program = self.parse_program();
+
+        match self.unlabeled.take() {
+            Some(function) => Err(Error::UnlabeledLoop { function }),
+            None => Ok(program),
+        }
     }
 
     fn parse_program(&mut self) -> Program {
-        let function = self.parse_function(self.source.0.clone());
+        let statics = self
+            .source
+            .1
+            .clone()
+            .into_iter()
+            .map(|global| self.parse_global(global))
+            .collect();
+
+        let functions = self
+            .source
+            .0
+            .clone()
+            .into_iter()
+            .map(|function| self.parse_function(function))
+            .collect();
+
+        Program(functions, statics)
+    }
 
-        Program(function)
+    /// Records the type of a file scope variable, whose
+    /// initializer was checked to be a constant during variable
+    /// resolution.
+    fn parse_global(&mut self, global: Declaration) -> StaticVariable {
+        self.types.insert(global.name.clone(), global.ty);
+
+        StaticVariable {
+            name: global.name,
+            init: global
+                .initializer
+                .and_then(|initializer| initializer.constant_value())
+                .unwrap_or(0),
+        }
     }
 
     fn parse_function(&mut self, function: ast::Function) -> Function {
@@ -184,13 +605,26 @@ impl TAC {
         // instructions.
         // self.instructions = Vec::from_iter(function.body.into_iter().map(|x| self.parse_block(x)));
 
+        self.function = function.name.clone();
+        let params = function
+            .params
+            .into_iter()
+            .map(|param| {
+                let name = param.name.clone();
+                self.process_declaration(param);
+                name
+            })
+            .collect();
         for block in function.body {
             self.process_block(block);
         }
 
         Function {
             identifier: function.name,
-            body: self.instructions.clone(),
+            params,
+            body: mem::take(&mut self.instructions),
+            arrays: mem::take(&mut self.arrays),
+            pointers: mem::take(&mut self.pointers),
         }
     }
 
@@ -204,10 +638,24 @@ impl TAC {
             ast::BlockItem::D(declaration) => {
                 self.process_declaration(declaration);
             }
+            ast::BlockItem::Line(line) => self.instructions.push(Instruction::Line(line)),
+            // Enumerators were replaced by their values.
+            ast::BlockItem::E(_) => {}
         }
     }
 
     fn process_declaration(&mut self, declaration: Declaration) {
+        self.types.insert(declaration.name.clone(), declaration.ty);
+        match declaration.ty {
+            ast::Type::Array(_) => self
+                .arrays
+                .push((declaration.name.clone(), declaration.ty.size())),
+            ast::Type::Pointer => {
+                self.pointers.insert(declaration.name.clone());
+            }
+            _ => {}
+        }
+
         if let Some(x) = declaration.initializer {
             // If a declaration includes an initializer,
             // we’ll handle it like a normal variable assignment
@@ -222,7 +670,7 @@ impl TAC {
     fn parse_statement(&mut self, statement: ast::Statement) -> Option<Instruction> {
         match statement {
             ast::Statement::Return(expression) => {
-                let val = self.parse_val(expression);
+                let val = expression.map(|expression| self.parse_val(expression));
 
                 Some(Instruction::Return(val))
             }
@@ -236,6 +684,23 @@ impl TAC {
             }
             // We wont emit instructions for a null statement
             ast::Statement::Null => None,
+            // Nested blocks are flattened through a worklist rather
+            // than recursion, so deeply nested blocks can't overflow
+            // the stack.
+            ast::Statement::Compound(block_items) => {
+                let mut worklist: Vec<ast::BlockItem> = block_items.into_iter().rev().collect();
+
+                while let Some(block) = worklist.pop() {
+                    match block {
+                        ast::BlockItem::S(ast::Statement::Compound(inner)) => {
+                            worklist.extend(inner.into_iter().rev());
+                        }
+                        block => self.process_block(block),
+                    }
+                }
+
+                None
+            }
             ast::Statement::If {
                 condition,
 
@@ -262,8 +727,9 @@ impl TAC {
                         target: (&else_label).into(),
                     });
 
-                    let instructions_for_statement1 = self.parse_statement(*then)?;
-                    self.instructions.push(instructions_for_statement1);
+                    if let Some(instruction) = self.parse_statement(*then) {
+                        self.instructions.push(instruction);
+                    }
                     self.instructions.push(Instruction::Jump {
                         target: (&end_label).into(),
                     });
@@ -288,18 +754,170 @@ impl TAC {
                         target: (&end_label).into(),
                     });
 
-                    let instructions_for_statement = self.parse_statement(*then)?;
-                    self.instructions.push(instructions_for_statement);
+                    if let Some(instruction) = self.parse_statement(*then) {
+                        self.instructions.push(instruction);
+                    }
                     self.instructions.push(Instruction::Label(end_label.into()));
                 };
                 None
             }
+            ast::Statement::Break(label) => Some(Instruction::Jump {
+                target: self.break_label(&label),
+            }),
+            ast::Statement::Continue(label) => Some(Instruction::Jump {
+                target: self.continue_label(&label),
+            }),
+            ast::Statement::While {
+                condition,
+                body,
+                label,
+            } => {
+                // Label(continue_label)
+                // <instructions for condition>
+                // JumpIfZero(c, break_label)
+                // <instructions for body>
+                // Jump(continue_label)
+                // Label(break_label)
+                let continue_label = self.continue_label(&label);
+                let break_label = self.break_label(&label);
+
+                self.instructions
+                    .push(Instruction::Label(continue_label.clone()));
+                let condition = self.parse_val(condition);
+                self.instructions.push(Instruction::JumpIfZero {
+                    condition,
+                    target: break_label.clone(),
+                });
+                self.emit_statement(*body);
+                self.instructions.push(Instruction::Jump {
+                    target: continue_label,
+                });
+                self.instructions.push(Instruction::Label(break_label));
+                None
+            }
+            ast::Statement::DoWhile {
+                body,
+                condition,
+                label,
+            } => {
+                // Label(start)
+                // <instructions for body>
+                // Label(continue_label)
+                // <instructions for condition>
+                // JumpIfNotZero(c, start)
+                // Label(break_label)
+                let start_label = self.start_label(&label);
+
+                self.instructions
+                    .push(Instruction::Label(start_label.clone()));
+                self.emit_statement(*body);
+                let continue_label = self.continue_label(&label);
+                self.instructions.push(Instruction::Label(continue_label));
+                let condition = self.parse_val(condition);
+                self.instructions.push(Instruction::JumpIfNotZero {
+                    condition,
+                    target: start_label,
+                });
+                let break_label = self.break_label(&label);
+                self.instructions.push(Instruction::Label(break_label));
+                None
+            }
+            ast::Statement::For {
+                init,
+                condition,
+                post,
+                body,
+                label,
+            } => {
+                // <instructions for init>
+                // Label(start)
+                // <instructions for condition>
+                // JumpIfZero(c, break_label)
+                // <instructions for body>
+                // Label(continue_label)
+                // <instructions for post>
+                // Jump(start)
+                // Label(break_label)
+                let start_label = self.start_label(&label);
+                let break_label = self.break_label(&label);
+
+                match init {
+                    ast::ForInit::InitDecl(declarations) => {
+                        for declaration in declarations {
+                            self.process_declaration(declaration);
+                        }
+                    }
+                    ast::ForInit::InitExp(Some(expression)) => {
+                        self.parse_val(expression);
+                    }
+                    ast::ForInit::InitExp(None) => {}
+                }
+                self.instructions
+                    .push(Instruction::Label(start_label.clone()));
+                // A missing condition is always true.
+                if let Some(condition) = condition {
+                    let condition = self.parse_val(condition);
+                    self.instructions.push(Instruction::JumpIfZero {
+                        condition,
+                        target: break_label.clone(),
+                    });
+                }
+                self.emit_statement(*body);
+                let continue_label = self.continue_label(&label);
+                self.instructions.push(Instruction::Label(continue_label));
+                if let Some(post) = post {
+                    self.parse_val(post);
+                }
+                self.instructions.push(Instruction::Jump {
+                    target: start_label,
+                });
+                self.instructions.push(Instruction::Label(break_label));
+                None
+            }
+        }
+    }
+
+    /// Pushes the instruction a nested statement
+    /// returns, if any.
+    fn emit_statement(&mut self, statement: ast::Statement) {
+        if let Some(instruction) = self.parse_statement(statement) {
+            self.instructions.push(instruction);
+        }
+    }
+
+    fn start_label(&mut self, label: &Option<Identifier>) -> Identifier {
+        self.loop_label("start", label)
+    }
+
+    fn break_label(&mut self, label: &Option<Identifier>) -> Identifier {
+        self.loop_label("break", label)
+    }
+
+    fn continue_label(&mut self, label: &Option<Identifier>) -> Identifier {
+        self.loop_label("continue", label)
+    }
+
+    /// Loops and their `break` and `continue` statements are
+    /// labeled by `LoopLabeling` before reaching this stage, an
+    /// unlabeled one is reported by [`TAC::try_to_tac_program`].
+    fn loop_label(&mut self, prefix: &str, label: &Option<Identifier>) -> Identifier {
+        match label {
+            Some(label) => format!("{prefix}_{}", label.0).into(),
+            None => {
+                self.unlabeled.get_or_insert_with(|| self.function.clone());
+                prefix.into()
+            }
         }
     }
 
     fn parse_val(&mut self, expression: ast::Expression) -> Val {
         match expression {
             ast::Expression::Constant(i) => Val::Constant(i),
+            ast::Expression::UnsignedConstant(i) => Val::Constant(i.into()),
+            // `sizeof` is a compile time constant, its operand is
+            // never evaluated.
+            ast::Expression::SizeOfType(ty) => Val::Constant(ty.size()),
+            ast::Expression::SizeOf(inner) => Val::Constant(self.type_of(&inner).size()),
             ast::Expression::Unary(op, inner) => {
                 let src = self.parse_val(*inner);
                 let dst_name = self.make_temporary_name();
@@ -313,82 +931,106 @@ impl TAC {
             }
             ast::Expression::Binary(op, e1, e2) => match op {
                 ast::BinaryOperator::And => {
+                    // The expression `<e1> && <e2>` only evaluates `<e2>`
+                    // when `<e1>` is non-zero:
+                    //
+                    // <instructions_for_e1>
+                    // JumpIfZero(v1, and_false)
+                    // <instructions_for_e2>
+                    // JumpIfZero(v2, and_false)
+                    // result = 1
+                    // Jump(end)
+                    // Label(and_false)
+                    // result = 0
+                    // Label(end)
+                    let id = self.make_label_id();
+                    let false_label: Identifier = format!("and_false.{id}").into();
+                    let end_label: Identifier = format!("end.{id}").into();
+                    let result = Val::Var(format!("result.{id}").into());
+
                     let v1 = self.parse_val(*e1);
+                    self.instructions.push(Instruction::JumpIfZero {
+                        condition: v1,
+                        target: false_label.clone(),
+                    });
                     let v2 = self.parse_val(*e2);
 
-                    //let temp_label_name = Rc::new(self.make_temporary_label(BinaryOperator::And));
-                    let temp_label_name = self.make_label("and");
-
                     self.instructions.append(
                         vec![
-                            Instruction::JumpIfZero {
-                                condition: v1,
-                                target: (&temp_label_name).into(),
-                            },
                             Instruction::JumpIfZero {
                                 condition: v2,
-                                target: (&temp_label_name).into(),
+                                target: false_label.clone(),
                             },
                             Instruction::Copy {
                                 src: Val::Constant(1),
-                                dst: Val::Var(format!("result.{}", self.label_count).into()),
+                                dst: result.clone(),
                             },
                             Instruction::Jump {
-                                target: format!("end.{}", self.label_count).into(),
+                                target: end_label.clone(),
                             },
-                            Instruction::Label((&temp_label_name).into()),
+                            Instruction::Label(false_label),
                             Instruction::Copy {
                                 src: Val::Constant(0),
-                                dst: Val::Var(format!("result.{}", self.label_count).into()),
+                                dst: result.clone(),
                             },
-                            Instruction::Label(format!("end.{}", self.label_count).into()),
+                            Instruction::Label(end_label),
                         ]
                         .as_mut(),
                     );
 
-                    Val::Var(format!("result.{}", self.label_count).into())
+                    result
                 }
 
                 ast::BinaryOperator::Or => {
+                    // The expression `<e1> || <e2>` only evaluates `<e2>`
+                    // when `<e1>` is zero.
+                    let id = self.make_label_id();
+                    let true_label: Identifier = format!("or_true.{id}").into();
+                    let end_label: Identifier = format!("end.{id}").into();
+                    let result = Val::Var(format!("result.{id}").into());
+
                     let v1 = self.parse_val(*e1);
+                    self.instructions.push(Instruction::JumpIfNotZero {
+                        condition: v1,
+                        target: true_label.clone(),
+                    });
                     let v2 = self.parse_val(*e2);
 
-                    //let temp_label_name = Rc::new(self.make_temporary_label(BinaryOperator::Or));
-                    let temp_label_name = self.make_label("or");
-
                     self.instructions.append(
                         vec![
-                            Instruction::JumpIfNotZero {
-                                condition: v1,
-                                target: (&temp_label_name).into(),
-                            },
                             Instruction::JumpIfNotZero {
                                 condition: v2,
-                                target: (&temp_label_name).into(),
+                                target: true_label.clone(),
                             },
                             // If no jumps are performed then both values
                             // are zero, meaning the result is 0.
                             Instruction::Copy {
                                 src: Val::Constant(0),
-                                dst: Val::Var(format!("result.{}", self.label_count).into()),
+                                dst: result.clone(),
                             },
                             Instruction::Jump {
-                                target: format!("end.{}", self.label_count).into(),
+                                target: end_label.clone(),
                             },
                             // If we jump to this label then one of the values
                             // is non-zero, meaning the result is 1.
-                            Instruction::Label((&temp_label_name).into()),
+                            Instruction::Label(true_label),
                             Instruction::Copy {
                                 src: Val::Constant(1),
-                                dst: Val::Var(format!("result.{}", self.label_count).into()),
+                                dst: result.clone(),
                             },
-                            Instruction::Label(format!("end.{}", self.label_count).into()),
+                            Instruction::Label(end_label),
                         ]
                         .as_mut(),
                     );
-                    Val::Var(format!("result.{}", self.label_count).into())
+                    result
                 }
                 _ => {
+                    let ty = match op {
+                        ast::BinaryOperator::ShiftLeft | ast::BinaryOperator::ShiftRight => {
+                            self.type_of(&e1)
+                        }
+                        _ => self.type_of(&e1).common(self.type_of(&e2)),
+                    };
                     let v1 = self.parse_val(*e1);
                     let v2 = self.parse_val(*e2);
                     let dst_name = self.make_temporary_name();
@@ -398,14 +1040,88 @@ impl TAC {
                         src_1: v1,
                         src_2: v2,
                         dst: dst.clone(),
+                        ty,
                     });
                     dst
                 }
             },
             ast::Expression::Var(i) => Val::Var(i),
-            ast::Expression::Assignment(a, rhs) => {
-                assert!(matches!(*a, ast::Expression::Var(_)));
+            ast::Expression::Subscript(array, index) => {
+                let array = Self::array_name(*array);
+                let index = self.parse_val(*index);
+                let dst = Val::Var(self.make_temporary_name().into());
+
+                self.instructions.push(Instruction::Load {
+                    array,
+                    index,
+                    scale: ast::Type::Int.size(),
+                    dst: dst.clone(),
+                });
+
+                dst
+            }
+            // `VariableResolution` only takes the address of
+            // variables, or of a dereferenced pointer.
+            ast::Expression::AddrOf(inner) => match *inner {
+                ast::Expression::Var(src) => {
+                    let dst_name: Identifier = self.make_temporary_name().into();
+                    self.pointers.insert(dst_name.clone());
+                    let dst = Val::Var(dst_name);
+
+                    self.instructions.push(Instruction::GetAddress {
+                        src,
+                        dst: dst.clone(),
+                    });
+                    dst
+                }
+                ast::Expression::Deref(ptr) => self.parse_val(*ptr),
+                _ => unreachable!("Only variables have their address taken"),
+            },
+            ast::Expression::Deref(ptr) => {
+                let ptr = self.parse_val(*ptr);
+                let dst = Val::Var(self.make_temporary_name().into());
 
+                self.instructions.push(Instruction::LoadThrough {
+                    ptr,
+                    dst: dst.clone(),
+                });
+                dst
+            }
+            // Storing through a pointer yields the stored value.
+            ast::Expression::Assignment(a, rhs) if matches!(*a, ast::Expression::Deref(_)) => {
+                let ast::Expression::Deref(ptr) = *a else {
+                    unreachable!("The guard matched a dereference")
+                };
+                let src = self.parse_val(*rhs);
+                let ptr = self.parse_val(*ptr);
+
+                self.instructions.push(Instruction::StoreThrough {
+                    src: src.clone(),
+                    ptr,
+                });
+                src
+            }
+            // Storing into an element yields the stored value.
+            ast::Expression::Assignment(a, rhs) if matches!(*a, ast::Expression::Subscript(..)) => {
+                let ast::Expression::Subscript(array, index) = *a else {
+                    unreachable!("The guard matched a subscript")
+                };
+                let src = self.parse_val(*rhs);
+                let array = Self::array_name(*array);
+                let index = self.parse_val(*index);
+
+                self.instructions.push(Instruction::Store {
+                    src: src.clone(),
+                    array,
+                    index,
+                    scale: ast::Type::Int.size(),
+                });
+
+                src
+            }
+            ast::Expression::Assignment(a, rhs) => {
+                // `VariableResolution` rejects assignments to
+                // anything but an lvalue.
                 let result = self.parse_val(*rhs);
                 let dst = self.parse_val(*a);
 
@@ -416,6 +1132,28 @@ impl TAC {
 
                 dst
             }
+            // Evaluating constants or variables has no side
+            // effects, so both branches can be evaluated and
+            // selected between.
+            ast::Expression::Conditional {
+                condition,
+                exp1,
+                exp2,
+            } if exp1.is_simple() && exp2.is_simple() => {
+                let condition = self.parse_val(*condition);
+                let src_1 = self.parse_val(*exp1);
+                let src_2 = self.parse_val(*exp2);
+                let dst = Val::Var(self.make_temporary_name().into());
+
+                self.instructions.push(Instruction::Select {
+                    condition,
+                    src_1,
+                    src_2,
+                    dst: dst.clone(),
+                });
+
+                dst
+            }
             ast::Expression::Conditional {
                 condition,
                 exp1,
@@ -462,6 +1200,71 @@ impl TAC {
 
                 Val::Var((&result_label).into())
             }
+            ast::Expression::Comma(left, right) => {
+                self.parse_val(*left);
+                self.parse_val(*right)
+            }
+            // Arguments are evaluated from left to right.
+            ast::Expression::FunctionCall { name, arguments } => {
+                let arguments = arguments
+                    .into_iter()
+                    .map(|argument| self.parse_val(argument))
+                    .collect();
+                let dst = Val::Var(self.make_temporary_name().into());
+
+                self.instructions.push(Instruction::FunCall {
+                    name,
+                    arguments,
+                    dst: dst.clone(),
+                });
+
+                dst
+            }
+        }
+    }
+
+    /// Returns the type of `expression` after the usual
+    /// arithmetic conversions.
+    fn type_of(&self, expression: &ast::Expression) -> ast::Type {
+        match expression {
+            // Sizes are unsigned, standing in for `size_t`.
+            ast::Expression::UnsignedConstant(_)
+            | ast::Expression::SizeOfType(_)
+            | ast::Expression::SizeOf(_) => ast::Type::UnsignedInt,
+            ast::Expression::Var(name) => self.types.get(name).copied().unwrap_or_default(),
+            ast::Expression::Unary(ast::UnaryOperator::Not, _) => ast::Type::Int,
+            ast::Expression::Unary(_, inner) => self.type_of(inner),
+            ast::Expression::Binary(operator, left, right) => match operator {
+                ast::BinaryOperator::Add
+                | ast::BinaryOperator::Subtract
+                | ast::BinaryOperator::Multiply
+                | ast::BinaryOperator::Divide
+                | ast::BinaryOperator::Remainder => self.type_of(left).common(self.type_of(right)),
+                ast::BinaryOperator::ShiftLeft | ast::BinaryOperator::ShiftRight => {
+                    self.type_of(left)
+                }
+                // Logical and relational operators yield `0` or `1`.
+                _ => ast::Type::Int,
+            },
+            ast::Expression::Assignment(left, _) => self.type_of(left),
+            ast::Expression::Conditional { exp1, exp2, .. } => {
+                self.type_of(exp1).common(self.type_of(exp2))
+            }
+            ast::Expression::Comma(_, right) => self.type_of(right),
+            ast::Expression::AddrOf(_) => ast::Type::Pointer,
+            ast::Expression::Constant(_)
+            | ast::Expression::FunctionCall { .. }
+            | ast::Expression::Subscript(..)
+            | ast::Expression::Deref(_) => ast::Type::Int,
+        }
+    }
+
+    /// `VariableResolution` only lets arrays, which are
+    /// variables, be subscripted.
+    fn array_name(array: ast::Expression) -> Identifier {
+        match array {
+            ast::Expression::Var(name) => name,
+            _ => unreachable!("Only arrays are subscripted"),
         }
     }
 
@@ -470,21 +1273,16 @@ impl TAC {
         format!("tmp.{}", self.temp_count)
     }
 
-    fn make_temporary_label(&mut self, binop: BinaryOperator) -> String {
-        self.label_count += 1;
-        match binop {
-            BinaryOperator::And => format!("and_false.{:?}", self.label_count),
-            BinaryOperator::Or => format!("or_true.{:?}", self.label_count),
-            _ => "Error!!".into(),
-        }
+    /// Returns a label made of `prefix` and a fresh counter
+    /// value, so no two labels share the same name.
+    fn make_label(&mut self, prefix: &str) -> String {
+        format!("{prefix}.{}", self.make_label_id())
     }
 
-    fn make_label(&mut self, prefix: &str) -> String {
+    /// Returns a fresh counter value, constructs emitting several
+    /// labels suffix all of them with the same value.
+    fn make_label_id(&mut self) -> usize {
         self.label_count += 1;
-        match prefix {
-            "and" => format!("and_false.{}", self.label_count),
-            "or" => format!("or_false.{}", self.label_count),
-            _ => format!("{prefix}{}", self.label_count),
-        }
+        self.label_count
     }
 }
@@ -1,16 +1,21 @@
-use crate::{parser::Parser, tac::TAC};
-use std::{
-    fs::File,
-    io::{prelude::*, BufReader},
+use crate::{
+    errors::{Error, Result},
+    parser::Parser,
+    tac::TAC,
 };
+use std::{fs, io, path::Path};
 
-pub fn read_file(path: &str) -> std::io::Result<String> {
-    let file = File::open(path)?;
-    let mut buf_reader = BufReader::new(file);
-    let mut contents = String::new();
-    buf_reader.read_to_string(&mut contents)?;
+/// Reads the whole file at `path`, naming it in the error
+/// when it can't be read.
+pub fn read_file(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref();
 
-    Ok(contents)
+    fs::read_to_string(path).map_err(|error| {
+        Error::IoError(io::Error::new(
+            error.kind(),
+            format!("Can't read {}: {error}", path.display()),
+        ))
+    })
 }
 
 pub fn parser_from_path(path: &str) -> Parser {
@@ -1,12 +1,16 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    cell::Cell,
+    collections::{HashMap, HashSet, VecDeque},
     fmt::Debug,
+    mem,
 };
 
 use crate::{
     assembly::{Assembly, BinaryOperator, Instruction, Instructions, Operand, Program, Reg},
     ast::{self, Declaration, Identifier},
+    const_eval::eval_const,
     errors::{Error, Result},
+    register_allocation,
 };
 
 /// Visits an instance of an assembly program
@@ -38,9 +42,25 @@ use crate::{
 /// ```
 pub struct AssemblyPass {
     program: Program,
-    instructions: Instructions,
-    pseudo_registers: HashMap<Operand, i64>,
-    offset: i64,
+    /// Stack slots of the pseudo registers of each function, by
+    /// function name.
+    pseudo_registers: HashMap<Identifier, HashMap<Operand, i64>>,
+    register_allocation: bool,
+}
+
+/// A pass over the assembly program.
+type Pass = fn(&mut AssemblyPass) -> &mut AssemblyPass;
+
+impl From<Program> for AssemblyPass {
+    /// Builds a visitor over an already constructed program,
+    /// no pseudo registers are known in this case.
+    fn from(program: Program) -> Self {
+        Self {
+            program,
+            pseudo_registers: HashMap::new(),
+            register_allocation: false,
+        }
+    }
 }
 
 impl AssemblyPass {
@@ -49,12 +69,10 @@ impl AssemblyPass {
     /// program field in such instance must be not None.
     pub fn build(assembly: Assembly) -> Self {
         if let Some(program) = assembly.program {
-            let instructions = program.0.instructions.clone();
             Self {
                 program,
-                instructions,
                 pseudo_registers: assembly.pseudo_registers,
-                offset: assembly.offset,
+                register_allocation: false,
             }
         } else {
             panic!("The program must exists in order to create the AssemblyPass instance. Try parsing the program fist.")
@@ -65,120 +83,199 @@ impl AssemblyPass {
         if let Some(info) = debug_info {
             println!("{info}");
         }
-        println!("{:?}", self.instructions);
+        for function in &self.program.0 {
+            println!("{:?}", function.instructions);
+        }
     }
 
-    fn get_stack_value(&self, operand: &Operand) -> Operand {
-        if self.pseudo_registers.contains_key(operand) {
-            Operand::Stack(
-                *self
-                    .pseudo_registers
-                    .get(operand)
-                    .expect("Should return the operand stack value"),
-            )
-        } else {
-            operand.clone()
+    fn get_stack_value(frame: &HashMap<Operand, i64>, operand: &Operand) -> Operand {
+        match frame.get(operand) {
+            Some(offset) => Operand::Stack(*offset),
+            None => operand.clone(),
         }
     }
 
-    fn convert_register(&self, instruction: &Instruction) -> Instruction {
-        match instruction {
-            Instruction::Mov { src, dst } => Instruction::Mov {
-                src: self.get_stack_value(src),
-                dst: self.get_stack_value(dst),
-            },
-            Instruction::Unary(op, operand) => {
-                Instruction::Unary(op.clone(), self.get_stack_value(operand))
-            }
-            Instruction::Binary(binop, x, y) => Instruction::Binary(
-                binop.clone(),
-                self.get_stack_value(x),
-                self.get_stack_value(y),
-            ),
-            Instruction::Idiv(operand) => Instruction::Idiv(self.get_stack_value(operand)),
-            Instruction::Cdq => Instruction::Cdq,
-            Instruction::AllocateStack(i) => Instruction::AllocateStack(*i),
-            Instruction::Ret => Instruction::Ret,
-            Instruction::Cmp(op1, op2) => {
-                Instruction::Cmp(self.get_stack_value(op1), self.get_stack_value(op2))
-            }
-            Instruction::SetCC(cond, operand) => {
-                Instruction::SetCC(cond.clone(), self.get_stack_value(operand))
-            }
-            i => i.clone(),
+    fn convert_register(frame: &HashMap<Operand, i64>, instruction: &Instruction) -> Instruction {
+        let mut instruction = instruction.clone();
+
+        for operand in instruction.operands_mut() {
+            *operand = Self::get_stack_value(frame, operand);
         }
+
+        instruction
+    }
+
+    /// Makes [`AssemblyPass::run_passes`] assign pseudo registers
+    /// to hardware registers before spilling the others to the
+    /// stack.
+    pub fn register_allocation(mut self, register_allocation: bool) -> Self {
+        self.register_allocation = register_allocation;
+        self
+    }
+
+    /// Assigns pseudo registers to hardware registers where they
+    /// don't interfere, see [`register_allocation::allocate_registers`].
+    pub fn allocate_registers(&mut self) -> &mut Self {
+        self.rewrite(|_, instructions| register_allocation::allocate_registers(instructions))
     }
 
-    /// Replaces pseudo registers on all instructions.
+    /// Replaces pseudo registers on all instructions, with the
+    /// stack slots of the frame of their function.
     pub fn replace_pseudo_registers(&mut self) -> &mut Self {
-        let new_instructions: Vec<Instruction> = self
-            .instructions
-            .clone()
-            .iter()
-            .map(|x| self.convert_register(x))
-            .collect();
-        self.instructions = new_instructions;
+        for function in &mut self.program.0 {
+            let Some(frame) = self.pseudo_registers.get(&function.name) else {
+                continue;
+            };
+
+            function.instructions = function
+                .instructions
+                .iter()
+                .map(|x| Self::convert_register(frame, x))
+                .collect();
+        }
+
         self
     }
 
     /// Rewrites move instructions, whenever both `src` and `dst`
-    /// are Stack operands.
+    /// are memory operands.
     pub fn rewrite_mov(&mut self) -> &mut Self {
-        let mut new_instructions: Vec<Instruction> = Vec::new();
+        self.rewrite(|_, instructions| {
+            let mut new_instructions: Vec<Instruction> = Vec::new();
 
-        for instruction in &self.instructions {
-            match instruction {
-                Instruction::Mov { src, dst } => {
-                    if matches!(src, Operand::Stack(_)) && matches!(dst, Operand::Stack(_)) {
-                        new_instructions.push(Instruction::Mov {
+            for instruction in instructions {
+                match instruction {
+                    Instruction::Movq { src, dst } if src.is_memory() && dst.is_memory() => {
+                        new_instructions.push(Instruction::Movq {
                             src: src.clone(),
                             dst: Operand::Register(Reg::R10),
                         });
-                        new_instructions.push(Instruction::Mov {
+                        new_instructions.push(Instruction::Movq {
                             src: Operand::Register(Reg::R10),
                             dst: dst.clone(),
                         });
-                    } else {
-                        new_instructions.push(instruction.clone())
                     }
+                    // The source can't be an immediate, and the
+                    // destination must be a register.
+                    Instruction::Movsx { src, dst } | Instruction::Movzx { src, dst } => {
+                        let src = match src {
+                            Operand::Imm(_) => {
+                                new_instructions.push(Instruction::Mov {
+                                    src: src.clone(),
+                                    dst: Operand::Register(Reg::R10),
+                                });
+                                Operand::Register(Reg::R10)
+                            }
+                            src => src.clone(),
+                        };
+                        let extended = match dst {
+                            Operand::Register(_) => dst.clone(),
+                            _ => Operand::Register(Reg::R11),
+                        };
+
+                        new_instructions.push(match instruction {
+                            Instruction::Movsx { .. } => Instruction::Movsx {
+                                src,
+                                dst: extended.clone(),
+                            },
+                            _ => Instruction::Movzx {
+                                src,
+                                dst: extended.clone(),
+                            },
+                        });
+                        if extended != *dst {
+                            new_instructions.push(Instruction::Mov {
+                                src: extended,
+                                dst: dst.clone(),
+                            });
+                        }
+                    }
+                    Instruction::Mov { src, dst } => {
+                        if src.is_memory() && dst.is_memory() {
+                            new_instructions.push(Instruction::Mov {
+                                src: src.clone(),
+                                dst: Operand::Register(Reg::R10),
+                            });
+                            new_instructions.push(Instruction::Mov {
+                                src: Operand::Register(Reg::R10),
+                                dst: dst.clone(),
+                            });
+                        } else {
+                            new_instructions.push(instruction.clone())
+                        }
+                    }
+                    _ => new_instructions.push(instruction.clone()),
                 }
-                _ => new_instructions.push(instruction.clone()),
             }
-        }
-        self.instructions = new_instructions;
-        self
+            new_instructions
+        })
+    }
+
+    /// Drops `mov` instructions that don't change any value:
+    /// moves of an operand into itself, and a reload right after
+    /// a store of the same operand, e.g. `movl %eax, x` followed
+    /// by `movl x, %eax`. The store is kept since `x` may still be
+    /// read later on.
+    pub fn remove_redundant_mov(&mut self) -> &mut Self {
+        self.rewrite(|_, instructions| {
+            let mut new_instructions: Vec<Instruction> = Vec::new();
+
+            for instruction in instructions {
+                if let Instruction::Mov { src, dst } = instruction {
+                    if src == dst {
+                        continue;
+                    }
+
+                    if let Some(Instruction::Mov {
+                        src: stored,
+                        dst: stored_into,
+                    }) = new_instructions.last()
+                    {
+                        if stored == dst && stored_into == src {
+                            continue;
+                        }
+                    }
+                }
+
+                new_instructions.push(instruction.clone());
+            }
+            new_instructions
+        })
     }
 
     /// The `cmp` instruction can't use memory addresses for
     /// both operands, also the second operand of a `cmp`
     /// instruction can't be a constant either.
     pub fn rewrite_cmp(&mut self) -> &mut Self {
-        let mut new_instructions: Vec<Instruction> = Vec::new();
-
-        for instruction in &self.instructions {
-            if let Instruction::Cmp(a, b) = instruction {
-                if matches!(a, Operand::Stack(_)) && matches!(b, Operand::Stack(_)) {
-                    new_instructions.push(Instruction::Mov {
-                        src: a.clone(),
-                        dst: Operand::Register(Reg::R10),
-                    });
-                    new_instructions.push(Instruction::Cmp(Operand::Register(Reg::R10), b.clone()));
-                } else if matches!(b, Operand::Imm(_)) {
-                    new_instructions.push(Instruction::Mov {
-                        src: b.clone(),
-                        dst: Operand::Register(Reg::R11),
-                    });
-                    new_instructions.push(Instruction::Cmp(a.clone(), Operand::Register(Reg::R11)));
+        self.rewrite(|_, instructions| {
+            let mut new_instructions: Vec<Instruction> = Vec::new();
+
+            for instruction in instructions {
+                if let Instruction::Cmp(a, b) = instruction {
+                    if a.is_memory() && b.is_memory() {
+                        new_instructions.push(Instruction::Mov {
+                            src: a.clone(),
+                            dst: Operand::Register(Reg::R10),
+                        });
+                        new_instructions
+                            .push(Instruction::Cmp(Operand::Register(Reg::R10), b.clone()));
+                    } else if matches!(b, Operand::Imm(_)) {
+                        new_instructions.push(Instruction::Mov {
+                            src: b.clone(),
+                            dst: Operand::Register(Reg::R11),
+                        });
+                        new_instructions
+                            .push(Instruction::Cmp(a.clone(), Operand::Register(Reg::R11)));
+                    } else {
+                        new_instructions.push(instruction.clone())
+                    }
                 } else {
-                    new_instructions.push(instruction.clone())
+                    new_instructions.push(instruction.clone());
                 }
-            } else {
-                new_instructions.push(instruction.clone());
             }
-        }
 
-        self.instructions = new_instructions;
-        self
+            new_instructions
+        })
     }
 
     /// Explores the instruction set and rewrites
@@ -186,125 +283,264 @@ impl AssemblyPass {
     /// following restrictions:
     ///
     /// 1. The `add` and `sub` instructions, like `mov`, can't use
-    ///     memory addresses as both the source and destination operands.
+    ///    memory addresses as both the source and destination operands.
     ///
     /// 2. The `imul` instruction can't use a memory address as its
-    ///     destination, regardless of its source operand.
-    ///     To fix an instructions destination operand, we use the `R11` register
-    ///     instead of `R10`.
-    ///     To fix `imul` we load the destination into R11, multiply it by the source
-    ///     operand, and then store the result back to the destination address.
+    ///    destination, regardless of its source operand.
+    ///    To fix an instructions destination operand, we use the `R11` register
+    ///    instead of `R10`.
+    ///    To fix `imul` we load the destination into R11, multiply it by the source
+    ///    operand, and then store the result back to the destination address.
+    ///
+    /// 3. Whenever `idiv` or `div` needs to operate on a constant, we copy that constant into
+    ///    the `R10` register first.
     ///
-    /// 3. Whenever `idiv` needs to operate on a constant, we copy that constant into
-    ///     the `R10` register first.
+    /// 4. The `cmov` instruction can't take a constant source, which goes through
+    ///    `R10`, nor a memory destination, which goes through `R11` like `imul`.
     pub fn rewrite_binop(&mut self) -> &mut Self {
-        let mut new_instructions: Vec<Instruction> = Vec::new();
-
-        for instruction in &self.instructions {
-            match instruction {
-                Instruction::Idiv(operand) => {
-                    new_instructions.push(Instruction::Mov {
-                        src: operand.clone(),
-                        dst: Operand::Register(Reg::R10),
-                    });
-                    new_instructions.push(Instruction::Idiv(Operand::Register(Reg::R10)));
-                }
-                Instruction::Binary(operator, src, dst) => match operator {
-                    BinaryOperator::Add => {
+        self.rewrite(|_, instructions| {
+            let mut new_instructions: Vec<Instruction> = Vec::new();
+
+            for instruction in instructions {
+                match instruction {
+                    Instruction::Idiv(operand) => {
                         new_instructions.push(Instruction::Mov {
-                            src: src.clone(),
+                            src: operand.clone(),
                             dst: Operand::Register(Reg::R10),
                         });
-
-                        new_instructions.push(Instruction::Binary(
-                            BinaryOperator::Add,
-                            Operand::Register(Reg::R10),
-                            dst.clone(),
-                        ));
+                        new_instructions.push(Instruction::Idiv(Operand::Register(Reg::R10)));
                     }
-                    BinaryOperator::Sub => {
+                    Instruction::Div(operand) => {
                         new_instructions.push(Instruction::Mov {
-                            src: src.clone(),
+                            src: operand.clone(),
                             dst: Operand::Register(Reg::R10),
                         });
+                        new_instructions.push(Instruction::Div(Operand::Register(Reg::R10)));
+                    }
+                    Instruction::CmovCC(cond, src, dst) => {
+                        let src = match src {
+                            Operand::Imm(_) => {
+                                new_instructions.push(Instruction::Mov {
+                                    src: src.clone(),
+                                    dst: Operand::Register(Reg::R10),
+                                });
+                                Operand::Register(Reg::R10)
+                            }
+                            src => src.clone(),
+                        };
 
-                        new_instructions.push(Instruction::Binary(
-                            BinaryOperator::Sub,
-                            Operand::Register(Reg::R10),
-                            dst.clone(),
-                        ));
+                        if matches!(dst, Operand::Register(_)) {
+                            new_instructions.push(Instruction::CmovCC(
+                                cond.clone(),
+                                src,
+                                dst.clone(),
+                            ));
+                        } else {
+                            new_instructions.push(Instruction::Mov {
+                                src: dst.clone(),
+                                dst: Operand::Register(Reg::R11),
+                            });
+                            new_instructions.push(Instruction::CmovCC(
+                                cond.clone(),
+                                src,
+                                Operand::Register(Reg::R11),
+                            ));
+                            new_instructions.push(Instruction::Mov {
+                                src: Operand::Register(Reg::R11),
+                                dst: dst.clone(),
+                            });
+                        }
                     }
-                    BinaryOperator::Mult => {
-                        new_instructions.push(Instruction::Mov {
-                            src: dst.clone(),
-                            dst: Operand::Register(Reg::R11),
-                        });
+                    Instruction::Binary(operator, src, dst) => match operator {
+                        BinaryOperator::Add => {
+                            new_instructions.push(Instruction::Mov {
+                                src: src.clone(),
+                                dst: Operand::Register(Reg::R10),
+                            });
 
-                        new_instructions.push(Instruction::Binary(
-                            BinaryOperator::Mult,
-                            src.clone(),
-                            Operand::Register(Reg::R11),
-                        ));
+                            new_instructions.push(Instruction::Binary(
+                                BinaryOperator::Add,
+                                Operand::Register(Reg::R10),
+                                dst.clone(),
+                            ));
+                        }
+                        BinaryOperator::Sub => {
+                            new_instructions.push(Instruction::Mov {
+                                src: src.clone(),
+                                dst: Operand::Register(Reg::R10),
+                            });
 
-                        new_instructions.push(Instruction::Mov {
-                            src: Operand::Register(Reg::R11),
-                            dst: dst.clone(),
-                        });
-                    }
-                    _ => unimplemented!(),
-                },
-                _ => new_instructions.push(instruction.clone()),
+                            new_instructions.push(Instruction::Binary(
+                                BinaryOperator::Sub,
+                                Operand::Register(Reg::R10),
+                                dst.clone(),
+                            ));
+                        }
+                        BinaryOperator::Mult => {
+                            new_instructions.push(Instruction::Mov {
+                                src: dst.clone(),
+                                dst: Operand::Register(Reg::R11),
+                            });
+
+                            new_instructions.push(Instruction::Binary(
+                                BinaryOperator::Mult,
+                                src.clone(),
+                                Operand::Register(Reg::R11),
+                            ));
+
+                            new_instructions.push(Instruction::Mov {
+                                src: Operand::Register(Reg::R11),
+                                dst: dst.clone(),
+                            });
+                        }
+                        // The count is either an immediate byte or `CL`.
+                        BinaryOperator::Sal | BinaryOperator::Sar | BinaryOperator::Shr => {
+                            let count = match src {
+                                Operand::Imm(count) if (0..=255).contains(count) => src.clone(),
+                                _ => {
+                                    new_instructions.push(Instruction::Mov {
+                                        src: src.clone(),
+                                        dst: Operand::Register(Reg::CX),
+                                    });
+                                    Operand::Register(Reg::CX)
+                                }
+                            };
+
+                            new_instructions.push(Instruction::Binary(
+                                operator.clone(),
+                                count,
+                                dst.clone(),
+                            ));
+                        }
+                        _ => unimplemented!(),
+                    },
+                    _ => new_instructions.push(instruction.clone()),
+                }
             }
+            new_instructions
+        })
+    }
+
+    /// Runs `rewrite` over the instructions of every function,
+    /// replacing them with the returned ones.
+    fn rewrite(&mut self, rewrite: impl Fn(&Self, &Instructions) -> Instructions) -> &mut Self {
+        let mut functions = mem::take(&mut self.program.0);
+
+        for function in &mut functions {
+            function.instructions = rewrite(self, &function.instructions);
         }
-        self.instructions = new_instructions;
+
+        self.program.0 = functions;
         self
     }
 
-    /// Pushes a new AllocateStack to the front of the instruction stream.
+    /// Returns the size of the stack frame required by a
+    /// function's instructions, that is, the deepest stack
+    /// slot referenced by any instruction. Arguments passed on
+    /// the stack belong to the frame of the caller.
+    fn stack_size(instructions: &Instructions) -> i64 {
+        instructions
+            .iter()
+            .flat_map(|instruction| instruction.operands())
+            .filter_map(|operand| match operand {
+                Operand::Stack(s) if *s > 0 => Some(*s),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Pushes a new AllocateStack to the front of each function.
+    ///
+    /// The frame size is computed from the final instruction stream
+    /// so slots introduced by previous passes are accounted for, and
+    /// rounded up to keep the stack pointer 16 byte aligned.
     pub fn allocate_stack(&mut self) -> &mut Self {
-        let mut new_instructions: VecDeque<Instruction> = VecDeque::from(self.instructions.clone());
+        self.rewrite(|_, instructions| {
+            let mut new_instructions: VecDeque<Instruction> = VecDeque::from(instructions.clone());
+
+            let frame_size = (Self::stack_size(instructions) + 15) / 16 * 16;
+            new_instructions.push_front(Instruction::AllocateStack(frame_size));
+
+            new_instructions.into()
+        })
+    }
 
-        new_instructions.push_front(Instruction::AllocateStack(self.offset));
+    /// Runs every pass in order, calling `inspect` with the
+    /// name of each pass and the program it produced.
+    pub fn run_passes(&mut self, mut inspect: impl FnMut(&str, &Program)) -> &mut Self {
+        let mut passes: Vec<(&str, Pass)> = vec![
+            ("ReplacePseudoRegisters", Self::replace_pseudo_registers),
+            ("RewriteMov", Self::rewrite_mov),
+            ("RemoveRedundantMov", Self::remove_redundant_mov),
+            ("RewriteBinaryOp", Self::rewrite_binop),
+            ("RewriteCmp", Self::rewrite_cmp),
+            ("AllocateStack", Self::allocate_stack),
+        ];
+        if self.register_allocation {
+            passes.insert(0, ("AllocateRegisters", Self::allocate_registers));
+        }
 
-        self.instructions = new_instructions.into();
+        for (name, pass) in passes {
+            pass(self);
+            inspect(name, &self.program);
+        }
 
         self
     }
 
-    /// Replaces the instruction set on
-    /// the original program and returns
-    /// the modified instance*.
-    pub fn modify_program(&mut self) -> Program {
-        self.program.0.instructions = self.instructions.clone();
-
+    /// Returns the modified program, which ends a chain of
+    /// passes.
+    pub fn modify_program(&self) -> Program {
         self.program.clone()
     }
 }
 
+/// External functions that can be called without being
+/// declared, along with their number of arguments.
+pub const EXTERN_FUNCTIONS: &[(&str, usize)] =
+    &[("abort", 0), ("exit", 1), ("getchar", 0), ("putchar", 1)];
+
 /// This takes an ast program an performs variable
-/// resolution on its block items.
+/// resolution on the block items of each function.
 ///
 /// This pass tracks which variables are in
 /// scope throughout the program and resolves each reference to
 /// a variable by finding the corresponding declaration.
 ///
 /// It reports an error if a program declares the same variable
-/// more than once or uses a variable that hasn't been delcared.
+/// more than once or uses a variable that hasn't been delcared,
+/// and a division or remainder by a constant zero where it is
+/// always evaluated.
 ///
 /// It renames each local variable with a globally unique
 /// identifier.
 pub struct VariableResolution {
-    block_items: ast::BlockItems,
+    program: ast::Program,
     variable_map: HashMap<Identifier, String>,
+    /// Variables declared in the innermost block.
+    current_scope: HashSet<Identifier>,
     offset: usize,
+    /// Placed between a variable name and its counter, see
+    /// [`VariableResolution::separator`].
+    separator: char,
+    /// Number of arguments of each function that can be called.
+    functions: HashMap<Identifier, usize>,
+    /// Types of the variables declared so far, by unique name.
+    types: HashMap<Identifier, ast::Type>,
+    /// Values of the enumerators declared so far, by unique name.
+    constants: HashMap<Identifier, i64>,
+    /// Number of enclosing operands that may never be evaluated,
+    /// see [`VariableResolution::resolve_unevaluated`].
+    unevaluated: Cell<usize>,
 }
 
 impl Debug for VariableResolution {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Variable Resolution: \nblock_items: {:#?}\n\nvariables_map: {:#?}",
-            &self.block_items, &self.variable_map
+            "Variable Resolution: \nprogram: {:#?}\n\nvariables_map: {:#?}",
+            &self.program, &self.variable_map
         )
         // f.debug_struct("VariableResolution")
         //     .field("block_items", &self.block_items)
@@ -317,29 +553,61 @@ impl Debug for VariableResolution {
 impl From<ast::Program> for VariableResolution {
     fn from(value: ast::Program) -> Self {
         VariableResolution {
-            block_items: value.0.body,
+            program: value,
             variable_map: HashMap::new(),
+            current_scope: HashSet::new(),
             offset: 0,
+            separator: '.',
+            functions: EXTERN_FUNCTIONS
+                .iter()
+                .map(|(name, arity)| (Identifier::from(*name), *arity))
+                .collect(),
+            types: HashMap::new(),
+            constants: HashMap::new(),
+            unevaluated: Cell::new(0),
         }
     }
 }
 
 impl VariableResolution {
+    /// Sets the separator used when renaming variables, `.` by
+    /// default. Names that end up as assembler symbols should use
+    /// a separator such as `$`, since `.` needs care in symbols.
+    pub fn separator(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Allows calls to an external function taking `arity`
+    /// arguments, besides the ones in [`EXTERN_FUNCTIONS`].
+    pub fn extern_function(mut self, name: impl Into<Identifier>, arity: usize) -> Self {
+        self.functions.insert(name.into(), arity);
+        self
+    }
+
     fn resolve_declaration(&mut self, declaration: ast::Declaration) -> Result<ast::Declaration> {
-        if self.variable_map.contains_key(&declaration.name) {
+        if self.current_scope.contains(&declaration.name) {
             return Err(Error::DuplicateVarDeclaration {
                 var: declaration.name,
             });
         }
 
         let unique_name = self.make_temporary_name(&declaration.name.0);
+        self.types
+            .insert(unique_name.clone().into(), declaration.ty);
+        self.current_scope.insert(declaration.name.clone());
         self.variable_map
             .insert(declaration.name, unique_name.clone());
         if let Some(init) = declaration.initializer {
-            let initializer = Some(self.resolve_expression(init)?);
+            let init = self.resolve_expression(init)?;
+            if self.is_pointer(&init) != (declaration.ty == ast::Type::Pointer) {
+                return Err(Error::PointerMismatch { value: init });
+            }
+            let initializer = Some(init);
 
             return Ok(Declaration {
                 name: unique_name.into(),
+                ty: declaration.ty,
                 initializer,
             });
         }
@@ -349,89 +617,499 @@ impl VariableResolution {
         // initializer we got from `resolve_expression`
         Ok(Declaration {
             name: unique_name.into(),
+            ty: declaration.ty,
             initializer: declaration.initializer,
         })
     }
 
+    /// Enumerators share the scope of variables, and resolve to
+    /// their value wherever they are used.
+    fn resolve_enum(&mut self, enumeration: ast::EnumDeclaration) -> Result<ast::EnumDeclaration> {
+        let mut enumerators = Vec::new();
+
+        for (name, value) in enumeration.enumerators {
+            if self.current_scope.contains(&name) {
+                return Err(Error::DuplicateVarDeclaration { var: name });
+            }
+
+            let unique_name = self.make_temporary_name(&name.0);
+            self.constants.insert(unique_name.clone().into(), value);
+            self.current_scope.insert(name.clone());
+            self.variable_map.insert(name, unique_name.clone());
+            enumerators.push((unique_name.into(), value));
+        }
+
+        Ok(ast::EnumDeclaration {
+            tag: enumeration.tag,
+            enumerators,
+        })
+    }
+
+    /// Resolves an operand that may never be evaluated, such as
+    /// the right operand of `&&`. Dividing by zero within it is
+    /// left to the runtime, as only evaluating it is undefined.
+    fn resolve_unevaluated<T>(&self, resolve: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        self.unevaluated.set(self.unevaluated.get() + 1);
+        let result = resolve(self);
+        self.unevaluated.set(self.unevaluated.get() - 1);
+
+        result
+    }
+
     // TODO: Check if this assignement doesnt conflict with other assignements.
     fn make_temporary_name(&mut self, name: &str) -> String {
         self.offset += 1;
-        format!("{}.{}", name, self.offset)
+        format!("{}{}{}", name, self.separator, self.offset)
     }
 
-    pub fn pass(&mut self) -> Result<&mut Self> {
-        let blocks: ast::BlockItems = self.block_items.clone();
-        let mut new_blocks = Vec::new();
-        for block in blocks {
-            new_blocks.push(match block {
-                ast::BlockItem::S(statement) => {
-                    ast::BlockItem::S(self.resolve_statement(statement)?)
+    /// File scope variables keep their names, since they are
+    /// assembler symbols, and must be initialized to constants,
+    /// which file scope enumerators are.
+    fn resolve_globals(&mut self) -> Result<HashMap<Identifier, String>> {
+        self.variable_map.clear();
+        self.current_scope.clear();
+        for enumeration in mem::take(&mut self.program.2) {
+            let enumeration = self.resolve_enum(enumeration)?;
+            self.program.2.push(enumeration);
+        }
+        let mut globals = mem::take(&mut self.variable_map);
+
+        if let Some(function) = self
+            .program
+            .0
+            .iter()
+            .find(|function| globals.contains_key(&function.name))
+        {
+            return Err(Error::DuplicateVarDeclaration {
+                var: function.name.clone(),
+            });
+        }
+
+        let mut program_globals = mem::take(&mut self.program.1);
+        for global in &mut program_globals {
+            self.variable_map = globals.clone();
+            global.initializer = global
+                .initializer
+                .take()
+                .map(|initializer| self.resolve_expression(initializer))
+                .transpose()?;
+        }
+        self.program.1 = program_globals;
+
+        for global in &self.program.1 {
+            let is_function = self
+                .program
+                .0
+                .iter()
+                .any(|function| function.name == global.name);
+
+            if is_function || globals.contains_key(&global.name) {
+                return Err(Error::DuplicateVarDeclaration {
+                    var: global.name.clone(),
+                });
+            }
+
+            match global.ty {
+                ast::Type::Array(_) => {
+                    return Err(Error::Unsupported {
+                        feature: "file scope arrays",
+                    })
                 }
-                ast::BlockItem::D(declaration) => {
-                    ast::BlockItem::D(self.resolve_declaration(declaration)?)
+                ast::Type::Pointer => {
+                    return Err(Error::Unsupported {
+                        feature: "file scope pointers",
+                    })
                 }
+                _ => {}
+            }
+
+            if let Some(initializer) = &global.initializer {
+                if initializer.constant_value().is_none() {
+                    return Err(Error::NonConstantInitializer {
+                        var: global.name.clone(),
+                        initializer: initializer.clone(),
+                    });
+                }
+            }
+
+            globals.insert(global.name.clone(), global.name.0.clone());
+        }
+
+        Ok(globals)
+    }
+
+    pub fn pass(&mut self) -> Result<&mut Self> {
+        let mut functions = self.program.0.clone();
+        let globals = self.resolve_globals()?;
+
+        // Functions of the program can be called from any of them.
+        // Each one is defined at most once, and its declarations
+        // must agree with its definition and with the external
+        // function it may declare.
+        let mut defined = HashSet::new();
+        let mut return_types = HashMap::new();
+        let declarations = self
+            .program
+            .3
+            .iter()
+            .map(|declaration| {
+                (
+                    &declaration.name,
+                    declaration.return_type,
+                    declaration.params.len(),
+                    false,
+                )
             })
+            .chain(functions.iter().map(|function| {
+                (
+                    &function.name,
+                    function.return_type,
+                    function.params.len(),
+                    true,
+                )
+            }));
+        for (name, return_type, arity, definition) in declarations {
+            let conflicting_arity = self
+                .functions
+                .get(name)
+                .is_some_and(|&expected| expected != arity);
+            let conflicting_type =
+                *return_types.entry(name.clone()).or_insert(return_type) != return_type;
+
+            if (definition && !defined.insert(name.clone()))
+                || conflicting_arity
+                || conflicting_type
+            {
+                return Err(Error::ConflictingDeclaration { name: name.clone() });
+            }
+            self.functions.insert(name.clone(), arity);
         }
-        self.block_items = new_blocks;
+
+        for global in &self.program.1 {
+            self.types.insert(global.name.clone(), global.ty);
+        }
+
+        for function in &mut functions {
+            // Local variables are only visible within their function,
+            // and may shadow the file scope ones.
+            self.variable_map = globals.clone();
+            self.current_scope.clear();
+
+            // Parameters share the scope of the outermost block of
+            // the body.
+            function.params = mem::take(&mut function.params)
+                .into_iter()
+                .map(|param| self.resolve_declaration(param))
+                .collect::<Result<_>>()?;
+            function.body = self.resolve_block(mem::take(&mut function.body))?;
+        }
+
+        self.program.0 = functions;
         Ok(self)
     }
 
-    pub fn get_updated_block_items(&mut self) -> Result<ast::BlockItems> {
+    /// Blocks nested right within a block are resolved through an
+    /// explicit stack rather than recursion, so deeply nested blocks
+    /// can't overflow the stack.
+    fn resolve_block(&mut self, blocks: ast::BlockItems) -> Result<ast::BlockItems> {
+        // Each block holds its remaining items, its resolved items
+        // and the scope to restore once it ends.
+        let mut stack = vec![(blocks.into_iter(), Vec::new(), None)];
+
+        while let Some((items, new_blocks, _)) = stack.last_mut() {
+            match items.next() {
+                Some(ast::BlockItem::S(ast::Statement::Compound(inner))) => {
+                    let scope = (
+                        self.variable_map.clone(),
+                        mem::take(&mut self.current_scope),
+                    );
+                    stack.push((inner.into_iter(), Vec::new(), Some(scope)));
+                }
+                Some(ast::BlockItem::S(statement)) => {
+                    new_blocks.push(ast::BlockItem::S(self.resolve_statement(statement)?))
+                }
+                Some(ast::BlockItem::D(declaration)) => {
+                    new_blocks.push(ast::BlockItem::D(self.resolve_declaration(declaration)?))
+                }
+                Some(ast::BlockItem::E(enumeration)) => {
+                    new_blocks.push(ast::BlockItem::E(self.resolve_enum(enumeration)?))
+                }
+                Some(line @ ast::BlockItem::Line(_)) => new_blocks.push(line),
+                None => {
+                    let Some((_, new_blocks, scope)) = stack.pop() else {
+                        unreachable!("The stack holds the current block")
+                    };
+                    let (Some((variable_map, current_scope)), Some((_, outer, _))) =
+                        (scope, stack.last_mut())
+                    else {
+                        return Ok(new_blocks);
+                    };
+
+                    self.variable_map = variable_map;
+                    self.current_scope = current_scope;
+                    outer.push(ast::BlockItem::S(ast::Statement::Compound(new_blocks)));
+                }
+            }
+        }
+
+        unreachable!("The outermost block returns once it ends")
+    }
+
+    /// Returns the resolved body of each function.
+    pub fn get_updated_block_items(&mut self) -> Result<Vec<ast::BlockItems>> {
+        Ok(self
+            .pass()?
+            .program
+            .0
+            .iter()
+            .map(|function| function.body.clone())
+            .collect())
+    }
+
+    pub fn get_updated_program(&mut self) -> Result<ast::Program> {
         // TODO: Avoid cloning
-        Ok(self.pass()?.block_items.clone())
+        Ok(self.pass()?.program.clone())
     }
 
     fn resolve_expression(&self, expression: ast::Expression) -> Result<ast::Expression> {
         match expression {
             ast::Expression::Assignment(left, right) => {
-                if !matches!(*left, ast::Expression::Var(_)) {
+                if !left.is_lvalue() {
                     Err(Error::InvalidLVal { value: *left })
                 } else {
-                    Ok(ast::Expression::Assignment(
-                        Box::new(self.resolve_expression(*left)?),
-                        Box::new(self.resolve_expression(*right)?),
-                    ))
+                    let value = (*left).clone();
+                    let left = self.resolve_expression(*left)?;
+                    // Enumerators resolve to constants.
+                    if !left.is_lvalue() {
+                        return Err(Error::InvalidLVal { value });
+                    }
+                    let right = self.resolve_expression(*right)?;
+
+                    if self.is_pointer(&left) != self.is_pointer(&right) {
+                        return Err(Error::PointerMismatch { value: right });
+                    }
+                    Ok(ast::Expression::Assignment(Box::new(left), Box::new(right)))
                 }
             }
+            // Only the address of an `int` can be taken, and `&*p`
+            // is `p` itself.
+            ast::Expression::AddrOf(inner) => match *inner {
+                ast::Expression::Var(v) => {
+                    let name = self.resolve_variable(v.clone())?;
+                    if self.constants.contains_key(&name) {
+                        return Err(Error::InvalidLVal {
+                            value: ast::Expression::Var(v),
+                        });
+                    }
+
+                    match self.types.get(&name) {
+                        Some(ast::Type::Int) => Ok(ast::Expression::AddrOf(Box::new(
+                            ast::Expression::Var(name),
+                        ))),
+                        Some(ast::Type::Array(_)) => Err(Error::ArrayValue { name: v }),
+                        _ => Err(Error::Unsupported {
+                            feature: "pointers to types other than int",
+                        }),
+                    }
+                }
+                inner @ ast::Expression::Deref(_) => Ok(ast::Expression::AddrOf(Box::new(
+                    self.resolve_expression(inner)?,
+                ))),
+                ast::Expression::Subscript(..) => Err(Error::Unsupported {
+                    feature: "pointers to array elements",
+                }),
+                inner => Err(Error::InvalidLVal { value: inner }),
+            },
+            ast::Expression::Deref(inner) => {
+                let inner = self.resolve_expression(*inner)?;
+
+                if !self.is_pointer(&inner) {
+                    return Err(Error::PointerMismatch { value: inner });
+                }
+                Ok(ast::Expression::Deref(Box::new(inner)))
+            }
             ast::Expression::Var(v) => {
-                if self.variable_map.contains_key(&v) {
-                    // It is safe to unwrap since we already know the map contains the key
-                    Ok(ast::Expression::Var(
-                        self.variable_map.get(&v).unwrap().into(),
-                    ))
+                let name = self.resolve_variable(v.clone())?;
+
+                // Arrays don't decay to pointers, since there are
+                // none yet.
+                if let Some(ast::Type::Array(_)) = self.types.get(&name) {
+                    Err(Error::ArrayValue { name: v })
+                } else if let Some(value) = self.constants.get(&name) {
+                    Ok(ast::Expression::Constant(*value))
                 } else {
-                    Err(Error::UndeclaredVar { value: v })
+                    Ok(ast::Expression::Var(name))
                 }
             }
+            ast::Expression::Subscript(array, index) => match *array {
+                ast::Expression::Var(v) => {
+                    let name = self.resolve_variable(v.clone())?;
+
+                    if !matches!(self.types.get(&name), Some(ast::Type::Array(_))) {
+                        return Err(Error::NotAnArray {
+                            value: ast::Expression::Var(v),
+                        });
+                    }
+
+                    Ok(ast::Expression::Subscript(
+                        Box::new(ast::Expression::Var(name)),
+                        Box::new(self.resolve_value(*index)?),
+                    ))
+                }
+                array => Err(Error::NotAnArray { value: array }),
+            },
             ast::Expression::Constant(i) => Ok(ast::Expression::Constant(i)),
-            ast::Expression::Unary(o, e) => Ok(ast::Expression::Unary(
-                o,
-                Box::new(self.resolve_expression(*e)?),
-            )),
-            ast::Expression::Binary(o, a, b) => Ok(ast::Expression::Binary(
-                o,
-                Box::new(self.resolve_expression(*a)?),
-                Box::new(self.resolve_expression(*b)?),
-            )),
+            ast::Expression::UnsignedConstant(i) => Ok(ast::Expression::UnsignedConstant(i)),
+            ast::Expression::SizeOfType(ty) => Ok(ast::Expression::SizeOfType(ty)),
+            // The operand is never evaluated, but its variables
+            // must be declared. Its size is the only use of an
+            // array besides subscripting it.
+            ast::Expression::SizeOf(e) => match *e {
+                ast::Expression::Var(v) if !self.is_constant(&v)? => Ok(ast::Expression::SizeOf(
+                    Box::new(ast::Expression::Var(self.resolve_variable(v)?)),
+                )),
+                e => Ok(ast::Expression::SizeOf(Box::new(
+                    self.resolve_unevaluated(|resolution| resolution.resolve_expression(e))?,
+                ))),
+            },
+            ast::Expression::Unary(o, e) => {
+                Ok(ast::Expression::Unary(o, Box::new(self.resolve_value(*e)?)))
+            }
+            ast::Expression::Binary(o, a, b) => {
+                // The diagnostic shows the division as written, with
+                // the names of the source.
+                let division = (self.unevaluated.get() == 0
+                    && matches!(
+                        o,
+                        ast::BinaryOperator::Divide | ast::BinaryOperator::Remainder
+                    ))
+                .then(|| ast::Expression::Binary(o.clone(), a.clone(), b.clone()));
+                let a = self.resolve_value(*a)?;
+                let b = match o {
+                    ast::BinaryOperator::And | ast::BinaryOperator::Or => {
+                        self.resolve_unevaluated(|resolution| resolution.resolve_value(*b))?
+                    }
+                    _ => self.resolve_value(*b)?,
+                };
+                // Enumerators are constants once resolved, so they
+                // are caught too.
+                let divides_by_zero = matches!(b, ast::Expression::UnsignedConstant(0))
+                    || eval_const(&b).is_ok_and(|divisor| divisor == 0);
+
+                match division {
+                    Some(expression) if divides_by_zero => {
+                        Err(Error::DivisionByZero { expression })
+                    }
+                    _ => Ok(ast::Expression::Binary(o, Box::new(a), Box::new(b))),
+                }
+            }
             ast::Expression::Conditional {
                 condition,
                 exp1,
                 exp2,
             } => Ok(ast::Expression::Conditional {
-                condition: Box::new(self.resolve_expression(*condition)?),
-                exp1: Box::new(self.resolve_expression(*exp1)?),
-                exp2: Box::new(self.resolve_expression(*exp2)?),
+                condition: Box::new(self.resolve_value(*condition)?),
+                exp1: Box::new(
+                    self.resolve_unevaluated(|resolution| resolution.resolve_value(*exp1))?,
+                ),
+                exp2: Box::new(
+                    self.resolve_unevaluated(|resolution| resolution.resolve_value(*exp2))?,
+                ),
             }),
+            ast::Expression::Comma(left, right) => Ok(ast::Expression::Comma(
+                Box::new(self.resolve_expression(*left)?),
+                Box::new(self.resolve_expression(*right)?),
+            )),
+            ast::Expression::FunctionCall { name, arguments } => {
+                // A variable in scope hides the function, as in C.
+                if self.variable_map.contains_key(&name) {
+                    return Err(Error::NotAFunction { name });
+                }
+
+                let Some(&expected) = self.functions.get(&name) else {
+                    return Err(Error::UndeclaredFunction { name });
+                };
+
+                if arguments.len() != expected {
+                    return Err(Error::ArgumentCount {
+                        name,
+                        expected,
+                        found: arguments.len(),
+                    });
+                }
+
+                Ok(ast::Expression::FunctionCall {
+                    name,
+                    arguments: arguments
+                        .into_iter()
+                        .map(|argument| self.resolve_value(argument))
+                        .collect::<Result<_>>()?,
+                })
+            }
+        }
+    }
+
+    /// Resolves an expression whose value must be an integer,
+    /// such as an operand or a condition. Pointers can only be
+    /// dereferenced, assigned and given to `sizeof`.
+    fn resolve_value(&self, expression: ast::Expression) -> Result<ast::Expression> {
+        let expression = self.resolve_expression(expression)?;
+
+        if self.is_pointer(&expression) {
+            return Err(Error::PointerMismatch { value: expression });
         }
+        Ok(expression)
     }
 
-    fn resolve_statement(&self, statement: ast::Statement) -> Result<ast::Statement> {
+    /// Returns true if a resolved expression evaluates to a
+    /// pointer, conditionals never do since their operands are
+    /// values.
+    fn is_pointer(&self, expression: &ast::Expression) -> bool {
+        match expression {
+            ast::Expression::Var(name) => self.types.get(name) == Some(&ast::Type::Pointer),
+            ast::Expression::AddrOf(_) => true,
+            ast::Expression::Assignment(left, _) => self.is_pointer(left),
+            ast::Expression::Comma(_, right) => self.is_pointer(right),
+            _ => false,
+        }
+    }
+
+    /// Returns the unique name of a variable in scope.
+    fn is_constant(&self, name: &Identifier) -> Result<bool> {
+        Ok(self
+            .constants
+            .contains_key(&self.resolve_variable(name.clone())?))
+    }
+
+    fn resolve_variable(&self, name: Identifier) -> Result<Identifier> {
+        match self.variable_map.get(&name) {
+            Some(unique_name) => Ok(unique_name.into()),
+            None => Err(Error::UndeclaredVar { value: name }),
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: ast::Statement) -> Result<ast::Statement> {
         match statement {
-            ast::Statement::Return(e) => Ok(ast::Statement::Return(self.resolve_expression(e)?)),
+            ast::Statement::Return(e) => Ok(ast::Statement::Return(
+                e.map(|e| self.resolve_value(e)).transpose()?,
+            )),
             ast::Statement::Expression(e) => {
                 Ok(ast::Statement::Expression(self.resolve_expression(e)?))
             }
             ast::Statement::Null => Ok(ast::Statement::Null),
+            ast::Statement::Compound(block_items) => {
+                // Variables declared within the block shadow the outer
+                // ones, and are forgotten once the block ends.
+                let variable_map = self.variable_map.clone();
+                let current_scope = mem::take(&mut self.current_scope);
+
+                let block_items = self.resolve_block(block_items);
+
+                self.variable_map = variable_map;
+                self.current_scope = current_scope;
+
+                Ok(ast::Statement::Compound(block_items?))
+            }
             ast::Statement::If {
                 condition,
                 then,
@@ -444,11 +1122,511 @@ impl VariableResolution {
                 };
 
                 Ok(ast::Statement::If {
-                    condition: self.resolve_expression(condition)?,
+                    condition: self.resolve_value(condition)?,
                     then: Box::new(self.resolve_statement(*then)?),
                     else_statement: else_stmt,
                 })
             }
+            ast::Statement::Break(label) => Ok(ast::Statement::Break(label)),
+            ast::Statement::Continue(label) => Ok(ast::Statement::Continue(label)),
+            ast::Statement::While {
+                condition,
+                body,
+                label,
+            } => Ok(ast::Statement::While {
+                condition: self.resolve_value(condition)?,
+                body: Box::new(self.resolve_statement(*body)?),
+                label,
+            }),
+            ast::Statement::DoWhile {
+                body,
+                condition,
+                label,
+            } => Ok(ast::Statement::DoWhile {
+                body: Box::new(self.resolve_statement(*body)?),
+                condition: self.resolve_value(condition)?,
+                label,
+            }),
+            ast::Statement::For {
+                init,
+                condition,
+                post,
+                body,
+                label,
+            } => {
+                // The header of the loop introduces a new scope,
+                // wrapping the one of its body.
+                let variable_map = self.variable_map.clone();
+                let current_scope = mem::take(&mut self.current_scope);
+
+                let statement = self.resolve_for(init, condition, post, *body, label);
+
+                self.variable_map = variable_map;
+                self.current_scope = current_scope;
+
+                statement
+            }
+        }
+    }
+
+    fn resolve_for(
+        &mut self,
+        init: ast::ForInit,
+        condition: Option<ast::Expression>,
+        post: Option<ast::Expression>,
+        body: ast::Statement,
+        label: Option<Identifier>,
+    ) -> Result<ast::Statement> {
+        let init = match init {
+            // Each variable is in the scope of the ones following it.
+            ast::ForInit::InitDecl(declarations) => ast::ForInit::InitDecl(
+                declarations
+                    .into_iter()
+                    .map(|declaration| self.resolve_declaration(declaration))
+                    .collect::<Result<_>>()?,
+            ),
+            ast::ForInit::InitExp(expression) => {
+                ast::ForInit::InitExp(self.resolve_optional_expression(expression)?)
+            }
+        };
+
+        Ok(ast::Statement::For {
+            init,
+            condition: condition.map(|e| self.resolve_value(e)).transpose()?,
+            post: self.resolve_optional_expression(post)?,
+            body: Box::new(self.resolve_statement(body)?),
+            label,
+        })
+    }
+
+    fn resolve_optional_expression(
+        &self,
+        expression: Option<ast::Expression>,
+    ) -> Result<Option<ast::Expression>> {
+        expression.map(|e| self.resolve_expression(e)).transpose()
+    }
+}
+
+/// Annotates each loop with a unique label, and each `break`
+/// and `continue` statement with the label of its enclosing
+/// loop.
+///
+/// It reports an error if a `break` or `continue` statement
+/// appears outside of a loop.
+#[derive(Debug)]
+pub struct LoopLabeling {
+    program: ast::Program,
+    offset: usize,
+    /// Name of the function being labeled, reported along with
+    /// misplaced `break` and `continue` statements.
+    function: Identifier,
+}
+
+impl From<ast::Program> for LoopLabeling {
+    fn from(value: ast::Program) -> Self {
+        LoopLabeling {
+            program: value,
+            offset: 0,
+            function: Identifier::default(),
+        }
+    }
+}
+
+impl LoopLabeling {
+    pub fn pass(&mut self) -> Result<&mut Self> {
+        let mut functions = self.program.0.clone();
+
+        for function in &mut functions {
+            self.function = function.name.clone();
+            // Function bodies are never within a loop.
+            function.body = self.label_block(mem::take(&mut function.body), None)?;
+        }
+
+        self.program.0 = functions;
+        Ok(self)
+    }
+
+    pub fn get_updated_program(&mut self) -> Result<ast::Program> {
+        Ok(self.pass()?.program.clone())
+    }
+
+    fn make_label(&mut self) -> Identifier {
+        self.offset += 1;
+        format!("loop.{}", self.offset).into()
+    }
+
+    /// Blocks nested right within a block are labeled through an
+    /// explicit stack, as in [`VariableResolution`].
+    fn label_block(
+        &mut self,
+        blocks: ast::BlockItems,
+        current_label: Option<&Identifier>,
+    ) -> Result<ast::BlockItems> {
+        let mut stack = vec![(blocks.into_iter(), Vec::new())];
+
+        while let Some((items, new_blocks)) = stack.last_mut() {
+            match items.next() {
+                Some(ast::BlockItem::S(ast::Statement::Compound(inner))) => {
+                    stack.push((inner.into_iter(), Vec::new()));
+                }
+                Some(ast::BlockItem::S(statement)) => new_blocks.push(ast::BlockItem::S(
+                    self.label_statement(statement, current_label)?,
+                )),
+                Some(block_item) => new_blocks.push(block_item),
+                None => {
+                    let Some((_, new_blocks)) = stack.pop() else {
+                        unreachable!("The stack holds the current block")
+                    };
+                    let Some((_, outer)) = stack.last_mut() else {
+                        return Ok(new_blocks);
+                    };
+
+                    outer.push(ast::BlockItem::S(ast::Statement::Compound(new_blocks)));
+                }
+            }
+        }
+
+        unreachable!("The outermost block returns once it ends")
+    }
+
+    fn label_statement(
+        &mut self,
+        statement: ast::Statement,
+        current_label: Option<&Identifier>,
+    ) -> Result<ast::Statement> {
+        match statement {
+            ast::Statement::Break(_) => current_label
+                .map(|label| ast::Statement::Break(Some(label.clone())))
+                .ok_or_else(|| Error::BreakOutsideLoop {
+                    function: self.function.clone(),
+                }),
+            ast::Statement::Continue(_) => current_label
+                .map(|label| ast::Statement::Continue(Some(label.clone())))
+                .ok_or_else(|| Error::ContinueOutsideLoop {
+                    function: self.function.clone(),
+                }),
+            ast::Statement::While {
+                condition, body, ..
+            } => {
+                let label = self.make_label();
+                let body = self.label_statement(*body, Some(&label))?;
+
+                Ok(ast::Statement::While {
+                    condition,
+                    body: Box::new(body),
+                    label: Some(label),
+                })
+            }
+            ast::Statement::DoWhile {
+                body, condition, ..
+            } => {
+                let label = self.make_label();
+                let body = self.label_statement(*body, Some(&label))?;
+
+                Ok(ast::Statement::DoWhile {
+                    body: Box::new(body),
+                    condition,
+                    label: Some(label),
+                })
+            }
+            ast::Statement::For {
+                init,
+                condition,
+                post,
+                body,
+                ..
+            } => {
+                let label = self.make_label();
+                let body = self.label_statement(*body, Some(&label))?;
+
+                Ok(ast::Statement::For {
+                    init,
+                    condition,
+                    post,
+                    body: Box::new(body),
+                    label: Some(label),
+                })
+            }
+            ast::Statement::If {
+                condition,
+                then,
+                else_statement,
+            } => {
+                let else_statement = match else_statement {
+                    Some(statement) => {
+                        Some(Box::new(self.label_statement(*statement, current_label)?))
+                    }
+                    None => None,
+                };
+
+                Ok(ast::Statement::If {
+                    condition,
+                    then: Box::new(self.label_statement(*then, current_label)?),
+                    else_statement,
+                })
+            }
+            ast::Statement::Compound(block_items) => Ok(ast::Statement::Compound(
+                self.label_block(block_items, current_label)?,
+            )),
+            statement @ (ast::Statement::Return(_)
+            | ast::Statement::Expression(_)
+            | ast::Statement::Null) => Ok(statement),
+        }
+    }
+}
+
+/// Makes sure every function ends with a `return`, appending a
+/// `return 0;` to the ones whose end is reachable, or a bare
+/// `return;` to `void` functions.
+///
+/// Every `return` must match the return type of its function,
+/// `void` functions can't return a value, and the others must.
+///
+/// Reaching the end of `main` returns `0`, as the C standard
+/// requires. For any other function a [`Error::MissingReturn`]
+/// warning is recorded, since its caller gets an unspecified
+/// value.
+///
+/// Loops are assumed to exit, so an infinite loop returning from
+/// within its body is still followed by a `return 0;`.
+#[derive(Debug)]
+pub struct ReturnAnalysis {
+    program: ast::Program,
+    warnings: Vec<Error>,
+}
+
+impl From<ast::Program> for ReturnAnalysis {
+    fn from(value: ast::Program) -> Self {
+        ReturnAnalysis {
+            program: value,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl ReturnAnalysis {
+    pub fn pass(&mut self) -> Result<&mut Self> {
+        for function in &mut self.program.0 {
+            Self::check_returns(function)?;
+
+            if Self::block_returns(&function.body) {
+                continue;
+            }
+
+            let value = match function.return_type {
+                ast::Type::Void => None,
+                _ => {
+                    if function.name.0 != "main" {
+                        self.warnings.push(Error::MissingReturn {
+                            name: function.name.clone(),
+                        });
+                    }
+                    Some(ast::Expression::Constant(0))
+                }
+            };
+            function
+                .body
+                .push(ast::BlockItem::S(ast::Statement::Return(value)));
+        }
+
+        Ok(self)
+    }
+
+    pub fn get_updated_program(&mut self) -> Result<ast::Program> {
+        Ok(self.pass()?.program.clone())
+    }
+
+    /// Returns the warnings recorded by [`ReturnAnalysis::pass`].
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// Takes the warnings recorded so far, to report them.
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Checks that every `return` in the function returns a value
+    /// unless the function returns `void`.
+    fn check_returns(function: &ast::Function) -> Result<()> {
+        let returns_void = matches!(function.return_type, ast::Type::Void);
+        let mut worklist: Vec<&ast::Statement> = Self::statements(&function.body).collect();
+
+        while let Some(statement) = worklist.pop() {
+            match statement {
+                ast::Statement::Return(value) if value.is_none() != returns_void => {
+                    return Err(Error::InvalidReturn {
+                        name: function.name.clone(),
+                        return_type: function.return_type,
+                    });
+                }
+                ast::Statement::If {
+                    then,
+                    else_statement,
+                    ..
+                } => {
+                    worklist.push(then);
+                    worklist.extend(else_statement.as_deref());
+                }
+                ast::Statement::Compound(block_items) => {
+                    worklist.extend(Self::statements(block_items))
+                }
+                ast::Statement::While { body, .. }
+                | ast::Statement::DoWhile { body, .. }
+                | ast::Statement::For { body, .. } => worklist.push(body),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn statements(block_items: &ast::BlockItems) -> impl Iterator<Item = &ast::Statement> {
+        block_items
+            .iter()
+            .filter_map(|block_item| match block_item {
+                ast::BlockItem::S(statement) => Some(statement),
+                ast::BlockItem::D(_) | ast::BlockItem::E(_) | ast::BlockItem::Line(_) => None,
+            })
+    }
+
+    /// Returns true if every path through the statement ends
+    /// in a `return`.
+    fn returns(statement: &ast::Statement) -> bool {
+        match statement {
+            ast::Statement::Return(_) => true,
+            ast::Statement::If {
+                then,
+                else_statement: Some(else_statement),
+                ..
+            } => Self::returns(then) && Self::returns(else_statement),
+            ast::Statement::Compound(block_items) => Self::block_returns(block_items),
+            _ => false,
+        }
+    }
+
+    /// A block returns once any of its statements does, the ones
+    /// after it are unreachable. Nested blocks are flattened
+    /// through a worklist, so deeply nested blocks can't overflow
+    /// the stack.
+    fn block_returns(block_items: &ast::BlockItems) -> bool {
+        let mut worklist: Vec<&ast::BlockItem> = block_items.iter().collect();
+
+        while let Some(block_item) = worklist.pop() {
+            match block_item {
+                ast::BlockItem::S(ast::Statement::Compound(inner)) => worklist.extend(inner),
+                ast::BlockItem::S(statement) if Self::returns(statement) => return true,
+                _ => {}
+            }
+        }
+
+        false
+    }
+}
+
+/// Records a warning for each expression statement whose value is
+/// discarded without any side effect, such as `x;` or `1 + 1;`,
+/// and for each assignment of an lvalue to itself, such as
+/// `x = x;`. Such statements are still compiled.
+///
+/// Only assignments and calls have side effects, a division by
+/// zero is assumed not to happen. It runs before
+/// `VariableResolution`, so warnings name the variables of the
+/// source.
+#[derive(Debug)]
+pub struct UnusedValueAnalysis {
+    program: ast::Program,
+    warnings: Vec<Error>,
+}
+
+impl From<ast::Program> for UnusedValueAnalysis {
+    fn from(value: ast::Program) -> Self {
+        UnusedValueAnalysis {
+            program: value,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl UnusedValueAnalysis {
+    pub fn pass(&mut self) -> Result<&mut Self> {
+        for function in &self.program.0 {
+            let mut worklist: Vec<&ast::Statement> =
+                ReturnAnalysis::statements(&function.body).collect();
+
+            while let Some(statement) = worklist.pop() {
+                match statement {
+                    ast::Statement::Expression(ast::Expression::Assignment(lvalue, value))
+                        if lvalue == value && Self::is_pure(lvalue) =>
+                    {
+                        self.warnings.push(Error::SelfAssignment {
+                            value: *lvalue.clone(),
+                        })
+                    }
+                    ast::Statement::Expression(expression) if Self::is_pure(expression) => {
+                        self.warnings.push(Error::UnusedValue {
+                            value: expression.clone(),
+                        })
+                    }
+                    ast::Statement::If {
+                        then,
+                        else_statement,
+                        ..
+                    } => {
+                        worklist.push(then);
+                        worklist.extend(else_statement.as_deref());
+                    }
+                    ast::Statement::Compound(block_items) => {
+                        worklist.extend(ReturnAnalysis::statements(block_items))
+                    }
+                    ast::Statement::While { body, .. }
+                    | ast::Statement::DoWhile { body, .. }
+                    | ast::Statement::For { body, .. } => worklist.push(body),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn get_updated_program(&mut self) -> Result<ast::Program> {
+        Ok(self.pass()?.program.clone())
+    }
+
+    /// Returns the warnings recorded by [`UnusedValueAnalysis::pass`].
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// Takes the warnings recorded so far, to report them.
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Returns true if evaluating the expression has no side
+    /// effect.
+    fn is_pure(expression: &ast::Expression) -> bool {
+        match expression {
+            ast::Expression::Constant(_)
+            | ast::Expression::UnsignedConstant(_)
+            | ast::Expression::Var(_)
+            | ast::Expression::SizeOfType(_)
+            | ast::Expression::SizeOf(_) => true,
+            ast::Expression::Unary(_, operand)
+            | ast::Expression::AddrOf(operand)
+            | ast::Expression::Deref(operand) => Self::is_pure(operand),
+            ast::Expression::Binary(_, left, right)
+            | ast::Expression::Comma(left, right)
+            | ast::Expression::Subscript(left, right) => {
+                Self::is_pure(left) && Self::is_pure(right)
+            }
+            ast::Expression::Conditional {
+                condition,
+                exp1,
+                exp2,
+            } => Self::is_pure(condition) && Self::is_pure(exp1) && Self::is_pure(exp2),
+            ast::Expression::Assignment(_, _) | ast::Expression::FunctionCall { .. } => false,
         }
     }
 }
@@ -1 +1,189 @@
+use std::fs;
 
+use nous::{
+    assembly::{Assembly, Function, Instruction, Operand, Program, Reg, Target},
+    errors::Error,
+    Compiler,
+};
+
+#[test]
+fn test_text_section() {
+    let program =
+        Assembly::from(String::from("int main(void) { return 2; }")).to_assembly_program();
+
+    for target in [Target::Linux, Target::MacOs] {
+        let assembly = program.format_for(target);
+        let function = assembly.find(".globl").expect("Should emit the function");
+
+        assert!(assembly[..function].contains("\t.text\n"));
+    }
+}
+
+#[test]
+fn test_gnu_stack_note_once() {
+    let program = Assembly::from(String::from(
+        "int helper(void) { return 1; } int main(void) { return 2; }",
+    ))
+    .to_assembly_program();
+
+    let assembly = program.format_for(Target::Linux);
+    let note = assembly
+        .find(".note.GNU-stack")
+        .expect("Should emit the note");
+
+    assert_eq!(assembly.matches(".globl").count(), 2);
+    assert_eq!(assembly.matches(".note.GNU-stack").count(), 1);
+    assert!(assembly.rfind("ret").expect("Should emit both functions") < note);
+}
+
+#[test]
+fn test_stack_arguments() {
+    let program = Compiler::new()
+        .extern_function("f", 7)
+        .assembly_program("int main(void) { return f(1, 2, 3, 4, 5, 6, 7); }")
+        .expect("Should compile the program");
+
+    let linux = program.format_for(Target::Linux);
+    let macos = program.format_for(Target::MacOs);
+
+    // The seventh argument is pushed, with 8 bytes of padding
+    // keeping the stack aligned.
+    assert!(linux.contains("\tsubq\t$8, %rsp\n\tmovl\t$1, %edi\n"));
+    assert!(linux.contains("\tmovl\t$6, %r9d\n\tpushq\t$7\n\tcall\tf@PLT\n\taddq\t$16, %rsp\n"));
+    assert!(macos.contains("\tcall\t_f\n"));
+}
+
+#[test]
+fn test_function_symbols() {
+    let program = Compiler::new()
+        .assembly_program(
+            "int helper(void) { return 1; } int main(void) { if (helper()) return 2; else return 3; }",
+        )
+        .expect("Should compile the program");
+    let assembly = program.format_for(Target::Linux);
+
+    // Functions are global symbols, called by name.
+    assert!(assembly.contains("\t.globl helper\nhelper:\n"));
+    assert!(assembly.contains("\tcall\thelper\n"));
+    assert!(!assembly.contains(".L_helper"));
+    // Branches jump to local labels.
+    assert!(assembly.contains("\tjmp\t.L_"));
+}
+
+#[test]
+fn test_static_variables() {
+    let program = Compiler::new()
+        .assembly_program("int g = 5; int z; int main(void) { z = g; return z; }")
+        .expect("Should compile the program");
+    let linux = program.format_for(Target::Linux);
+    let macos = program.format_for(Target::MacOs);
+
+    assert!(linux.contains("\t.globl g\n\t.data\n\t.balign 4\ng:\n\t.long 5\n"));
+    assert!(linux.contains("\t.globl z\n\t.bss\n\t.balign 4\nz:\n\t.zero 4\n"));
+    // Both operands of a `mov` can't be in memory.
+    assert!(linux.contains("\tmovl\tg(%rip), %r10d\n\tmovl\t%r10d, z(%rip)\n"));
+    assert!(macos.contains("\tmovl\t_g(%rip), %r10d\n"));
+    assert!(macos.contains("\t.globl _z\n"));
+}
+
+#[test]
+fn test_external_calls() {
+    let program = Compiler::new()
+        .assembly_program(
+            "int helper(void) { return putchar(65); } int main(void) { return helper(); }",
+        )
+        .expect("Should compile the program");
+    let linux = program.format_for(Target::Linux);
+    let macos = program.format_for(Target::MacOs);
+
+    // Only functions missing from the program go through the PLT.
+    assert!(linux.contains("\tcall\tputchar@PLT\n"));
+    assert!(linux.contains("\tcall\thelper\n"));
+    assert!(macos.contains("\tcall\t_putchar\n"));
+    assert!(macos.contains("\tcall\t_helper\n"));
+}
+
+#[test]
+fn test_dump_pseudo_registers() {
+    let program = Assembly::from(String::from("int g = 3; int main(void) { return -g; }"))
+        .to_assembly_program();
+
+    // Formatting would panic on the pseudo registers.
+    assert_eq!(
+        program.dump(),
+        "main:\n\
+         \tmovl\tg(%rip), pseudo(tmp.1)\n\
+         \tnegl\tpseudo(tmp.1)\n\
+         \tmovl\tpseudo(tmp.1), %eax\n\
+         \tmovq\t%rbp, %rsp\n\tpopq\t%rbp\n\tret\n\
+         static g = 3\n"
+    );
+}
+
+#[test]
+fn test_unreplaced_pseudo_register() {
+    // No pass ran over the program, so the pseudo register is left.
+    let program = Program(
+        vec![Function {
+            name: "main".into(),
+            instructions: vec![
+                Instruction::Mov {
+                    src: Operand::Imm(1),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Cmp(Operand::Imm(0), Operand::Pseudo("x".into())),
+                Instruction::Ret,
+            ],
+        }],
+        vec![],
+    );
+
+    match program.try_format_for(Target::Linux) {
+        Err(Error::UnreplacedPseudoRegister {
+            function,
+            instruction,
+        }) => {
+            assert_eq!(function.0, "main");
+            assert_eq!(instruction, "cmpl\t$0, pseudo(x)");
+        }
+        result => panic!("Expected an unreplaced pseudo register, found {result:?}"),
+    }
+}
+
+#[test]
+fn test_shared_epilogue() {
+    let program = Compiler::new()
+        .assembly_program(
+            "int main(void) { int x = 2; if (x == 1) return 10; if (x == 2) return 20; return 30; }",
+        )
+        .expect("Should compile the program");
+    let assembly = program.format_for(Target::Linux);
+
+    assert_eq!(assembly.matches("popq\t%rbp").count(), 1);
+    assert_eq!(assembly.matches("\tjmp\t.L_return.main\n").count(), 2);
+    // The last `return` falls through into the epilogue.
+    assert!(assembly.contains("\tmovl\t$30, %eax\n.L_return.main:\n"));
+}
+
+#[test]
+fn test_macos_symbols() {
+    let source = fs::read_to_string("tests/files/valid/return_2.c").expect("Should read the file");
+    let assembly = Compiler::new()
+        .assembly_program(&source)
+        .expect("Should compile the program")
+        .format_for(Target::MacOs);
+
+    assert!(assembly.contains("\t.globl _main\n_main:\n"));
+    assert!(!assembly.contains("\nmain:"));
+
+    // Local labels drop the leading dot on macOS.
+    let program = Compiler::new()
+        .assembly_program("int main(void) { int a = 1; if (a) a = 2; return a; }")
+        .expect("Should compile the program");
+    let macos = program.format_for(Target::MacOs);
+    let linux = program.format_for(Target::Linux);
+
+    assert!(macos.contains("\tje\tL_") && macos.contains("\nL_"));
+    assert!(!macos.contains(".L_"));
+    assert!(linux.contains("\tje\t.L_") && linux.contains("\n.L_"));
+}
@@ -1,20 +1,32 @@
+use std::fs;
+
 use nous::{
-    ast::{BlockItem, Expression, Function, Identifier, Program, Statement},
+    ast::{BinaryOperator, BlockItem, Expression, Function, Identifier, Program, Statement, Type},
+    errors::Result,
     parser::Parser,
     utils::read_file,
 };
 
 #[test]
-fn test_return_2() -> std::io::Result<()> {
+fn test_return_2() -> Result<()> {
     let file = read_file("tests/files/valid/return_2.c")?;
 
     let mut parser = Parser::from(file);
 
-    let test = Program(Function {
-        name: Identifier("main".to_owned()),
-        // body: Statement::Return(Expression::Constant(2)),
-        body: vec![BlockItem::S(Statement::Return(Expression::Constant(2)))],
-    });
+    let test = Program(
+        vec![Function {
+            name: Identifier("main".to_owned()),
+            return_type: Type::Int,
+            params: vec![],
+            // body: Statement::Return(Some(Expression::Constant(2))),
+            body: vec![BlockItem::S(Statement::Return(Some(Expression::Constant(
+                2,
+            ))))],
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     let program = parser.to_ast_program().unwrap();
 
@@ -24,3 +36,107 @@ fn test_return_2() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_pretty() {
+    let program =
+        Parser::build("int main(void) { int a = 2; if (a > 1) return a; else { a = -a; } }")
+            .to_ast_program()
+            .expect("Should parse the program");
+
+    let expected = "\
+Program
+  Function main
+    Declaration a
+      Constant 2
+    If
+      Binary GreaterThan
+        Var a
+        Constant 1
+      Then
+        Return
+          Var a
+      Else
+        Compound
+          Expression
+            Assignment
+              Var a
+              Unary Negate
+                Var a
+";
+
+    assert_eq!(program.pretty(), expected);
+}
+
+fn constant(value: i64) -> Box<Expression> {
+    Box::new(Expression::Constant(value))
+}
+
+#[test]
+fn test_to_c_precedence() {
+    let product = Expression::Binary(BinaryOperator::Multiply, constant(2), constant(3));
+    let sum = Expression::Binary(BinaryOperator::Add, constant(2), constant(3));
+
+    assert_eq!(
+        Expression::Binary(BinaryOperator::Add, Box::new(product), constant(4)).to_c(),
+        "2 * 3 + 4"
+    );
+    assert_eq!(
+        Expression::Binary(BinaryOperator::Multiply, Box::new(sum.clone()), constant(4)).to_c(),
+        "(2 + 3) * 4"
+    );
+    assert_eq!(
+        Expression::Binary(BinaryOperator::Subtract, constant(1), Box::new(sum)).to_c(),
+        "1 - (2 + 3)"
+    );
+}
+
+fn assert_round_trip(source: &str) {
+    let program = Parser::build(source)
+        .to_ast_program()
+        .expect("Should parse the program");
+    let c = program.to_c();
+    let reparsed = Parser::build(&c)
+        .to_ast_program()
+        .unwrap_or_else(|error| panic!("Should reparse {c:?}: {error:?}"));
+
+    assert_eq!(program, reparsed, "{c}");
+}
+
+#[test]
+fn test_to_c_round_trip() {
+    assert_round_trip(
+        "int main(void) { int a = 2; int b; b = a = -(-a) * ~(a + 1); \
+         if (a > 1) if (b) return a; else ; else { a = !a || b && a != 3; } \
+         return a ? b ? 1 : 2 : (a = 3) ? 4 : 5; }",
+    );
+    assert_round_trip(
+        "int helper(void) { return 1; } \
+         int main(void) { for (int i = 0; i < 10; i = i + 1) { if (i % 2) continue; } \
+         for (;;) break; do { while (1) break; } while (0); return (a = 1, (a, a) + 1), a; }",
+    );
+    assert_round_trip("int main(void) { int a[3]; a[a[0] + 1] = -a[2]; return sizeof a; }");
+    assert_round_trip("int main(void) { int x; int *p = &x; *p = -*&x; return *p; }");
+    assert_round_trip(
+        "int main(void) { for (unsigned i = 0, j = 3u; i < j; i = i + 1) ; \
+         for (int *p, a[2], k = (1, 2);;) break; return 0; }",
+    );
+    assert_round_trip("enum C { A = 0, B = 2 };\nint main(void) { enum { D = -1 }; return 0; }");
+    assert_round_trip(
+        "int f(int, unsigned int b); int main(void) { return f(1, 2u); } \
+         int f(int a, unsigned int b) { return a + b; }",
+    );
+}
+
+#[test]
+fn test_to_c_round_trip_files() {
+    for entry in fs::read_dir("playground").expect("Should read the playground") {
+        let path = entry.expect("Should read the entry").path();
+        let source = fs::read_to_string(&path).expect("Should read the program");
+
+        // Some programs are meant to fail parsing.
+        if Parser::build(&source).to_ast_program().is_ok() {
+            assert_round_trip(&source);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use nous::{
+    cfg::{build_cfg, linearize, Successor},
+    parser::Parser,
+    tac::{Instruction, TAC},
+    visitor::LoopLabeling,
+};
+
+fn body(source: &str) -> Vec<Instruction> {
+    let program = Parser::build(source)
+        .to_ast_program()
+        .expect("Should parse the program");
+    let program = LoopLabeling::from(program)
+        .get_updated_program()
+        .expect("Should label the loops");
+
+    TAC::from(program).to_tac_program().0[0].body.clone()
+}
+
+#[test]
+fn test_if_else() {
+    let body = body("int main(void) { int x = 1; if (x) x = 2; else x = 3; return x; }");
+    let cfg = build_cfg(&body);
+
+    // The condition, both branches, and the join point.
+    assert_eq!(cfg.blocks.len(), 4);
+    assert_eq!(
+        cfg.edges(),
+        vec![
+            (0, Successor::Block(2)),
+            (0, Successor::Block(1)),
+            (1, Successor::Block(3)),
+            (2, Successor::Block(3)),
+            (3, Successor::Exit),
+        ]
+    );
+    assert_eq!(cfg.blocks[3].predecessors, vec![1, 2]);
+    assert!(matches!(
+        cfg.blocks[2].instructions[0],
+        Instruction::Label(_)
+    ));
+
+    assert_eq!(linearize(cfg), body);
+}
+
+#[test]
+fn test_loop() {
+    let body = body("int main(void) { int x = 0; while (x < 3) x = x + 1; return x; }");
+    let cfg = build_cfg(&body);
+
+    // The condition is reached from the entry and from the end
+    // of the body.
+    assert_eq!(cfg.blocks.len(), 4);
+    assert_eq!(cfg.blocks[1].predecessors, vec![0, 2]);
+    assert_eq!(cfg.blocks[2].successors, vec![Successor::Block(1)]);
+    assert_eq!(cfg.blocks[3].successors, vec![Successor::Exit]);
+
+    assert_eq!(linearize(cfg), body);
+}
+
+#[test]
+fn test_empty() {
+    let cfg = build_cfg(&vec![]);
+
+    assert!(cfg.blocks.is_empty());
+    assert!(linearize(cfg).is_empty());
+}
@@ -0,0 +1,272 @@
+use std::{fs, process::Command};
+
+use nous::{
+    assembly::{Assembly, Target},
+    ast::{BinaryOperator, Expression},
+    compile_to_assembly,
+    compiler::{Emit, Output},
+    errors::Error,
+    parser::Parser,
+    tac::TAC,
+    visitor::{AssemblyPass, LoopLabeling, ReturnAnalysis, VariableResolution},
+    Compiler,
+};
+
+const SOURCE: &str = "int main(void) { int a = 2; return a; }";
+
+fn assembly(compiler: Compiler) -> String {
+    match compiler
+        .compile(SOURCE)
+        .expect("Should compile the program")
+    {
+        Output::Assembly(assembly) => assembly,
+        output => panic!("Expected assembly, found {output:?}"),
+    }
+}
+
+#[test]
+fn test_emit_assembly_linux() {
+    let assembly = assembly(Compiler::new().target(Target::Linux));
+
+    assert!(assembly.contains("\t.globl main\nmain:"));
+    assert!(assembly.contains("\t.type main, @function\n"));
+    assert!(assembly.contains("\tret\n\t.size main, .-main\n"));
+    assert!(assembly.contains(".note.GNU-stack"));
+}
+
+#[test]
+fn test_emit_assembly_macos() {
+    let assembly = assembly(Compiler::new().target(Target::MacOs).emit(Emit::Assembly));
+
+    assert!(assembly.contains("\t.globl _main\n_main:"));
+    assert!(!assembly.contains(".type"));
+    assert!(!assembly.contains(".size"));
+    assert!(!assembly.contains(".note.GNU-stack"));
+}
+
+#[test]
+fn test_compile_to_assembly() {
+    let source = fs::read_to_string("playground/return_2.c").expect("Should read file");
+
+    let program = Parser::build(&source)
+        .to_ast_program()
+        .expect("Should parse the program");
+    let program = VariableResolution::from(program)
+        .get_updated_program()
+        .expect("Should resolve the program");
+    let program = LoopLabeling::from(program)
+        .get_updated_program()
+        .expect("Should label the program");
+    let program = ReturnAnalysis::from(program)
+        .get_updated_program()
+        .expect("Should analyse the program");
+    let mut assembly = Assembly::from(TAC::from(program).to_tac_program());
+    assembly.parse_program();
+    let mut visitor = AssemblyPass::build(assembly);
+    visitor.run_passes(|_, _| {});
+
+    for target in [Target::Linux, Target::MacOs] {
+        assert_eq!(
+            compile_to_assembly(&source, target).expect("Should compile the program"),
+            visitor.modify_program().format_for(target)
+        );
+    }
+    assert!(compile_to_assembly("int main(void) { return x; }", Target::Linux).is_err());
+}
+
+#[test]
+fn test_opt_level() {
+    let unoptimized = assembly(Compiler::new());
+    let optimized = assembly(Compiler::new().opt_level(1));
+
+    assert!(optimized.contains("main:"));
+    assert!(unoptimized.contains("main:"));
+}
+
+#[test]
+fn test_emit_object() {
+    let output = std::env::temp_dir().join("nous_compiler_object.o");
+
+    let result = Compiler::new()
+        .emit(Emit::Object)
+        .output(&output)
+        .compile(SOURCE)
+        .expect("Should assemble the program");
+
+    assert_eq!(result, Output::Object(output.clone()));
+    assert!(output.exists());
+    fs::remove_file(&output).expect("Cleaning files");
+}
+
+#[test]
+fn test_emit_executable() {
+    let output = std::env::temp_dir().join("nous_compiler_executable");
+
+    let result = Compiler::new()
+        .emit(Emit::Executable)
+        .output(&output)
+        .compile(SOURCE)
+        .expect("Should link the program");
+
+    assert_eq!(result, Output::Executable(output.clone()));
+
+    let status = Command::new(&output)
+        .status()
+        .expect("Should run the program");
+    fs::remove_file(&output).expect("Cleaning files");
+
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn test_keep_intermediate() {
+    let output = std::env::temp_dir().join("nous_compiler_keep_intermediate.o");
+    let assembly_file = output.with_extension("s");
+
+    let compile = |keep_intermediate: bool| {
+        Compiler::new()
+            .emit(Emit::Object)
+            .keep_intermediate(keep_intermediate)
+            .output(&output)
+            .compile(SOURCE)
+            .expect("Should assemble the program");
+    };
+
+    compile(false);
+    assert!(output.exists());
+    assert!(!assembly_file.exists());
+
+    compile(true);
+    assert!(fs::read_to_string(&assembly_file)
+        .expect("Should keep the assembly file")
+        .contains("main"));
+
+    fs::remove_file(&output).expect("Cleaning files");
+    fs::remove_file(&assembly_file).expect("Cleaning files");
+}
+
+#[test]
+fn test_assembly_path() {
+    let tmpdir = std::env::temp_dir().join("nous_compiler_assembly_path");
+    let _ = fs::remove_dir_all(&tmpdir);
+    fs::create_dir_all(&tmpdir).expect("Should create the directory");
+    let output = tmpdir.join("x.s");
+
+    let compiler = Compiler::new()
+        .emit(Emit::Executable)
+        .input("sources/program.c")
+        .tmpdir(&tmpdir)
+        .keep_intermediate(true)
+        .output(&output);
+    assert_eq!(compiler.assembly_path(&output), tmpdir.join("program.s"));
+
+    // The executable named like an assembly file doesn't take
+    // the place of the intermediate one.
+    compiler.compile(SOURCE).expect("Should link the program");
+    assert!(tmpdir.join("program.s").exists());
+    let status = Command::new(&output)
+        .status()
+        .expect("Should run the program");
+    assert_eq!(status.code(), Some(2));
+
+    let compiler = Compiler::new().input("x.c").output(&output);
+    assert_ne!(compiler.assembly_path(&output), output);
+
+    fs::remove_dir_all(&tmpdir).expect("Cleaning files");
+}
+
+#[test]
+fn test_missing_linker() {
+    let output = std::env::temp_dir().join("nous_compiler_missing_linker");
+
+    let result = Compiler::new()
+        .emit(Emit::Executable)
+        .linker("nous-nonexistent-linker")
+        .output(&output)
+        .compile(SOURCE);
+
+    let Err(Error::IoError(error)) = result else {
+        panic!("Expected an IO error, found {result:?}");
+    };
+    assert!(error.to_string().contains("nous-nonexistent-linker"));
+}
+
+#[test]
+fn test_clang_toolchain() {
+    if Command::new("clang").arg("--version").output().is_err() {
+        return;
+    }
+    let output = std::env::temp_dir().join("nous_compiler_clang");
+
+    Compiler::new()
+        .emit(Emit::Executable)
+        .assembler("clang")
+        .linker("clang")
+        .output(&output)
+        .compile(SOURCE)
+        .expect("Should link the program with clang");
+
+    let status = Command::new(&output)
+        .status()
+        .expect("Should run the program");
+    fs::remove_file(&output).expect("Cleaning files");
+
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn test_compile_error() {
+    assert!(Compiler::new()
+        .compile("int main(void) { return a; }")
+        .is_err());
+}
+
+#[test]
+fn test_invalid_lvalue() {
+    let result = Compiler::new().compile("int main(void) { int a = 1; return (a + 1) = 2; }");
+
+    let Err(Error::InvalidLVal { value }) = result else {
+        panic!("Expected an invalid lvalue error, found {result:?}");
+    };
+    assert_eq!(
+        value,
+        Expression::Binary(
+            BinaryOperator::Add,
+            Box::new(Expression::Var("a".into())),
+            Box::new(Expression::Constant(1)),
+        )
+    );
+}
+
+#[test]
+fn test_division_by_zero() {
+    let result = Compiler::new().compile("int main(void) { return 1 / 0; }");
+
+    let Err(error @ Error::DivisionByZero { .. }) = result else {
+        panic!("Expected a division by zero error, found {result:?}");
+    };
+    assert_eq!(error.to_string(), "The divisor of `1 / 0` is always zero");
+    let Error::DivisionByZero { expression } = error else {
+        unreachable!()
+    };
+    assert_eq!(
+        expression,
+        Expression::Binary(
+            BinaryOperator::Divide,
+            Box::new(Expression::Constant(1)),
+            Box::new(Expression::Constant(0)),
+        )
+    );
+}
+
+#[test]
+fn test_deeply_nested_blocks() {
+    let depth = 500;
+    let source = format!(
+        "int main(void) {{ int a = 1; {} return a; {} }}",
+        "{ int a = 2; ".repeat(depth),
+        "} ".repeat(depth)
+    );
+
+    assert!(Compiler::new().compile(&source).is_ok());
+}
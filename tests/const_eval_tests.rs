@@ -0,0 +1,67 @@
+use nous::{
+    ast::{BlockItem, Expression, Type},
+    const_eval::eval_const,
+    errors::Error,
+    parser::Parser,
+};
+
+fn expression(source: &str) -> Expression {
+    Parser::parse_expression_only(source).expect("Should parse the expression")
+}
+
+#[test]
+fn test_eval_const() {
+    for (source, value) in [
+        ("2+3*4", 14),
+        ("-5", -5),
+        ("(7 - 10) / 2", -1),
+        ("!0 + ~0", 0),
+        ("3 > 2 && 0 || 4", 1),
+        ("-2147483648", -2147483648),
+    ] {
+        assert_eq!(
+            eval_const(&expression(source)).ok(),
+            Some(value),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_non_constants() {
+    for source in ["x+1", "f()", "2147483647 + 1", "1 / 0", "1u", "sizeof(int)"] {
+        assert!(
+            matches!(
+                eval_const(&expression(source)),
+                Err(Error::NotAConstant { .. })
+            ),
+            "{source} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_constant_lengths_and_values() {
+    let program =
+        Parser::build("enum { A = 2 * 3, B }; int main(void) { int a[2+3]; return sizeof a; }")
+            .to_ast_program()
+            .expect("Should parse the program");
+
+    assert_eq!(program.2[0].enumerators[0].1, 6);
+    assert_eq!(program.2[0].enumerators[1].1, 7);
+    assert!(matches!(
+        &program.0[0].body[0],
+        BlockItem::D(declaration) if declaration.ty == Type::Array(5)
+    ));
+
+    for source in [
+        "int main(void) { int a[x]; return 0; }",
+        "int main(void) { int a[1 - 1]; return 0; }",
+        "enum { A = B + 1 }; int main(void) { return 0; }",
+    ] {
+        assert!(
+            Parser::build(source).to_ast_program().is_err(),
+            "{source} should be rejected"
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+#[test]
+fn test_output_flag() {
+    let output = std::env::temp_dir().join("nous_output_flag.s");
+    let _ = fs::remove_file(&output);
+
+    let status = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .args(["-f", "playground/return_2.c", "-o"])
+        .arg(&output)
+        .arg("emit-code")
+        .status()
+        .expect("Should run the driver");
+
+    assert!(status.success());
+
+    let assembly = fs::read_to_string(&output).expect("Should write the output file");
+    fs::remove_file(&output).expect("Cleaning files");
+
+    assert!(assembly.contains("main:"));
+}
+
+/// Copies the programs in `tests/files/multiple` into a fresh
+/// temporary directory.
+fn multiple_files(dir_name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(dir_name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Should create the directory");
+
+    for file in ["main.c", "helper.c"] {
+        fs::copy(format!("tests/files/multiple/{file}"), dir.join(file))
+            .expect("Should copy the program");
+    }
+
+    dir
+}
+
+#[test]
+fn test_multiple_files() {
+    // `main` doesn't call `helper`, but both objects must be
+    // linked together.
+    let dir = multiple_files("nous_multiple_files");
+    let executable = dir.join("program");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .arg("-f")
+        .arg(dir.join("main.c"))
+        .arg("-f")
+        .arg(dir.join("helper.c"))
+        .arg("-o")
+        .arg(&executable)
+        .status()
+        .expect("Should run the driver");
+
+    assert!(status.success());
+    assert!(!dir.join("main.o").exists());
+    assert!(!dir.join("helper.o").exists());
+
+    let status = Command::new(&executable)
+        .status()
+        .expect("Should run the program");
+    fs::remove_dir_all(&dir).expect("Cleaning files");
+
+    assert_eq!(status.code(), Some(2));
+}
+
+#[test]
+fn test_multiple_files_compile_only() {
+    let dir = multiple_files("nous_multiple_files_compile_only");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .arg("-f")
+        .arg(dir.join("main.c"))
+        .arg("-f")
+        .arg(dir.join("helper.c"))
+        .arg("-c")
+        .status()
+        .expect("Should run the driver");
+
+    assert!(status.success());
+    assert!(dir.join("main.o").exists());
+    assert!(dir.join("helper.o").exists());
+    assert!(!dir.join("main").exists());
+
+    fs::remove_dir_all(&dir).expect("Cleaning files");
+}
+
+#[test]
+fn test_keep_intermediate() {
+    let dir = multiple_files("nous_keep_intermediate");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .arg("-f")
+        .arg(dir.join("main.c"))
+        .arg("--keep-intermediate")
+        .output()
+        .expect("Should run the driver");
+
+    assert!(output.status.success());
+    assert!(dir.join("main.s").exists());
+    assert!(dir.join("main.o").exists());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("main.s"));
+
+    fs::remove_dir_all(&dir).expect("Cleaning files");
+}
+
+#[test]
+fn test_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .args(["--stdin", "parse"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Should run the driver");
+
+    child
+        .stdin
+        .take()
+        .expect("Should open the standard input")
+        .write_all(b"int main(void) { return 3; }")
+        .expect("Should write the program");
+
+    let output = child
+        .wait_with_output()
+        .expect("Should wait for the driver");
+    let ast = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(ast.contains(r#"name: "main""#));
+    assert!(ast.contains("Return(Some(Constant(3)))"));
+}
+
+#[test]
+fn test_gcc_failure() {
+    // Without `main` the program assembles but fails to link.
+    let dir = std::env::temp_dir().join("nous_gcc_failure");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Should create the directory");
+    fs::write(dir.join("no_main.c"), "int helper(void) { return 1; }")
+        .expect("Should write the program");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .arg("-f")
+        .arg(dir.join("no_main.c"))
+        .output()
+        .expect("Should run the driver");
+    fs::remove_dir_all(&dir).expect("Cleaning files");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("gcc failed"));
+    assert!(stderr.contains("main"));
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn test_extern_flag() {
+    let dir = std::env::temp_dir().join("nous_extern_flag");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Should create the directory");
+
+    let source = dir.join("main.c");
+    fs::write(&source, "int main(void) { return abs(-4); }").expect("Should write the program");
+    let executable = dir.join("main");
+
+    let driver = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_nous"))
+            .arg("-f")
+            .arg(&source)
+            .arg("-o")
+            .arg(&executable)
+            .args(args)
+            .output()
+            .expect("Should run the driver")
+    };
+
+    // `abs` isn't a known external function.
+    assert!(!driver(&[]).status.success());
+    assert!(driver(&["--extern", "abs:1"]).status.success());
+
+    let status = Command::new(&executable)
+        .status()
+        .expect("Should run the program");
+    fs::remove_dir_all(&dir).expect("Cleaning files");
+
+    assert_eq!(status.code(), Some(4));
+}
+
+#[test]
+fn test_tmpdir() {
+    let dir = multiple_files("nous_tmpdir");
+    let tmpdir = dir.join("intermediates");
+    let output_dir = dir.join("output");
+    let executable = output_dir.join("program");
+    fs::create_dir(&output_dir).expect("Should create the output directory");
+
+    let run = |keep_intermediate: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_nous"));
+        command
+            .arg("-f")
+            .arg(dir.join("main.c"))
+            .arg("--tmpdir")
+            .arg(&tmpdir)
+            .arg("-o")
+            .arg(&executable);
+        if keep_intermediate {
+            command.arg("--keep-intermediate");
+        }
+        command.output().expect("Should run the driver")
+    };
+    // Only the intermediate files are in the temporary directory,
+    // never next to the source or the output.
+    let assert_no_stray_files = || {
+        for file in ["main.s", "main.o", "program.s", "program.o"] {
+            assert!(!dir.join(file).exists(), "{file}");
+            assert!(!output_dir.join(file).exists(), "{file}");
+        }
+    };
+
+    let output = run(true);
+    assert!(output.status.success(), "{output:?}");
+    assert!(executable.exists());
+    assert!(tmpdir.join("main.s").exists());
+    assert!(tmpdir.join("main.o").exists());
+    assert_no_stray_files();
+
+    fs::remove_dir_all(&tmpdir).expect("Cleaning files");
+    fs::remove_file(&executable).expect("Cleaning files");
+
+    // Without `--keep-intermediate` they are cleaned out of it.
+    let output = run(false);
+    assert!(output.status.success(), "{output:?}");
+    assert!(executable.exists());
+    assert!(tmpdir.exists());
+    assert_eq!(
+        fs::read_dir(&tmpdir)
+            .expect("Should read the temporary directory")
+            .count(),
+        0
+    );
+    assert_no_stray_files();
+
+    fs::remove_dir_all(&dir).expect("Cleaning files");
+}
+
+#[test]
+fn test_dump_passes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .args(["-f", "playground/return_2.c", "--dump-passes", "emit-code"])
+        .output()
+        .expect("Should run the driver");
+
+    assert!(output.status.success());
+
+    let dump = String::from_utf8_lossy(&output.stderr);
+    for pass in [
+        "ReplacePseudoRegisters",
+        "RewriteMov",
+        "RewriteBinaryOp",
+        "RewriteCmp",
+        "AllocateStack",
+    ] {
+        assert!(dump.contains(&format!("After {pass}:")), "{pass}");
+    }
+}
+
+#[test]
+fn test_verbose() {
+    let stderr = |flags: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+            .args(["-f", "playground/return_2.c"])
+            .args(flags)
+            .arg("emit-code")
+            .output()
+            .expect("Should run the driver");
+        assert!(output.status.success());
+
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    assert_eq!(stderr(&[]), "");
+
+    let stages = stderr(&["-v"]);
+    assert!(stages.contains("Parsing\n"), "{stages}");
+    assert!(!stages.contains("After"), "{stages}");
+
+    let passes = stderr(&["-vv"]);
+    assert!(
+        passes.contains("Three address code:\nmain:\n\tReturn 2\n"),
+        "{passes}"
+    );
+    assert!(passes.contains("After AllocateStack:"), "{passes}");
+}
+
+#[test]
+fn test_missing_linker() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .args([
+            "-f",
+            "playground/return_2.c",
+            "--linker",
+            "nous-nonexistent-linker",
+        ])
+        .arg("-o")
+        .arg(std::env::temp_dir().join("nous_missing_linker"))
+        .output()
+        .expect("Should run the driver");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("Can't run nous-nonexistent-linker"));
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn test_annotate() {
+    let emit_code = |annotate: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_nous"));
+        command.args(["-f", "tests/files/nested_binaryop.c"]);
+        if annotate {
+            command.arg("--annotate");
+        }
+
+        let output = command
+            .arg("emit-code")
+            .output()
+            .expect("Should run the driver");
+        assert!(output.status.success());
+
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let annotated = emit_code(true);
+    assert!(annotated.contains("\t# tmp.1 = 4 Subtract 2\n\tmovl\t$4, "));
+    assert!(annotated.contains("\t# Return tmp.3\n"));
+
+    assert!(!emit_code(false).contains('#'));
+}
+
+#[test]
+fn test_annotate_lines() {
+    let emit_code = |file: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+            .args(["-f", file, "--annotate-lines", "emit-code"])
+            .output()
+            .expect("Should run the driver");
+        assert!(output.status.success());
+
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    // The `return` starts on line 7, its value being on line 8.
+    let annotated = emit_code("tests/files/valid/newlines.c");
+    assert!(
+        annotated.contains("\t# line 7\n\tmovl\t$0, %eax\n"),
+        "{annotated}"
+    );
+    assert_eq!(annotated.matches("# line").count(), 1);
+
+    // Comments still count as lines.
+    assert!(emit_code("tests/files/valid/multi_digit.c").contains("\t# line 3\n"));
+}
+
+#[test]
+fn test_missing_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .args(["-f", "tests/files/nonexistent.i", "emit-code"])
+        .output()
+        .expect("Should run the driver");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("Can't read tests/files/nonexistent.i"));
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn test_run() {
+    let dir = std::env::temp_dir().join("nous_run");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Should create the directory");
+    fs::write(dir.join("program.c"), "int main(void) { return 6 * 7; }")
+        .expect("Should write the program");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .arg("-f")
+        .arg(dir.join("program.c"))
+        .arg("--run")
+        .status()
+        .expect("Should run the driver");
+
+    assert_eq!(status.code(), Some(42));
+    assert!(!dir.join("program").exists());
+
+    let status = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .arg("-f")
+        .arg(dir.join("program.c"))
+        .args(["--run", "--keep-intermediate"])
+        .status()
+        .expect("Should run the driver");
+
+    assert_eq!(status.code(), Some(42));
+    assert!(dir.join("program").exists());
+
+    fs::remove_dir_all(&dir).expect("Cleaning files");
+}
+
+#[test]
+fn test_intermediate_stages() {
+    // Both stages run the semantic analysis, which labels loops.
+    let output = |stage: &str, file: &str| {
+        Command::new(env!("CARGO_BIN_EXE_nous"))
+            .args(["-f", file, stage])
+            .output()
+            .expect("Should run the driver")
+    };
+
+    let tac = output("tac", "playground/test_while_break.c");
+    assert!(tac.status.success());
+    assert!(String::from_utf8_lossy(&tac.stdout).contains("break_loop.1:"));
+
+    let code_gen = output("code-gen", "playground/test_while_break.c");
+    assert!(code_gen.status.success());
+    assert!(String::from_utf8_lossy(&code_gen.stdout).contains("Jmp('break_loop.1')"));
+
+    for stage in ["tac", "code-gen"] {
+        let undeclared = output(stage, "playground/test_undeclared.c");
+        let stderr = String::from_utf8_lossy(&undeclared.stderr);
+
+        assert!(!undeclared.status.success());
+        assert!(stderr.contains("Undeclared variable"), "{stage}: {stderr}");
+        assert!(!stderr.contains("panicked"));
+    }
+}
+
+#[test]
+fn test_warn_unused() {
+    let stderr = |flags: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+            .args(["-f", "playground/test_unused.c"])
+            .args(flags)
+            .arg("emit-code")
+            .output()
+            .expect("Should run the driver");
+
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    assert!(stderr(&[]).is_empty());
+
+    // Warnings are rendered by miette, naming the variables of
+    // the source.
+    let warnings = stderr(&["--warn-unused"]);
+    assert!(warnings.contains("The value of `x` is unused"));
+    assert!(warnings.contains("`x` is assigned to itself"));
+    assert!(!warnings.contains("Warning:"));
+    assert!(!warnings.contains("x.1"));
+}
+
+#[test]
+fn test_missing_return_warning() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .args(["-f", "playground/test_missing_return.c", "emit-code"])
+        .output()
+        .expect("Should run the driver");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success());
+    assert!(stderr.contains("can reach its end without returning a value"));
+    assert!(stderr.contains("helper"));
+}
+
+#[test]
+fn test_target_flag() {
+    let emit_code = |target: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+            .args(["-f", "tests/files/nested_binaryop.c", "--target", target])
+            .arg("emit-code")
+            .output()
+            .expect("Should run the driver");
+        assert!(output.status.success());
+
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let linux = emit_code("linux");
+    assert!(linux.contains("\t.globl main\nmain:\n"));
+    assert!(linux.contains("\t.type main, @function\n"));
+    assert!(linux.contains(".note.GNU-stack"));
+
+    let macos = emit_code("macos");
+    assert!(macos.contains("\t.globl _main\n_main:\n"));
+    assert!(!macos.contains("@function"));
+    assert!(!macos.contains(".note.GNU-stack"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .args(["-f", "tests/files/nested_binaryop.c", "--target", "windows"])
+        .arg("emit-code")
+        .output()
+        .expect("Should run the driver");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_version() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nous"))
+        .arg("--version")
+        .output()
+        .expect("Should run the driver");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        format!("nous {}", env!("CARGO_PKG_VERSION"))
+    );
+}
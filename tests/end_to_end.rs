@@ -3,13 +3,21 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::process::Command;
 
-use nous::assembly::Assembly;
+use nous::assembly::{Assembly, Target};
 use nous::visitor::AssemblyPass;
+use nous::Compiler;
+
+/// Path of the test file `{name}_debug_test{extension}`, kept
+/// in the temporary directory so test runs leave no files in
+/// the working tree.
+fn test_file(name: &str, extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{name}_debug_test{extension}"))
+}
 
 /// Writes to a file named `debug_test.s`
 fn write_to_file(name: &str, content: &str) -> std::io::Result<()> {
     // Open the file in write mode, creating it if it doesn't exist
-    let file_name = format!("{name}_debug_test.s");
+    let file_name = test_file(name, ".s");
     let mut file = File::create(file_name)?;
 
     // Write the content to the file
@@ -18,8 +26,8 @@ fn write_to_file(name: &str, content: &str) -> std::io::Result<()> {
 }
 
 fn compile_assembly(name: &str) -> Result<(), String> {
-    let assembly_file = format!("{name}_debug_test.s");
-    let output_binary = format!("{name}_debug_test");
+    let assembly_file = test_file(name, ".s");
+    let output_binary = test_file(name, "");
 
     // Use Command to call clang
     let status = Command::new("clang")
@@ -32,7 +40,8 @@ fn compile_assembly(name: &str) -> Result<(), String> {
     if status.success() {
         println!(
             "Compilation successful: {} -> {}",
-            assembly_file, output_binary
+            assembly_file.display(),
+            output_binary.display()
         );
         Ok(())
     } else {
@@ -41,7 +50,7 @@ fn compile_assembly(name: &str) -> Result<(), String> {
 }
 
 fn grab(name: &str) -> Result<i32, String> {
-    let command = format!("./{name}_debug_test");
+    let command = test_file(name, "");
 
     // Spawn the C program as a subprocess.
     let output = Command::new(command)
@@ -61,8 +70,8 @@ fn grab(name: &str) -> Result<i32, String> {
 
 fn clean_files(prefix: &str) -> Result<(), String> {
     let status = Command::new("rm")
-        .arg(format!("{prefix}_debug_test.s"))
-        .arg(format!("{prefix}_debug_test"))
+        .arg(test_file(prefix, ".s"))
+        .arg(test_file(prefix, ""))
         .status()
         .map_err(|e| format!("Got {e}"))?;
     if status.success() {
@@ -72,6 +81,71 @@ fn clean_files(prefix: &str) -> Result<(), String> {
     }
 }
 
+/// Compiles `source` through the whole pipeline, then returns
+/// the exit code of the program.
+fn run(name: &str, source: &str) -> i32 {
+    run_optimized(name, source, 0)
+}
+
+/// Same as [`run`], with the optimizations of `opt_level`.
+fn run_optimized(name: &str, source: &str, opt_level: u8) -> i32 {
+    let program = Compiler::new()
+        .opt_level(opt_level)
+        .assembly_program(source)
+        .expect("Should compile the program");
+
+    write_to_file(name, &program.format()).expect("Should write to program file");
+
+    compile_assembly(name).expect("Should compile assembly code");
+    let status = grab(name).expect("Should grab status code");
+
+    clean_files(name).expect("Cleaning files");
+    status
+}
+
+/// Compiles `source` through the whole pipeline, then returns
+/// the standard output of the program.
+fn run_stdout(name: &str, source: &str) -> String {
+    let program = Compiler::new()
+        .assembly_program(source)
+        .expect("Should compile the program");
+
+    write_to_file(name, &program.format()).expect("Should write to program file");
+    compile_assembly(name).expect("Should compile assembly code");
+
+    let output = Command::new(test_file(name, ""))
+        .output()
+        .expect("Failed to execute command");
+
+    clean_files(name).expect("Cleaning files");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Runs `playground/test_{name}.c` compiled by both this compiler
+/// and the reference one, asserting both exit with the same code,
+/// which is returned.
+fn run_against_reference(name: &str) -> i32 {
+    let source_file = format!("playground/test_{name}.c");
+    let source = std::fs::read_to_string(&source_file).expect("Should read file");
+    let status = run(name, &source);
+
+    // The reference compiler gives the expected result.
+    let reference = std::env::temp_dir().join(format!("nous_{name}_reference"));
+    Command::new("clang")
+        .arg("-o")
+        .arg(&reference)
+        .arg(&source_file)
+        .status()
+        .expect("Should compile the reference program");
+    let expected = Command::new(&reference)
+        .status()
+        .expect("Should run the reference program");
+    std::fs::remove_file(&reference).expect("Cleaning files");
+
+    assert_eq!(Some(status), expected.code());
+    status
+}
+
 #[test]
 fn basic_return_2() {
     let program = Assembly::from(PathBuf::from("playground/return_2.c")).to_assembly_program();
@@ -204,3 +278,360 @@ fn test_if_statement() {
     clean_files(file_name).expect("Cleaning files");
     assert_eq!(status, 1);
 }
+
+#[test]
+fn test_control_flow() {
+    assert_eq!(run_against_reference("control_flow"), 33);
+}
+
+#[test]
+fn test_while_break() {
+    let source =
+        std::fs::read_to_string("playground/test_while_break.c").expect("Should read file");
+
+    // 1 + 3 + 5 + 7 + 9
+    assert_eq!(run("while_break", &source), 25);
+}
+
+#[test]
+fn test_for_continue() {
+    let source =
+        std::fs::read_to_string("playground/test_for_continue.c").expect("Should read file");
+
+    // `continue` jumps to the increment: 0 + 2 + 4 + 6 + 8
+    assert_eq!(run("for_continue", &source), 20);
+}
+
+#[test]
+fn test_while_continue() {
+    let source =
+        std::fs::read_to_string("playground/test_while_continue.c").expect("Should read file");
+
+    // 3 + 6 + 9, then the `do` loop stops after its second
+    // iteration, without reaching the addition.
+    assert_eq!(run("while_continue", &source), 20);
+}
+
+#[test]
+fn test_precedence() {
+    assert_eq!(run_against_reference("precedence"), 43);
+}
+
+#[test]
+fn test_negate_after_binary_operator() {
+    let status = run(
+        "negate_after_binop",
+        "int main(void) { return 1 - -2 * -3 + 10; }",
+    );
+
+    assert_eq!(status, 5);
+}
+
+#[test]
+fn test_exit() {
+    let status = run("exit", "int main(void) { exit(5); return 0; }");
+
+    assert_eq!(status, 5);
+}
+
+#[test]
+fn test_function_call() {
+    let status = run(
+        "function_call",
+        "int three(void) { return 3; } \
+         int main(void) { int a = putchar(10); return three() * 2 + a; }",
+    );
+
+    // `putchar` returns the written character.
+    assert_eq!(status, 16);
+}
+
+#[test]
+fn test_for_multiple_declarations() {
+    // `j` is initialized from `i`, and both are only in the scope
+    // of the loop.
+    let status = run(
+        "for_multiple_declarations",
+        "int main(void) { int i = 10; int steps = 0; \
+         for (int i = 0, j = i + 6; i < j; i = i + 1) { j = j - 1; steps = steps + 1; } \
+         return steps * 10 + i; }",
+    );
+
+    assert_eq!(status, 40);
+}
+
+#[test]
+fn test_forward_call() {
+    let status = run(
+        "forward_call",
+        "int main(void) { return helper() + 1; } \
+         int helper(void) { return 41; }",
+    );
+
+    assert_eq!(status, 42);
+}
+
+#[test]
+fn test_function_parameters() {
+    // Arguments past the sixth one are passed on the stack.
+    let source = "int f(int a, int b); \
+                  int many(int a, int b, int c, int d, int e, int g, int h, int i) { \
+                  return a - b + c * d - e + g * 2 + h * 3 - i; } \
+                  int main(void) { int x = f(10, 3); \
+                  return x + many(1, 2, 3, 4, 5, 6, 7, 8) + f(x, 1); } \
+                  int f(int a, int b) { a = a - b; return a * 2; }";
+
+    for opt_level in 0..=2 {
+        assert_eq!(
+            run_optimized(
+                &format!("function_parameters_o{opt_level}"),
+                source,
+                opt_level
+            ),
+            71
+        );
+    }
+}
+
+#[test]
+fn test_argument_order() {
+    // Each argument appends a digit to `order` as it is evaluated.
+    let source = "int order = 0; \
+                  int a(void) { order = order * 10 + 1; return 1; } \
+                  int b(void) { order = order * 10 + 2; return 2; } \
+                  int f(int x, int y) { return order * 10 + x + y; } \
+                  int main(void) { return f(a(), b()); }";
+
+    for opt_level in 0..=2 {
+        assert_eq!(
+            run_optimized(&format!("argument_order_o{opt_level}"), source, opt_level),
+            123
+        );
+    }
+}
+
+#[test]
+fn test_multiple_declarations() {
+    let status = run(
+        "multiple_declarations",
+        "int g = 4, h; \
+         int main(void) { int a, b = 2, c; a = 3; c = a * b; \
+         { int a = 1, b = a + c; return b + g + h; } }",
+    );
+
+    // The inner `b` reads the inner `a`, declared before it.
+    assert_eq!(status, 11);
+}
+
+#[test]
+fn test_unary_multiplicative() {
+    assert_eq!(run_against_reference("unary_multiplicative"), 101);
+}
+
+#[test]
+fn test_unsigned() {
+    assert_eq!(run_against_reference("unsigned"), 15);
+}
+
+#[test]
+fn test_conditional_move() {
+    let source = "int main(void) { int x = 5; int y = 0 - 3; \
+                  return (x > 0 ? 1 : 0) + (y > 0 ? 10 : x); }";
+
+    let assembly = Compiler::new()
+        .assembly_program(source)
+        .expect("Should compile the program")
+        .format();
+    assert!(assembly.contains("\tcmovne\t"));
+    assert!(!assembly.contains("\tjmp\t"));
+
+    assert_eq!(run("conditional_move", source), 6);
+}
+
+#[test]
+fn test_remainder_signs() {
+    // The remainder takes the sign of the dividend, offset by
+    // 10 to keep exit codes positive.
+    for (a, b, expected) in [(-7, 3, 9), (7, -3, 11), (-7, -3, 9)] {
+        let source = format!("int main(void) {{ int a = {a}; int b = {b}; return a % b + 10; }}");
+
+        assert_eq!(run("remainder_signs", &source), expected, "{a} % {b}");
+    }
+}
+
+#[test]
+fn test_globals() {
+    assert_eq!(run_against_reference("globals"), 11);
+}
+
+#[test]
+fn test_implicit_return() {
+    assert_eq!(run("implicit_return", "int main(void) { int x = 1; }"), 0);
+}
+
+#[test]
+fn test_void_functions() {
+    assert_eq!(run_against_reference("void"), 12);
+}
+
+#[test]
+fn test_sizeof() {
+    assert_eq!(run_against_reference("sizeof"), 211);
+}
+
+#[test]
+fn test_putchar_output() {
+    let stdout = run_stdout(
+        "putchar_output",
+        "int main(void) { putchar('A'); putchar(66); putchar(10); return 0; }",
+    );
+
+    assert_eq!(stdout, "AB\n");
+}
+
+#[test]
+fn test_register_allocation() {
+    for name in [
+        "binary_precedences",
+        "div_rem",
+        "test_arrays",
+        "test_conditional_exp",
+        "test_control_flow",
+        "test_for_continue",
+        "test_globals",
+        "test_logical4",
+        "test_unsigned",
+        "test_void",
+        "test_while_break",
+        "test_while_continue",
+    ] {
+        let source =
+            std::fs::read_to_string(format!("playground/{name}.c")).expect("Should read file");
+
+        assert_eq!(
+            run_optimized(&format!("{name}_o1"), &source, 1),
+            run(name, &source),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_arrays() {
+    let source = "int main(void) { int a[3]; a[2] = 7; a[0] = a[2] * 2; return a[2] + a[0]; }";
+
+    assert_eq!(run("array_element", source), 21);
+    assert_eq!(run_optimized("array_element_o1", source, 1), 21);
+    assert_eq!(run_against_reference("arrays"), 60);
+}
+
+#[test]
+fn test_pointers() {
+    let source = "int main(void) { int x = 1; int *p = &x; *p = 41; x = x + 1; \
+                  *p = *p * 2; return x - 42; }";
+
+    for opt_level in 0..=2 {
+        assert_eq!(
+            run_optimized(&format!("pointer_o{opt_level}"), source, opt_level),
+            42
+        );
+    }
+    assert_eq!(run_against_reference("pointers"), 38);
+}
+
+#[test]
+fn test_shifts() {
+    assert_eq!(
+        run(
+            "shift_constants",
+            "int main(void) { return (1 << 5) + (256 >> 2); }"
+        ),
+        96
+    );
+
+    // Counts held in variables go through `%cl`, right shifts keep
+    // the sign of `int` and fill `unsigned int` with zeroes.
+    let source = "int main(void) { int a = 1; int b = 5; int n = 0 - 256; \
+                  unsigned u = 4294967295u; \
+                  return (a << b) + (n >> b) + (u >> 28) + (b << 1 + 1); }";
+    for opt_level in 0..=2 {
+        assert_eq!(
+            run_optimized(&format!("shift_o{opt_level}"), source, opt_level),
+            59
+        );
+    }
+}
+
+#[test]
+fn test_enums() {
+    let source = "enum Color { RED, GREEN = 5, BLUE }; int main(void) { return BLUE; }";
+
+    assert_eq!(run("enum_blue", source), 6);
+    assert_eq!(run_optimized("enum_blue_o1", source, 1), 6);
+}
+
+#[test]
+fn test_values_across_calls() {
+    let source = "int main(void) { int a = 6; int b = a * 7; int c = b + 1; \
+                  putchar(10); return b + c - 43; }";
+
+    // Both values stay in registers, saved around the call.
+    let assembly = Compiler::new()
+        .opt_level(1)
+        .assembly_program(source)
+        .expect("Should compile the program")
+        .format_for(Target::Linux);
+    let saved = |instruction: &str| {
+        assembly
+            .lines()
+            .filter(|line| line.starts_with(instruction) && !line.ends_with("%rbp"))
+            .count()
+    };
+    assert_eq!(saved("\tpushq"), 2, "{assembly}");
+    assert_eq!(saved("\tpopq"), 2, "{assembly}");
+
+    assert_eq!(run_optimized("values_across_calls", source, 1), 42);
+}
+
+#[test]
+fn test_conditional_value() {
+    let source = |a: i32| {
+        format!(
+            "int main(void) {{ int a = {a}; int b = 2; int c = 3; int x; \
+             x = (a ? b : c); x = x + (a ? b = 5 : c); return x * 10 + b; }}"
+        )
+    };
+
+    assert_eq!(run("conditional_value_0", &source(0)), 62);
+    assert_eq!(run_optimized("conditional_value_1", &source(1), 2), 75);
+}
+
+#[test]
+fn test_negated_condition() {
+    let source = |x: i32| format!("int main(void) {{ int x = {x}; if (!x) return 1; return 2; }}");
+
+    // The `!` isn't materialized, the jump tests `x` directly.
+    let assembly = Compiler::new()
+        .assembly_program(&source(0))
+        .expect("Should compile the program")
+        .format_for(Target::Linux);
+    assert_eq!(assembly.matches("cmpl").count(), 1);
+    assert!(!assembly.contains("set"));
+    assert!(assembly.contains("\tjne\t"));
+
+    assert_eq!(run("negated_condition_0", &source(0)), 1);
+    assert_eq!(run("negated_condition_3", &source(3)), 2);
+}
+
+#[test]
+fn test_shared_epilogue() {
+    let source = |x: i32| {
+        format!(
+            "int main(void) {{ int x = {x}; if (x == 1) return 10; if (x == 2) return 20; return 30; }}"
+        )
+    };
+
+    for (x, expected) in [(1, 10), (2, 20), (3, 30)] {
+        assert_eq!(run(&format!("shared_epilogue_{x}"), &source(x)), expected);
+    }
+}
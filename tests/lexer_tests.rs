@@ -1,11 +1,15 @@
 use std::iter::zip;
 
 use logos::{Lexer, Logos};
-use nous::{lexer::Token, utils::read_file};
+use nous::{
+    errors::Result,
+    lexer::{LexerOptions, Token},
+    utils::read_file,
+};
 
 /// Valid programs.
 #[test]
-fn test_tabs() -> std::io::Result<()> {
+fn test_tabs() -> Result<()> {
     let contents = read_file("tests/files/valid/newlines.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -28,7 +32,7 @@ fn test_tabs() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_newlines() -> std::io::Result<()> {
+fn test_newlines() -> Result<()> {
     let contents = read_file("tests/files/valid/newlines.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -52,7 +56,7 @@ fn test_newlines() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_no_newlines() -> std::io::Result<()> {
+fn test_no_newlines() -> Result<()> {
     let contents = read_file("tests/files/valid/no_newlines.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -76,7 +80,7 @@ fn test_no_newlines() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_multidigit() -> std::io::Result<()> {
+fn test_multidigit() -> Result<()> {
     let contents = read_file("tests/files/valid/multi_digit.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -99,7 +103,7 @@ fn test_multidigit() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_negation() -> std::io::Result<()> {
+fn test_negation() -> Result<()> {
     let contents = read_file("tests/files/valid/negation.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -124,7 +128,7 @@ fn test_negation() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_return_2() -> std::io::Result<()> {
+fn test_return_2() -> Result<()> {
     let contents = read_file("tests/files/valid/return_2.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -148,7 +152,7 @@ fn test_return_2() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_spaces() -> std::io::Result<()> {
+fn test_spaces() -> Result<()> {
     let contents = read_file("tests/files/valid/spaces.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -172,7 +176,7 @@ fn test_spaces() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_decrement() -> std::io::Result<()> {
+fn test_decrement() -> Result<()> {
     // This should not compile but Im checking if it recognizes this as
     // a separate token.
     let contents = read_file("tests/files/invalid/decrement_const.c")?;
@@ -199,7 +203,7 @@ fn test_decrement() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_nested_negation() -> std::io::Result<()> {
+fn test_nested_negation() -> Result<()> {
     let contents = read_file("tests/files/valid/nested_negation.c")?;
 
     let lexer = Token::lexer(&contents);
@@ -227,7 +231,7 @@ fn test_nested_negation() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_bitwise_complement() -> std::io::Result<()> {
+fn test_bitwise_complement() -> Result<()> {
     let contents = read_file("tests/files/valid/bit_comp.c")?;
     let lexer = Token::lexer(&contents);
 
@@ -380,6 +384,22 @@ fn test_invalid_identifier_2() {
     }
 }
 
+#[test]
+fn test_float() {
+    let tokens: Vec<Token> = Token::lexer("return 3.14;")
+        .map(|token| token.expect("Should lex the float"))
+        .collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Return,
+            Token::Float(String::from("3.14")),
+            Token::Semicolon
+        ]
+    );
+}
+
 fn compare_tokens(lexer: Lexer<Token>, tokens: Vec<Token>) {
     for (a, b) in zip(lexer, tokens) {
         if let Ok(token) = a {
@@ -387,3 +407,180 @@ fn compare_tokens(lexer: Lexer<Token>, tokens: Vec<Token>) {
         }
     }
 }
+
+#[test]
+fn test_brackets() {
+    let tokens: Vec<Token> = Token::lexer("int a[10];")
+        .map(|token| token.expect("Should lex the brackets"))
+        .collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Int,
+            Token::Identifier(String::from("a")),
+            Token::LBracket,
+            Token::Constant(10),
+            Token::RBracket,
+            Token::Semicolon
+        ]
+    );
+}
+
+#[test]
+fn test_character_literals() {
+    for (source, code_point) in [
+        ("'a'", 97),
+        ("'A'", 65),
+        (r"'\n'", 10),
+        (r"'\t'", 9),
+        (r"'\\'", 92),
+        (r"'\''", 39),
+        (r"'\0'", 0),
+    ] {
+        let tokens: Vec<_> = Token::lexer(source).collect();
+
+        assert_eq!(tokens, vec![Ok(Token::Constant(code_point))], "{source}");
+    }
+}
+
+#[test]
+fn test_invalid_character_literals() {
+    for source in ["''", "'ab'", r"'\q'"] {
+        assert!(
+            Token::lexer(source).any(|token| token.is_err()),
+            "{source} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_block_comments() {
+    let source = "/* a /* b */ c */";
+    let tokens: Vec<_> = Token::lexer(source).collect();
+
+    // The first `*/` closes the comment.
+    assert_eq!(
+        tokens,
+        vec![
+            Ok(Token::Identifier(String::from("c"))),
+            Ok(Token::Mul),
+            Ok(Token::Div)
+        ]
+    );
+}
+
+#[test]
+fn test_nested_block_comments() {
+    let options = LexerOptions {
+        nested_comments: true,
+    };
+
+    let tokens: Vec<_> = Token::lexer_with_extras("/* a /* b */ c */ 1", options).collect();
+    assert_eq!(tokens, vec![Ok(Token::Constant(1))]);
+
+    let tokens: Vec<_> = Token::lexer_with_extras("/* a /* b */ c", options).collect();
+    assert!(tokens.iter().any(|token| token.is_err()));
+}
+
+#[test]
+fn test_oversized_constant() {
+    let tokens: Vec<_> = Token::lexer("return 99999999999999999999;").collect();
+
+    assert_eq!(tokens[0], Ok(Token::Return));
+    assert!(tokens[1].is_err());
+}
+
+#[test]
+fn test_identifiers() {
+    let tokens: Vec<_> = Token::lexer("a-b _count").collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Ok(Token::Identifier(String::from("a"))),
+            Ok(Token::Negation),
+            Ok(Token::Identifier(String::from("b"))),
+            Ok(Token::Identifier(String::from("_count"))),
+        ]
+    );
+}
+
+#[test]
+fn test_adjacent_operators() {
+    let identifier = |name: &str| Token::Identifier(String::from(name));
+
+    // The longest operator wins, and `=` never merges with the
+    // unary operator following it.
+    for (source, expected) in [
+        (
+            "a<=b",
+            vec![identifier("a"), Token::LessThanOrEq, identifier("b")],
+        ),
+        (
+            "a>=b",
+            vec![identifier("a"), Token::GreaterThanOrEq, identifier("b")],
+        ),
+        (
+            "a==b",
+            vec![identifier("a"), Token::EqualTo, identifier("b")],
+        ),
+        (
+            "a!=b",
+            vec![identifier("a"), Token::NotEqualTo, identifier("b")],
+        ),
+        (
+            "a=-1",
+            vec![
+                identifier("a"),
+                Token::Assign,
+                Token::Negation,
+                Token::Constant(1),
+            ],
+        ),
+        (
+            "a=!b",
+            vec![identifier("a"), Token::Assign, Token::Not, identifier("b")],
+        ),
+        (
+            "a===b",
+            vec![
+                identifier("a"),
+                Token::EqualTo,
+                Token::Assign,
+                identifier("b"),
+            ],
+        ),
+        (
+            "x--y",
+            vec![identifier("x"), Token::Decrement, identifier("y")],
+        ),
+        (
+            "a<<b",
+            vec![identifier("a"), Token::ShiftLeft, identifier("b")],
+        ),
+        (
+            "a>>=b",
+            vec![
+                identifier("a"),
+                Token::ShiftRight,
+                Token::Assign,
+                identifier("b"),
+            ],
+        ),
+        (
+            "x---y",
+            vec![
+                identifier("x"),
+                Token::Decrement,
+                Token::Negation,
+                identifier("y"),
+            ],
+        ),
+    ] {
+        let tokens: Vec<_> = Token::lexer(source).collect();
+        let expected: Vec<_> = expected.into_iter().map(Ok).collect();
+
+        assert_eq!(tokens, expected, "{source}");
+    }
+}
@@ -1,8 +1,11 @@
 use nous::{
     ast::{
-        BinaryOperator, BlockItem, Declaration, Expression, Function, Identifier, Program,
-        Statement, UnaryOperator,
+        BinaryOperator, BlockItem, Declaration, EnumDeclaration, Expression, ForInit, Function,
+        FunctionDeclaration, Identifier, Program, Statement, Type, UnaryOperator,
     },
+    errors::Error,
+    lexer::Token,
+    parser::Parser,
     utils::parser_from_path,
 };
 // Testing unary operators
@@ -17,12 +20,19 @@ fn test_unary() {
         )),
     );
 
-    let expected_program = Program(Function {
-        name: "main".into(),
-        body: vec![BlockItem::S(nous::ast::Statement::Return(
-            expected_expression,
-        ))],
-    });
+    let expected_program = Program(
+        vec![Function {
+            name: "main".into(),
+            return_type: Type::Int,
+            params: vec![],
+            body: vec![BlockItem::S(nous::ast::Statement::Return(Some(
+                expected_expression,
+            )))],
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     assert_eq!(parser.to_ast_program().unwrap(), expected_program)
 }
@@ -45,13 +55,20 @@ fn test_same_precedence() {
         Box::new(Expression::Constant(3)),
     );
 
-    let expected_program = Program(nous::ast::Function {
-        name: "main".into(),
-        // body: nous::ast::Statement::Return(expected_expression),
-        body: vec![BlockItem::S(nous::ast::Statement::Return(
-            expected_expression,
-        ))],
-    });
+    let expected_program = Program(
+        vec![nous::ast::Function {
+            name: "main".into(),
+            return_type: Type::Int,
+            // body: nous::ast::Statement::Return(expected_expression),
+            params: vec![],
+            body: vec![BlockItem::S(nous::ast::Statement::Return(Some(
+                expected_expression,
+            )))],
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     assert_eq!(parser.to_ast_program().unwrap(), expected_program);
 }
@@ -74,12 +91,19 @@ fn test_different_precedences() {
         Box::new(Expression::Constant(1)),
     );
 
-    let expected_program = Program(nous::ast::Function {
-        name: "main".into(),
-        body: vec![BlockItem::S(nous::ast::Statement::Return(
-            expected_expression,
-        ))],
-    });
+    let expected_program = Program(
+        vec![nous::ast::Function {
+            name: "main".into(),
+            return_type: Type::Int,
+            params: vec![],
+            body: vec![BlockItem::S(nous::ast::Statement::Return(Some(
+                expected_expression,
+            )))],
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     assert_eq!(parser.to_ast_program().unwrap(), expected_program)
 }
@@ -91,15 +115,25 @@ fn test_expression() {
     let expected_body = vec![
         BlockItem::D(nous::ast::Declaration {
             name: "x".into(),
+            ty: Type::Int,
             initializer: Some(Expression::Constant(3)),
         }),
-        BlockItem::S(nous::ast::Statement::Return(Expression::Var("x".into()))),
+        BlockItem::S(nous::ast::Statement::Return(Some(Expression::Var(
+            "x".into(),
+        )))),
     ];
 
-    let expected_program = Program(Function {
-        name: "main".into(),
-        body: expected_body,
-    });
+    let expected_program = Program(
+        vec![Function {
+            name: "main".into(),
+            return_type: Type::Int,
+            params: vec![],
+            body: expected_body,
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     assert_eq!(parser.to_ast_program().unwrap(), expected_program)
 }
@@ -110,13 +144,21 @@ fn test_no_expression() {
 
     let expected_body = vec![BlockItem::D(nous::ast::Declaration {
         name: "y".into(),
+        ty: Type::Int,
         initializer: None,
     })];
 
-    let expected_program = Program(Function {
-        name: "main".into(),
-        body: expected_body,
-    });
+    let expected_program = Program(
+        vec![Function {
+            name: "main".into(),
+            return_type: Type::Int,
+            params: vec![],
+            body: expected_body,
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     assert_eq!(parser.to_ast_program().unwrap(), expected_program);
 }
@@ -128,19 +170,30 @@ fn test_mixed_expression() {
     let expected_body = vec![
         BlockItem::D(nous::ast::Declaration {
             name: "x".into(),
+            ty: Type::Int,
             initializer: None,
         }),
         BlockItem::D(nous::ast::Declaration {
             name: "y".into(),
+            ty: Type::Int,
             initializer: Some(Expression::Constant(3)),
         }),
-        BlockItem::S(nous::ast::Statement::Return(Expression::Var("y".into()))),
+        BlockItem::S(nous::ast::Statement::Return(Some(Expression::Var(
+            "y".into(),
+        )))),
     ];
 
-    let expected_program = Program(Function {
-        name: Identifier("main".into()),
-        body: expected_body,
-    });
+    let expected_program = Program(
+        vec![Function {
+            name: Identifier("main".into()),
+            return_type: Type::Int,
+            params: vec![],
+            body: expected_body,
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     assert_eq!(parser.to_ast_program().unwrap(), expected_program);
 }
@@ -152,10 +205,12 @@ fn test_expr_dec() {
     let exptected_body = vec![
         BlockItem::D(Declaration {
             name: "temp".into(),
+            ty: Type::Int,
             initializer: Some(Expression::Constant(10)),
         }),
         BlockItem::D(Declaration {
             name: "x".into(),
+            ty: Type::Int,
             initializer: Some(Expression::Constant(10)),
         }),
         BlockItem::S(Statement::Expression(Expression::Assignment(
@@ -166,13 +221,978 @@ fn test_expr_dec() {
                 Box::new(Expression::Var("x".into())),
             )),
         ))),
-        BlockItem::S(Statement::Return(Expression::Var("temp".into()))),
+        BlockItem::S(Statement::Return(Some(Expression::Var("temp".into())))),
     ];
 
-    let exptected_program = Program(Function {
-        name: "main".into(),
-        body: exptected_body,
-    });
+    let exptected_program = Program(
+        vec![Function {
+            name: "main".into(),
+            return_type: Type::Int,
+            params: vec![],
+            body: exptected_body,
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
 
     assert_eq!(parser.to_ast_program().unwrap(), exptected_program);
 }
+
+#[test]
+fn test_parser_stuck() {
+    // Missing the closing brace, the parser used to spin at the
+    // end of the token stream.
+    let mut parser = Parser::from(String::from("int main(void) { return 0;"));
+
+    assert!(matches!(
+        parser.to_ast_program(),
+        Err(Error::UnterminatedBlock {
+            delimiter: Token::LBrace,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_unterminated_blocks() {
+    let error = |source: &str| match Parser::build(source).to_ast_program() {
+        Err(Error::UnterminatedBlock {
+            delimiter,
+            line,
+            span,
+        }) => (delimiter, line, span.offset()),
+        result => panic!("{source} should be unterminated, got {result:?}"),
+    };
+
+    assert_eq!(error("int main(void){ return 0;"), (Token::LBrace, 1, 14));
+    // The innermost block left open is pointed at.
+    assert_eq!(
+        error("int main(void) {\n  if (1) {\n    { }\n    return 0;\n"),
+        (Token::LBrace, 2, 26)
+    );
+    assert_eq!(
+        error("int main(void) { int a = 1; {"),
+        (Token::LBrace, 1, 28)
+    );
+    assert_eq!(error("int main(void) { return (1"), (Token::LParen, 1, 24));
+    assert_eq!(error("int main(void) { while (1"), (Token::LParen, 1, 23));
+    assert_eq!(
+        error("int main(void) { return f(1, 2"),
+        (Token::LParen, 1, 25)
+    );
+
+    // A parenthesis closed by the wrong token isn't unterminated.
+    assert!(matches!(
+        Parser::build("int main(void) { return (1; }").to_ast_program(),
+        Err(Error::MalformedFactor { .. })
+    ));
+}
+
+#[test]
+fn test_ends_at_eof() {
+    let mut parser = Parser::build("int x; int main(void) { return x; } int y = 1;");
+
+    parser.to_ast_program().expect("Should parse the program");
+    assert_eq!(parser.current_token(), &Token::Eof);
+
+    let mut parser = Parser::build("");
+    assert_eq!(
+        parser.to_ast_program().expect("Should parse nothing"),
+        Program(vec![], vec![], vec![], vec![])
+    );
+    assert_eq!(parser.current_token(), &Token::Eof);
+
+    // Tokens after the last function aren't ignored anymore.
+    assert!(Parser::build("int main(void) { return 0; } }")
+        .to_ast_program()
+        .is_err());
+}
+
+#[test]
+fn test_unsupported_float() {
+    let mut parser = Parser::from(String::from("int main(void) { return 3.14; }"));
+
+    assert!(matches!(
+        parser.to_ast_program(),
+        Err(Error::Unsupported {
+            feature: "floating point"
+        })
+    ));
+}
+
+#[test]
+fn test_multiple_functions() {
+    let mut parser = Parser::from(String::from(
+        "int helper(void) { return 1; } int main(void) { return 2; }",
+    ));
+
+    let expected_program = Program(
+        vec![
+            Function {
+                name: Identifier("helper".into()),
+                return_type: Type::Int,
+                params: vec![],
+                body: vec![BlockItem::S(Statement::Return(Some(Expression::Constant(
+                    1,
+                ))))],
+            },
+            Function {
+                name: Identifier("main".into()),
+                return_type: Type::Int,
+                params: vec![],
+                body: vec![BlockItem::S(Statement::Return(Some(Expression::Constant(
+                    2,
+                ))))],
+            },
+        ],
+        vec![],
+        vec![],
+        vec![],
+    );
+
+    assert_eq!(
+        parser
+            .to_ast_program()
+            .expect("Should parse both functions"),
+        expected_program
+    );
+}
+
+#[test]
+fn test_loops() {
+    let mut parser = Parser::build(
+        "int main(void) { for (int i = 0; ; ) do break; while (i); while (1) continue; }",
+    );
+
+    let expected_program = Program(
+        vec![Function {
+            name: Identifier("main".into()),
+            return_type: Type::Int,
+            params: vec![],
+            body: vec![
+                BlockItem::S(Statement::For {
+                    init: ForInit::InitDecl(vec![Declaration {
+                        name: Identifier("i".into()),
+                        ty: Type::Int,
+                        initializer: Some(Expression::Constant(0)),
+                    }]),
+                    condition: None,
+                    post: None,
+                    body: Box::new(Statement::DoWhile {
+                        body: Box::new(Statement::Break(None)),
+                        condition: Expression::Var(Identifier("i".into())),
+                        label: None,
+                    }),
+                    label: None,
+                }),
+                BlockItem::S(Statement::While {
+                    condition: Expression::Constant(1),
+                    body: Box::new(Statement::Continue(None)),
+                    label: None,
+                }),
+            ],
+        }],
+        vec![],
+        vec![],
+        vec![],
+    );
+
+    assert_eq!(
+        parser.to_ast_program().expect("Should parse the loops"),
+        expected_program
+    );
+}
+
+#[test]
+fn test_comma() {
+    let mut parser = Parser::build("int main(void) { int a; return (a = 1, a = 2, a); }");
+    let assign = |value| {
+        Box::new(Expression::Assignment(
+            Box::new(Expression::Var(Identifier("a".into()))),
+            Box::new(Expression::Constant(value)),
+        ))
+    };
+
+    let program = parser.to_ast_program().expect("Should parse the program");
+
+    assert_eq!(
+        program.0[0].body[1],
+        BlockItem::S(Statement::Return(Some(Expression::Comma(
+            Box::new(Expression::Comma(assign(1), assign(2))),
+            Box::new(Expression::Var(Identifier("a".into()))),
+        ))))
+    );
+}
+
+#[test]
+fn test_arrays() {
+    let program = Parser::build("int main(void) { int a[10]; a[1] = -a[i + 1]; }")
+        .to_ast_program()
+        .expect("Should parse the array");
+    let var = |name: &str| Box::new(Expression::Var(Identifier(name.into())));
+
+    assert_eq!(
+        program.0[0].body,
+        vec![
+            BlockItem::D(Declaration {
+                name: Identifier("a".into()),
+                ty: Type::Array(10),
+                initializer: None,
+            }),
+            BlockItem::S(Statement::Expression(Expression::Assignment(
+                Box::new(Expression::Subscript(
+                    var("a"),
+                    Box::new(Expression::Constant(1))
+                )),
+                // The subscript binds tighter than the negation.
+                Box::new(Expression::Unary(
+                    UnaryOperator::Negate,
+                    Box::new(Expression::Subscript(
+                        var("a"),
+                        Box::new(Expression::Binary(
+                            BinaryOperator::Add,
+                            var("i"),
+                            Box::new(Expression::Constant(1))
+                        ))
+                    ))
+                )),
+            ))),
+        ]
+    );
+}
+
+#[test]
+fn test_pointers() {
+    let program = Parser::build("int main(void) { int *p = &x; *p = x * *p; }")
+        .to_ast_program()
+        .expect("Should parse the pointer");
+    let var = |name: &str| Box::new(Expression::Var(Identifier(name.into())));
+
+    assert_eq!(
+        program.0[0].body,
+        vec![
+            BlockItem::D(Declaration {
+                name: Identifier("p".into()),
+                ty: Type::Pointer,
+                initializer: Some(Expression::AddrOf(var("x"))),
+            }),
+            BlockItem::S(Statement::Expression(Expression::Assignment(
+                Box::new(Expression::Deref(var("p"))),
+                Box::new(Expression::Binary(
+                    BinaryOperator::Multiply,
+                    var("x"),
+                    Box::new(Expression::Deref(var("p")))
+                )),
+            ))),
+        ]
+    );
+
+    for (source, unsupported) in [
+        (
+            "int main(void) { int **p; return 0; }",
+            "pointers to pointers",
+        ),
+        (
+            "int main(void) { unsigned *p; return 0; }",
+            "pointers to unsigned int",
+        ),
+        (
+            "int main(void) { int *a[2]; return 0; }",
+            "arrays of pointers",
+        ),
+    ] {
+        assert!(
+            matches!(
+                Parser::build(source).to_ast_program(),
+                Err(Error::Unsupported { feature }) if feature == unsupported
+            ),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_multiple_declarations() {
+    let program = Parser::build(
+        "int g, *h; int main(void) { int a, b = 2, c; int *d, e[2]; unsigned f, g = b; }",
+    )
+    .to_ast_program()
+    .expect("Should parse the declarations");
+    let declaration = |name: &str, ty: Type, initializer: Option<Expression>| {
+        BlockItem::D(Declaration {
+            name: Identifier(name.into()),
+            ty,
+            initializer,
+        })
+    };
+
+    assert_eq!(
+        program.1.iter().map(|global| global.ty).collect::<Vec<_>>(),
+        vec![Type::Int, Type::Pointer]
+    );
+    assert_eq!(
+        program.0[0].body,
+        vec![
+            declaration("a", Type::Int, None),
+            declaration("b", Type::Int, Some(Expression::Constant(2))),
+            declaration("c", Type::Int, None),
+            // The type specifiers are shared, the declarators aren't.
+            declaration("d", Type::Pointer, None),
+            declaration("e", Type::Array(2), None),
+            declaration("f", Type::UnsignedInt, None),
+            declaration(
+                "g",
+                Type::UnsignedInt,
+                Some(Expression::Var(Identifier("b".into())))
+            ),
+        ]
+    );
+
+    for source in [
+        "int main(void) { int a, ; return 0; }",
+        "int main(void) { int a b; return 0; }",
+    ] {
+        assert!(
+            Parser::build(source).to_ast_program().is_err(),
+            "{source} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_for_multiple_declarations() {
+    let program = Parser::build("int main(void) { for (int i = 0, j = 3; i < j; i = i + 1); }")
+        .to_ast_program()
+        .expect("Should parse the loop");
+
+    let BlockItem::S(Statement::For { init, .. }) = &program.0[0].body[0] else {
+        panic!("Expected a `for` loop, found {:?}", program.0[0].body[0]);
+    };
+    assert_eq!(
+        *init,
+        ForInit::InitDecl(vec![
+            Declaration {
+                name: Identifier("i".into()),
+                ty: Type::Int,
+                initializer: Some(Expression::Constant(0)),
+            },
+            Declaration {
+                name: Identifier("j".into()),
+                ty: Type::Int,
+                initializer: Some(Expression::Constant(3)),
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_annotate_lines() {
+    let source = "int main(void) {\n    int x = 1;\n\n    { x = 2; }\n    return x;\n}";
+    let program = Parser::build(source)
+        .annotate_lines(true)
+        .to_ast_program()
+        .expect("Should parse the program");
+    let var = |name: &str| Box::new(Expression::Var(Identifier(name.into())));
+
+    assert_eq!(
+        program.0[0].body,
+        vec![
+            BlockItem::Line(2),
+            BlockItem::D(Declaration {
+                name: Identifier("x".into()),
+                ty: Type::Int,
+                initializer: Some(Expression::Constant(1)),
+            }),
+            BlockItem::S(Statement::Compound(vec![
+                BlockItem::Line(4),
+                BlockItem::S(Statement::Expression(Expression::Assignment(
+                    var("x"),
+                    Box::new(Expression::Constant(2))
+                ))),
+            ])),
+            BlockItem::Line(5),
+            BlockItem::S(Statement::Return(Some(*var("x")))),
+        ]
+    );
+
+    // Lines are only recorded when asked for.
+    let program = Parser::build(source)
+        .to_ast_program()
+        .expect("Should parse");
+    assert_eq!(program.0[0].body.len(), 3);
+}
+
+#[test]
+fn test_enums() {
+    let program = Parser::build(
+        "enum Color { RED, GREEN = 5, BLUE, }; int main(void) { enum { A = -1, B }; return B; }",
+    )
+    .to_ast_program()
+    .expect("Should parse the enumerations");
+    let enumerators = |enumerators: &[(&str, i64)]| {
+        enumerators
+            .iter()
+            .map(|(name, value)| (Identifier((*name).into()), *value))
+            .collect()
+    };
+
+    assert_eq!(
+        program.2,
+        vec![EnumDeclaration {
+            tag: Some(Identifier("Color".into())),
+            enumerators: enumerators(&[("RED", 0), ("GREEN", 5), ("BLUE", 6)]),
+        }]
+    );
+    assert_eq!(
+        program.0[0].body[0],
+        BlockItem::E(EnumDeclaration {
+            tag: None,
+            enumerators: enumerators(&[("A", -1), ("B", 0)]),
+        })
+    );
+
+    for source in [
+        "enum { }; int main(void) { return 0; }",
+        "enum { A B }; int main(void) { return 0; }",
+        "enum { A = B }; int main(void) { return 0; }",
+        "enum { A = 2147483647, B }; int main(void) { return 0; }",
+        "enum { A } int main(void) { return 0; }",
+    ] {
+        assert!(Parser::build(source).to_ast_program().is_err(), "{source}");
+    }
+    assert!(matches!(
+        Parser::build("enum Color c; int main(void) { return 0; }").to_ast_program(),
+        Err(Error::Unsupported {
+            feature: "variables of enumerated types"
+        })
+    ));
+}
+
+#[test]
+fn test_unsupported_arrays() {
+    for (source, unsupported) in [
+        (
+            "int main(void) { int m[3][3]; return 0; }",
+            "multidimensional arrays",
+        ),
+        (
+            "int main(void) { unsigned a[3]; return 0; }",
+            "arrays of unsigned int",
+        ),
+        (
+            "int main(void) { int a[1] = 0; return 0; }",
+            "array initializers",
+        ),
+    ] {
+        assert!(
+            matches!(
+                Parser::build(source).to_ast_program(),
+                Err(Error::Unsupported { feature }) if feature == unsupported
+            ),
+            "{source}"
+        );
+    }
+
+    for source in [
+        "int main(void) { int a[0]; return 0; }",
+        "int main(void) { int a[n]; return 0; }",
+        "int main(void) { return a[1; }",
+    ] {
+        assert!(Parser::build(source).to_ast_program().is_err(), "{source}");
+    }
+}
+
+#[test]
+fn test_all_precedence_levels() {
+    let mut parser = Parser::build("int main(void) { return a || b && c == d < e + f * g; }");
+    let var = |name: &str| Box::new(Expression::Var(Identifier(name.into())));
+    let binary = |operator, left, right| Box::new(Expression::Binary(operator, left, right));
+
+    // a || (b && (c == (d < (e + (f * g)))))
+    let expected_expression = binary(
+        BinaryOperator::Or,
+        var("a"),
+        binary(
+            BinaryOperator::And,
+            var("b"),
+            binary(
+                BinaryOperator::Equal,
+                var("c"),
+                binary(
+                    BinaryOperator::LessThan,
+                    var("d"),
+                    binary(
+                        BinaryOperator::Add,
+                        var("e"),
+                        binary(BinaryOperator::Multiply, var("f"), var("g")),
+                    ),
+                ),
+            ),
+        ),
+    );
+
+    let program = parser.to_ast_program().expect("Should parse the program");
+
+    assert_eq!(
+        program.0[0].body[0],
+        BlockItem::S(Statement::Return(Some(*expected_expression)))
+    );
+}
+
+#[test]
+fn test_for_clauses() {
+    let var = || Expression::Var(Identifier("i".into()));
+    let assign = || {
+        Expression::Assignment(
+            Box::new(var()),
+            Box::new(Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(var()),
+                Box::new(Expression::Constant(1)),
+            )),
+        )
+    };
+    let less = || {
+        Expression::Binary(
+            BinaryOperator::LessThan,
+            Box::new(var()),
+            Box::new(Expression::Constant(5)),
+        )
+    };
+    let declaration = || {
+        ForInit::InitDecl(vec![Declaration {
+            name: Identifier("i".into()),
+            ty: Type::Int,
+            initializer: Some(Expression::Constant(0)),
+        }])
+    };
+
+    let cases = [
+        ("for(;;)", ForInit::InitExp(None), None, None),
+        ("for(int i=0;;i=i+1)", declaration(), None, Some(assign())),
+        ("for(;i<5;)", ForInit::InitExp(None), Some(less()), None),
+        ("for(;;i=i+1)", ForInit::InitExp(None), None, Some(assign())),
+        ("for(i=i+1;;)", ForInit::InitExp(Some(assign())), None, None),
+        ("for(int i=0;i<5;)", declaration(), Some(less()), None),
+        (
+            "for(i=i+1;i<5;i=i+1)",
+            ForInit::InitExp(Some(assign())),
+            Some(less()),
+            Some(assign()),
+        ),
+    ];
+
+    for (header, init, condition, post) in cases {
+        let source = format!("int main(void) {{ {header} ; return 0; }}");
+        let program = Parser::build(&source)
+            .to_ast_program()
+            .unwrap_or_else(|error| panic!("Should parse {header}: {error:?}"));
+
+        assert_eq!(
+            program.0[0].body,
+            vec![
+                BlockItem::S(Statement::For {
+                    init,
+                    condition,
+                    post,
+                    body: Box::new(Statement::Null),
+                    label: None,
+                }),
+                BlockItem::S(Statement::Return(Some(Expression::Constant(0)))),
+            ],
+            "{header}"
+        );
+    }
+}
+
+#[test]
+fn test_negate_after_binary_operator() {
+    let mut parser = Parser::build("int main(void) { return 1 - -2 * -3; }");
+    let negate = |value| {
+        Box::new(Expression::Unary(
+            UnaryOperator::Negate,
+            Box::new(Expression::Constant(value)),
+        ))
+    };
+
+    let program = parser.to_ast_program().expect("Should parse the program");
+
+    assert_eq!(
+        program.0[0].body[0],
+        BlockItem::S(Statement::Return(Some(Expression::Binary(
+            BinaryOperator::Subtract,
+            Box::new(Expression::Constant(1)),
+            Box::new(Expression::Binary(
+                BinaryOperator::Multiply,
+                negate(2),
+                negate(3)
+            )),
+        ))))
+    );
+}
+
+#[test]
+fn test_function_call() {
+    let mut parser = Parser::build("int main(void) { return f() + g(1, a = 2, (a, 3)); }");
+    let var = || Box::new(Expression::Var(Identifier("a".into())));
+
+    let program = parser.to_ast_program().expect("Should parse the program");
+
+    assert_eq!(
+        program.0[0].body[0],
+        BlockItem::S(Statement::Return(Some(Expression::Binary(
+            BinaryOperator::Add,
+            Box::new(Expression::FunctionCall {
+                name: Identifier("f".into()),
+                arguments: vec![],
+            }),
+            Box::new(Expression::FunctionCall {
+                name: Identifier("g".into()),
+                arguments: vec![
+                    Expression::Constant(1),
+                    Expression::Assignment(var(), Box::new(Expression::Constant(2))),
+                    Expression::Comma(var(), Box::new(Expression::Constant(3))),
+                ],
+            }),
+        ))))
+    );
+}
+
+#[test]
+fn test_assign_is_not_equal() {
+    let mut parser = Parser::build("int main(void) { int a; a = 1; return a == 1; }");
+    let var = || Box::new(Expression::Var(Identifier("a".into())));
+
+    let program = parser.to_ast_program().expect("Should parse the program");
+
+    assert_eq!(
+        program.0[0].body[1..],
+        [
+            BlockItem::S(Statement::Expression(Expression::Assignment(
+                var(),
+                Box::new(Expression::Constant(1))
+            ))),
+            BlockItem::S(Statement::Return(Some(Expression::Binary(
+                BinaryOperator::Equal,
+                var(),
+                Box::new(Expression::Constant(1))
+            )))),
+        ]
+    );
+}
+
+#[test]
+fn test_unary_minus_with_multiplicative_operators() {
+    let var = |name: &str| Box::new(Expression::Var(Identifier(name.into())));
+    let negate = |name| Box::new(Expression::Unary(UnaryOperator::Negate, var(name)));
+
+    let cases = [
+        (
+            "-a * b",
+            Expression::Binary(BinaryOperator::Multiply, negate("a"), var("b")),
+        ),
+        (
+            "a * -b",
+            Expression::Binary(BinaryOperator::Multiply, var("a"), negate("b")),
+        ),
+        (
+            "-a / -b",
+            Expression::Binary(BinaryOperator::Divide, negate("a"), negate("b")),
+        ),
+        (
+            "-a % b",
+            Expression::Binary(BinaryOperator::Remainder, negate("a"), var("b")),
+        ),
+    ];
+
+    for (expression, expected) in cases {
+        let source = format!("int main(void) {{ return {expression}; }}");
+        let program = Parser::build(&source)
+            .to_ast_program()
+            .expect("Should parse the program");
+
+        assert_eq!(
+            program.0[0].body[0],
+            BlockItem::S(Statement::Return(Some(expected))),
+            "{expression}"
+        );
+    }
+}
+
+#[test]
+fn test_type_specifiers() {
+    for (declaration, ty) in [
+        ("int a;", Type::Int),
+        ("unsigned a;", Type::UnsignedInt),
+        ("unsigned int a;", Type::UnsignedInt),
+        ("int unsigned a;", Type::UnsignedInt),
+    ] {
+        let source = format!("int main(void) {{ {declaration} return 0u; }}");
+        let program = Parser::build(&source)
+            .to_ast_program()
+            .expect("Should parse the program");
+
+        assert!(
+            matches!(&program.0[0].body[0], BlockItem::D(d) if d.ty == ty),
+            "{declaration}"
+        );
+    }
+
+    assert!(matches!(
+        Parser::build("int main(void) { unsigned unsigned a; return 0; }").to_ast_program(),
+        Err(Error::InvalidType { .. })
+    ));
+}
+
+#[test]
+fn test_integer_out_of_range() {
+    assert!(matches!(
+        Parser::build("int main(void) { return 99999999999999999999; }").to_ast_program(),
+        Err(Error::IntegerOutOfRange { literal }) if literal == "99999999999999999999"
+    ));
+    assert!(matches!(
+        Parser::build("int main(void) { return 4294967296u; }").to_ast_program(),
+        Err(Error::IntegerOutOfRange { .. })
+    ));
+    assert!(matches!(
+        Parser::build("int main(void) { return @; }").to_ast_program(),
+        Err(Error::InvalidToken { slice }) if slice == "@"
+    ));
+}
+
+#[test]
+fn test_global_declarations() {
+    let mut parser = Parser::build("int g = 5; unsigned h; int main(void) { return g; } int k;");
+    let program = parser.to_ast_program().expect("Should parse the globals");
+
+    assert_eq!(program.0.len(), 1);
+    assert_eq!(
+        program.1,
+        vec![
+            Declaration {
+                name: Identifier("g".into()),
+                ty: Type::Int,
+                initializer: Some(Expression::Constant(5)),
+            },
+            Declaration {
+                name: Identifier("h".into()),
+                ty: Type::UnsignedInt,
+                initializer: None,
+            },
+            Declaration {
+                name: Identifier("k".into()),
+                ty: Type::Int,
+                initializer: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_function_declarations() {
+    let program = Parser::build(
+        "int f(int, unsigned b); void g(void); \
+         int f(int a, unsigned int b) { return a; }",
+    )
+    .to_ast_program()
+    .expect("Should parse the program");
+
+    assert_eq!(
+        program.3,
+        vec![
+            FunctionDeclaration {
+                name: Identifier("f".into()),
+                return_type: Type::Int,
+                params: vec![Type::Int, Type::UnsignedInt],
+            },
+            FunctionDeclaration {
+                name: Identifier("g".into()),
+                return_type: Type::Void,
+                params: vec![],
+            },
+        ]
+    );
+    assert_eq!(
+        program.0[0].params,
+        vec![
+            Declaration {
+                name: Identifier("a".into()),
+                ty: Type::Int,
+                initializer: None,
+            },
+            Declaration {
+                name: Identifier("b".into()),
+                ty: Type::UnsignedInt,
+                initializer: None,
+            },
+        ]
+    );
+
+    for source in [
+        "int f(int) { return 0; }",
+        "int f() { return 0; }",
+        "int f(int a,) { return a; }",
+        "int f(void)",
+    ] {
+        assert!(Parser::build(source).to_ast_program().is_err(), "{source}");
+    }
+}
+
+#[test]
+fn test_function_or_declaration() {
+    let x = || Declaration {
+        name: Identifier("x".into()),
+        ty: Type::Int,
+        initializer: Some(Expression::Constant(1)),
+    };
+    let f = || Function {
+        name: Identifier("f".into()),
+        return_type: Type::Int,
+        params: vec![],
+        body: vec![],
+    };
+
+    for source in [
+        "int f(void) {} int x = 1;",
+        "int x = 1; int f(void) {}",
+        "int x = 1;",
+        "int f(void) {}",
+    ] {
+        let program = Parser::build(source)
+            .to_ast_program()
+            .unwrap_or_else(|error| panic!("Should parse {source:?}: {error:?}"));
+
+        let functions = if source.contains("f(void)") {
+            vec![f()]
+        } else {
+            vec![]
+        };
+        let globals = if source.contains("x = 1") {
+            vec![x()]
+        } else {
+            vec![]
+        };
+        assert_eq!(
+            program,
+            Program(functions, globals, vec![], vec![]),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_argument_lists() {
+    for (call, arguments) in [
+        ("f()", vec![]),
+        ("f(1)", vec![1]),
+        ("f(1, 2, 3)", vec![1, 2, 3]),
+    ] {
+        // The operator following the call must not be dropped.
+        let source = format!("int main(void) {{ return {call} * 2; }}");
+        let program = Parser::build(&source)
+            .to_ast_program()
+            .expect("Should parse the call");
+
+        assert_eq!(
+            program.0[0].body[0],
+            BlockItem::S(Statement::Return(Some(Expression::Binary(
+                BinaryOperator::Multiply,
+                Box::new(Expression::FunctionCall {
+                    name: Identifier("f".into()),
+                    arguments: arguments.into_iter().map(Expression::Constant).collect(),
+                }),
+                Box::new(Expression::Constant(2)),
+            )))),
+            "{call}"
+        );
+    }
+
+    for call in ["f(1,)", "f(1 2)", "f(,)"] {
+        let source = format!("int main(void) {{ return {call}; }}");
+
+        assert!(Parser::build(&source).to_ast_program().is_err(), "{call}");
+    }
+}
+
+#[test]
+fn test_sizeof() {
+    let mut parser = Parser::build(
+        "int main(void) { int x; return sizeof(int) + sizeof x * sizeof(unsigned) + sizeof(x = 1); }",
+    );
+    let var = || Box::new(Expression::Var(Identifier("x".into())));
+    let program = parser.to_ast_program().expect("Should parse the program");
+
+    assert_eq!(
+        program.0[0].body[1],
+        BlockItem::S(Statement::Return(Some(Expression::Binary(
+            BinaryOperator::Add,
+            Box::new(Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(Expression::SizeOfType(Type::Int)),
+                Box::new(Expression::Binary(
+                    BinaryOperator::Multiply,
+                    Box::new(Expression::SizeOf(var())),
+                    Box::new(Expression::SizeOfType(Type::UnsignedInt)),
+                )),
+            )),
+            Box::new(Expression::SizeOf(Box::new(Expression::Assignment(
+                var(),
+                Box::new(Expression::Constant(1))
+            )))),
+        ))))
+    );
+}
+
+#[test]
+fn test_void_function() {
+    let program = Parser::build("void f(void) { return; } int main(void) { f(); return 0; }")
+        .to_ast_program()
+        .expect("Should parse a void function");
+
+    assert_eq!(
+        program.0[0],
+        Function {
+            name: Identifier("f".into()),
+            return_type: Type::Void,
+            params: vec![],
+            body: vec![BlockItem::S(Statement::Return(None))],
+        }
+    );
+    assert_eq!(program.0[0].to_c(), "void f(void) {\n    return;\n}");
+}
+
+#[test]
+fn test_parse_expression_only() {
+    let constant = |value| Box::new(Expression::Constant(value));
+
+    assert_eq!(
+        Parser::parse_expression_only("1 + 2 * 3").expect("Should parse the expression"),
+        Expression::Binary(
+            BinaryOperator::Add,
+            constant(1),
+            Box::new(Expression::Binary(
+                BinaryOperator::Multiply,
+                constant(2),
+                constant(3)
+            ))
+        )
+    );
+
+    assert!(matches!(
+        Parser::parse_expression_only("1 + "),
+        Err(Error::MalformedFactor {
+            found: Token::Eof,
+            ..
+        })
+    ));
+    assert!(matches!(
+        Parser::parse_expression_only("1 2"),
+        Err(Error::UnexpectedToken {
+            expected: Token::Eof,
+            found: Token::Constant(2),
+            ..
+        })
+    ));
+    assert!(Parser::parse_expression_only("1 @ 2").is_err());
+}
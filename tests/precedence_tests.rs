@@ -0,0 +1,158 @@
+use nous::{
+    ast::{BinaryOperator, BlockItem, Expression, Identifier, Statement},
+    errors::Error,
+    lexer::{Token, PRECEDENCE_TABLE},
+    parser::Parser,
+    visitor::VariableResolution,
+};
+
+fn precedence(token: Token) -> usize {
+    token.precedence().expect("Should be a binary operator")
+}
+
+/// Parses `return <expression>;` and returns the expression.
+fn parse(expression: &str) -> Expression {
+    let source = format!("int main(void) {{ return {expression}; }}");
+    let program = Parser::build(&source)
+        .to_ast_program()
+        .unwrap_or_else(|error| panic!("Should parse {expression:?}: {error:?}"));
+
+    let BlockItem::S(Statement::Return(Some(expression))) = &program.0[0].body[0] else {
+        panic!("Expected a return statement");
+    };
+    expression.clone()
+}
+
+fn var(name: &str) -> Box<Expression> {
+    Box::new(Expression::Var(Identifier(name.into())))
+}
+
+#[test]
+fn test_relative_ranking() {
+    // From the tightest binding level to the loosest one.
+    let levels = [
+        vec![Token::Mul, Token::Div, Token::Remainder],
+        vec![Token::Add, Token::Negation],
+        vec![Token::ShiftLeft, Token::ShiftRight],
+        vec![
+            Token::LessThan,
+            Token::LessThanOrEq,
+            Token::GreaterThan,
+            Token::GreaterThanOrEq,
+        ],
+        vec![Token::EqualTo, Token::NotEqualTo],
+        vec![Token::And],
+        vec![Token::Or],
+        vec![Token::QuestionMark],
+        vec![Token::Assign],
+        vec![Token::Comma],
+    ];
+
+    for level in &levels {
+        for token in level {
+            assert_eq!(precedence(token.clone()), precedence(level[0].clone()));
+        }
+    }
+    for pair in levels.windows(2) {
+        assert!(
+            precedence(pair[0][0].clone()) > precedence(pair[1][0].clone()),
+            "{:?} should bind tighter than {:?}",
+            pair[0][0],
+            pair[1][0]
+        );
+    }
+
+    // Every operator is listed exactly once.
+    assert_eq!(
+        PRECEDENCE_TABLE.len(),
+        levels.iter().map(Vec::len).sum::<usize>()
+    );
+}
+
+#[test]
+fn test_non_operators() {
+    for token in [Token::Not, Token::BitComp, Token::Colon, Token::Semicolon] {
+        assert!(matches!(token.precedence(), Err(Error::Precedence { .. })));
+    }
+}
+
+#[test]
+fn test_left_associativity() {
+    assert_eq!(
+        parse("a - b - c"),
+        Expression::Binary(
+            BinaryOperator::Subtract,
+            Box::new(Expression::Binary(
+                BinaryOperator::Subtract,
+                var("a"),
+                var("b")
+            )),
+            var("c"),
+        )
+    );
+    assert_eq!(
+        parse("a, b, c"),
+        Expression::Comma(Box::new(Expression::Comma(var("a"), var("b"))), var("c"))
+    );
+}
+
+#[test]
+fn test_right_associativity() {
+    assert_eq!(
+        parse("a = b = c"),
+        Expression::Assignment(
+            var("a"),
+            Box::new(Expression::Assignment(var("b"), var("c")))
+        )
+    );
+    assert_eq!(
+        parse("a ? b : c ? d : e"),
+        Expression::Conditional {
+            condition: var("a"),
+            exp1: var("b"),
+            exp2: Box::new(Expression::Conditional {
+                condition: var("c"),
+                exp1: var("d"),
+                exp2: var("e"),
+            }),
+        }
+    );
+}
+
+#[test]
+fn test_conditional_and_assignment() {
+    assert_eq!(
+        parse("a = b ? c : d"),
+        Expression::Assignment(
+            var("a"),
+            Box::new(Expression::Conditional {
+                condition: var("b"),
+                exp1: var("c"),
+                exp2: var("d"),
+            }),
+        )
+    );
+
+    // As in C, the conditional is the left operand of the
+    // assignment, which isn't an lvalue.
+    let conditional = parse("a ? b : c = d");
+    assert_eq!(
+        conditional,
+        Expression::Assignment(
+            Box::new(Expression::Conditional {
+                condition: var("a"),
+                exp1: var("b"),
+                exp2: var("c"),
+            }),
+            var("d"),
+        )
+    );
+
+    let program = Parser::build("int main(void) { int a; int b; int c; int d; a ? b : c = d; }")
+        .to_ast_program()
+        .expect("Should parse the program");
+    assert!(matches!(
+        VariableResolution::from(program).get_updated_program(),
+        Err(Error::InvalidLVal { .. })
+    ));
+}
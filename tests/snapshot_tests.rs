@@ -0,0 +1,83 @@
+use std::{env, fs, path::Path};
+
+use nous::{assembly::Target, compiler::Output, Compiler};
+
+/// Directory holding the `.c` programs and their `.s.golden` files.
+const GOLDEN_DIR: &str = "tests/files/golden";
+
+/// Set this variable to regenerate the golden files.
+const UPDATE_VAR: &str = "NOUS_UPDATE_GOLDEN";
+
+fn assembly(source: &str) -> String {
+    match Compiler::new()
+        .target(Target::Linux)
+        .compile(source)
+        .expect("Should compile the program")
+    {
+        Output::Assembly(assembly) => assembly,
+        output => panic!("Expected assembly, found {output:?}"),
+    }
+}
+
+/// Compares the generated assembly with the golden file,
+/// describing the first differing line.
+fn compare(name: &str, golden: &str, generated: &str) -> Result<(), String> {
+    for (line, (expected, found)) in golden.lines().zip(generated.lines()).enumerate() {
+        if expected != found {
+            return Err(format!(
+                "{name}: line {} differs, expected {expected:?} but found {found:?}",
+                line + 1
+            ));
+        }
+    }
+
+    if golden.lines().count() != generated.lines().count() {
+        return Err(format!("{name}: the number of lines differs"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_golden_files() {
+    let update = env::var_os(UPDATE_VAR).is_some();
+    let mut failures = vec![];
+
+    let mut programs: Vec<_> = fs::read_dir(GOLDEN_DIR)
+        .expect("Should read the golden directory")
+        .map(|entry| entry.expect("Should read the entry").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "c"))
+        .collect();
+    programs.sort();
+
+    for program in programs {
+        let source = fs::read_to_string(&program).expect("Should read the program");
+        let generated = assembly(&source);
+        let golden_path = program.with_extension("s.golden");
+
+        if update {
+            fs::write(&golden_path, &generated).expect("Should write the golden file");
+            continue;
+        }
+
+        let golden = fs::read_to_string(&golden_path).unwrap_or_else(|_| {
+            panic!("Missing {golden_path:?}, run with {UPDATE_VAR}=1 to create it")
+        });
+
+        if let Err(failure) = compare(&program.display().to_string(), &golden, &generated) {
+            failures.push(failure);
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_codegen_change_fails() {
+    let golden = fs::read_to_string(Path::new(GOLDEN_DIR).join("return_2.s.golden"))
+        .expect("Should read the golden file");
+    let changed = golden.replacen("$2", "$3", 1);
+
+    assert!(compare("return_2", &golden, &golden).is_ok());
+    assert!(compare("return_2", &golden, &changed).is_err());
+}
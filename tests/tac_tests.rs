@@ -1,7 +1,16 @@
+use std::collections::HashSet;
+
 use nous::{
-    ast::{BinaryOperator, Identifier},
-    tac::{self, Instruction, Val},
+    ast::{BinaryOperator, Identifier, Type},
+    errors::Error,
+    optimizations::{
+        self, ConstantFolding, DeadCodeElimination, JumpThreading, Optimization,
+        UnreachableBlockElimination,
+    },
+    parser::Parser,
+    tac::{self, Instruction, Val, TAC},
     utils::tac_from_path,
+    visitor::LoopLabeling,
 };
 
 #[test]
@@ -15,23 +24,26 @@ fn test_binary_op() {
             src_1: Val::Constant(4),
             src_2: Val::Constant(2),
             dst: Val::Var(Identifier(String::from("tmp.1"))),
+            ty: Type::Int,
         },
         Instruction::Binary {
             binary_operator: BinaryOperator::Add,
             src_1: Val::Var(Identifier(String::from("tmp.1"))),
             src_2: Val::Constant(2),
             dst: Val::Var(Identifier(String::from("tmp.2"))),
+            ty: Type::Int,
         },
         Instruction::Binary {
             binary_operator: BinaryOperator::Subtract,
             src_1: Val::Var(Identifier(String::from("tmp.2"))),
             src_2: Val::Constant(3),
             dst: Val::Var(Identifier(String::from("tmp.3"))),
+            ty: Type::Int,
         },
-        Instruction::Return(Val::Var(Identifier(String::from("tmp.3")))),
+        Instruction::Return(Some(Val::Var(Identifier(String::from("tmp.3"))))),
     ];
 
-    assert_eq!(expected_instructions, program.0.body);
+    assert_eq!(expected_instructions, program.0[0].body);
 }
 
 #[test]
@@ -45,35 +57,603 @@ fn test_if_statement() {
             src_1: Val::Constant(2),
             src_2: Val::Constant(1),
             dst: Val::Var("tmp.1".into()),
+            ty: Type::Int,
         },
         Instruction::JumpIfZero {
             condition: Val::Var("tmp.1".into()),
-            target: "else2".into(),
+            target: "else.2".into(),
         },
-        Instruction::Return(Val::Constant(3)),
+        Instruction::Return(Some(Val::Constant(3))),
         Instruction::Jump {
-            target: "end1".into(),
+            target: "end.1".into(),
         },
-        Instruction::Label("else2".into()),
+        Instruction::Label("else.2".into()),
         Instruction::Binary {
             binary_operator: BinaryOperator::GreaterThan,
             src_1: Val::Constant(1),
             src_2: Val::Constant(1),
             dst: Val::Var("tmp.2".into()),
+            ty: Type::Int,
         },
         Instruction::JumpIfZero {
             condition: Val::Var("tmp.2".into()),
-            target: "else4".into(),
+            target: "else.4".into(),
+        },
+        Instruction::Return(Some(Val::Constant(2))),
+        Instruction::Jump {
+            target: "end.3".into(),
+        },
+        Instruction::Label("else.4".into()),
+        Instruction::Return(Some(Val::Constant(1))),
+        Instruction::Label("end.3".into()),
+        Instruction::Label("end.1".into()),
+    ];
+
+    assert_eq!(expected_instructions, program.0[0].body);
+}
+
+#[test]
+fn test_constant_folding() {
+    let tac = TAC::from(String::from("int main(void) { return 2 + 3 * 4; }")).to_tac_program();
+    let program = optimizations::optimize(tac, 1);
+
+    let expected_instructions = vec![
+        Instruction::Copy {
+            src: Val::Constant(12),
+            dst: Val::Var("tmp.1".into()),
+        },
+        Instruction::Binary {
+            binary_operator: BinaryOperator::Add,
+            src_1: Val::Constant(2),
+            src_2: Val::Var("tmp.1".into()),
+            dst: Val::Var("tmp.2".into()),
+            ty: Type::Int,
+        },
+        Instruction::Return(Some(Val::Var("tmp.2".into()))),
+    ];
+
+    assert_eq!(expected_instructions, program.0[0].body);
+}
+
+#[test]
+fn test_constant_folding_keeps_runtime_errors() {
+    let tac = TAC::from(String::from(
+        "int main(void) { int a = 1 / 0; return 2147483647 + 1; }",
+    ))
+    .to_tac_program();
+    let program = optimizations::optimize(tac, 1);
+
+    assert_eq!(
+        program.0[0]
+            .body
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::Binary { .. }))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn test_unlabeled_loop() {
+    let mut tac = TAC::build("int main(void) { while (1) { break; } return 0; }");
+
+    assert!(matches!(
+        tac.try_to_tac_program(),
+        Err(Error::UnlabeledLoop { function }) if function == "main".into()
+    ));
+}
+
+#[test]
+fn test_shift_folding() {
+    let shift = |binary_operator, src_1, src_2, ty| Instruction::Binary {
+        binary_operator,
+        src_1: Val::Constant(src_1),
+        src_2: Val::Constant(src_2),
+        dst: Val::Var("x".into()),
+        ty,
+    };
+    let fold = |instruction| ConstantFolding.optimize(vec![instruction]).remove(0);
+    let copy = |value| Instruction::Copy {
+        src: Val::Constant(value),
+        dst: Val::Var("x".into()),
+    };
+
+    assert_eq!(
+        fold(shift(BinaryOperator::ShiftLeft, 1, 5, Type::Int)),
+        copy(32)
+    );
+    assert_eq!(
+        fold(shift(BinaryOperator::ShiftRight, -256, 2, Type::Int)),
+        copy(-64)
+    );
+    assert_eq!(
+        fold(shift(
+            BinaryOperator::ShiftRight,
+            4294967295,
+            28,
+            Type::UnsignedInt
+        )),
+        copy(15)
+    );
+
+    // Counts out of range are left to the runtime.
+    for count in [32, -1] {
+        let instruction = shift(BinaryOperator::ShiftLeft, 1, count, Type::Int);
+        assert_eq!(fold(instruction.clone()), instruction);
+    }
+}
+
+#[test]
+fn test_unsigned_constant_folding() {
+    let tac = TAC::from(String::from(
+        "int main(void) { unsigned a = 0u - 1u; return a > 1u; }",
+    ))
+    .to_tac_program();
+
+    assert!(matches!(
+        tac.0[0].body[0],
+        Instruction::Binary {
+            ty: Type::UnsignedInt,
+            ..
+        }
+    ));
+
+    let program = optimizations::optimize(tac, 1);
+
+    assert_eq!(
+        program.0[0].body[0],
+        Instruction::Copy {
+            src: Val::Constant(4294967295),
+            dst: Val::Var("tmp.1".into()),
+        }
+    );
+}
+
+#[test]
+fn test_select() {
+    let tac = TAC::from(String::from(
+        "int main(void) { int a = 1; return a ? 2 : a; }",
+    ))
+    .to_tac_program();
+
+    assert_eq!(
+        tac.0[0].body[1],
+        Instruction::Select {
+            condition: Val::Var("a".into()),
+            src_1: Val::Constant(2),
+            src_2: Val::Var("a".into()),
+            dst: Val::Var("tmp.1".into()),
+        }
+    );
+}
+
+#[test]
+fn test_dead_code_elimination() {
+    let tac = TAC::from(String::from(
+        "int main(void) { return 1; int a = 2; a = a + 3; return a; }",
+    ))
+    .to_tac_program();
+    let program = optimizations::optimize(tac, 1);
+
+    assert_eq!(
+        vec![Instruction::Return(Some(Val::Constant(1)))],
+        program.0[0].body
+    );
+}
+
+#[test]
+fn test_dead_code_elimination_labels() {
+    let instructions = vec![
+        Instruction::JumpIfZero {
+            condition: Val::Var("a".into()),
+            target: "else.1".into(),
+        },
+        Instruction::Jump {
+            target: "end.2".into(),
+        },
+        Instruction::Copy {
+            src: Val::Constant(1),
+            dst: Val::Var("a".into()),
+        },
+        Instruction::Label("else.1".into()),
+        Instruction::Return(Some(Val::Constant(2))),
+        Instruction::Label("unused.3".into()),
+        Instruction::Return(Some(Val::Constant(3))),
+        Instruction::Label("end.2".into()),
+        Instruction::Return(Some(Val::Constant(4))),
+    ];
+
+    let expected_instructions = vec![
+        Instruction::JumpIfZero {
+            condition: Val::Var("a".into()),
+            target: "else.1".into(),
+        },
+        Instruction::Jump {
+            target: "end.2".into(),
+        },
+        Instruction::Label("else.1".into()),
+        Instruction::Return(Some(Val::Constant(2))),
+        Instruction::Label("end.2".into()),
+        Instruction::Return(Some(Val::Constant(4))),
+    ];
+
+    let optimized = DeadCodeElimination.optimize(instructions);
+    assert_eq!(expected_instructions, optimized);
+    assert_eq!(
+        expected_instructions,
+        DeadCodeElimination.optimize(optimized)
+    );
+}
+
+#[test]
+fn test_unreachable_block_elimination() {
+    let tac = TAC::build("int main(void) { int x = 1; if (0) x = 2; else x = 3; return x; }")
+        .to_tac_program();
+    let body = JumpThreading.optimize(ConstantFolding.optimize(tac.0[0].body.clone()));
+    let optimized = UnreachableBlockElimination.optimize(body);
+
+    // The condition folds to a jump over the `then` branch.
+    assert_eq!(
+        tac::Program(
+            vec![tac::Function {
+                body: optimized,
+                ..tac.0[0].clone()
+            }],
+            vec![]
+        )
+        .format(),
+        "main:\n\tx = 1\n\tJump else.2\nelse.2:\n\tx = 3\nend.1:\n\tReturn x\n"
+    );
+}
+
+#[test]
+fn test_constant_branches() {
+    let instructions = vec![
+        Instruction::JumpIfZero {
+            condition: Val::Constant(0),
+            target: "a.1".into(),
+        },
+        Instruction::JumpIfZero {
+            condition: Val::Constant(3),
+            target: "b.2".into(),
+        },
+        Instruction::JumpIfNotZero {
+            condition: Val::Constant(0),
+            target: "c.3".into(),
+        },
+        Instruction::JumpIfNotZero {
+            condition: Val::Constant(-1),
+            target: "d.4".into(),
+        },
+        Instruction::JumpIfZero {
+            condition: Val::Var("x".into()),
+            target: "e.5".into(),
+        },
+    ];
+
+    assert_eq!(
+        JumpThreading.optimize(instructions),
+        vec![
+            Instruction::Jump {
+                target: "a.1".into()
+            },
+            Instruction::Jump {
+                target: "d.4".into()
+            },
+            Instruction::JumpIfZero {
+                condition: Val::Var("x".into()),
+                target: "e.5".into(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_jump_threading() {
+    let instructions = vec![
+        Instruction::JumpIfZero {
+            condition: Val::Var("x".into()),
+            target: "a.1".into(),
+        },
+        Instruction::Jump {
+            target: "b.2".into(),
+        },
+        Instruction::Label("a.1".into()),
+        Instruction::Label("c.3".into()),
+        Instruction::Jump {
+            target: "b.2".into(),
+        },
+        Instruction::Label("b.2".into()),
+        Instruction::Jump {
+            target: "end.4".into(),
+        },
+        Instruction::Label("end.4".into()),
+        Instruction::Return(Some(Val::Var("x".into()))),
+    ];
+
+    // Both jumps go through the chain to `end.4`.
+    let optimized = JumpThreading.optimize(instructions);
+    assert_eq!(
+        optimized[..2],
+        [
+            Instruction::JumpIfZero {
+                condition: Val::Var("x".into()),
+                target: "end.4".into(),
+            },
+            Instruction::Jump {
+                target: "end.4".into(),
+            },
+        ]
+    );
+    assert_eq!(
+        optimized[4],
+        Instruction::Jump {
+            target: "end.4".into(),
+        }
+    );
+
+    // A loop of jumps is left as is.
+    let instructions = vec![
+        Instruction::Label("a.1".into()),
+        Instruction::Jump {
+            target: "b.2".into(),
+        },
+        Instruction::Label("b.2".into()),
+        Instruction::Jump {
+            target: "a.1".into(),
+        },
+    ];
+    assert_eq!(JumpThreading.optimize(instructions.clone()), instructions);
+}
+
+#[test]
+fn test_jump_threading_pipeline() {
+    let tac = TAC::build("int main(void) { int x = 1; if (1) x = 2; return x; }").to_tac_program();
+    let program = optimizations::optimize(tac, 1);
+
+    // The branch is dropped along with the label it targeted.
+    assert_eq!(program.format(), "main:\n\tx = 1\n\tx = 2\n\tReturn x\n");
+}
+
+#[test]
+fn test_unique_labels() {
+    let tac = TAC::from(String::from(
+        "int main(void) { int a = 1; return a && 2 || (a ? 0 && a : a || 3); }",
+    ))
+    .to_tac_program();
+
+    let labels: Vec<&Identifier> = tac.0[0]
+        .body
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::Label(label) => Some(label),
+            _ => None,
+        })
+        .collect();
+    let unique: HashSet<&Identifier> = labels.iter().copied().collect();
+
+    assert_eq!(labels.len(), 10);
+    assert_eq!(labels.len(), unique.len());
+}
+
+#[test]
+fn test_logical_result_variables() {
+    let tac = TAC::from(String::from("int main(void) { return (1 && 0) || 1; }")).to_tac_program();
+
+    let expected_instructions = vec![
+        Instruction::JumpIfZero {
+            condition: Val::Constant(1),
+            target: "and_false.2".into(),
+        },
+        Instruction::JumpIfZero {
+            condition: Val::Constant(0),
+            target: "and_false.2".into(),
+        },
+        Instruction::Copy {
+            src: Val::Constant(1),
+            dst: Val::Var("result.2".into()),
+        },
+        Instruction::Jump {
+            target: "end.2".into(),
+        },
+        Instruction::Label("and_false.2".into()),
+        Instruction::Copy {
+            src: Val::Constant(0),
+            dst: Val::Var("result.2".into()),
+        },
+        Instruction::Label("end.2".into()),
+        Instruction::JumpIfNotZero {
+            condition: Val::Var("result.2".into()),
+            target: "or_true.1".into(),
+        },
+        Instruction::JumpIfNotZero {
+            condition: Val::Constant(1),
+            target: "or_true.1".into(),
+        },
+        Instruction::Copy {
+            src: Val::Constant(0),
+            dst: Val::Var("result.1".into()),
         },
-        Instruction::Return(Val::Constant(2)),
         Instruction::Jump {
-            target: "end3".into(),
+            target: "end.1".into(),
         },
-        Instruction::Label("else4".into()),
-        Instruction::Return(Val::Constant(1)),
-        Instruction::Label("end3".into()),
-        Instruction::Label("end1".into()),
+        Instruction::Label("or_true.1".into()),
+        Instruction::Copy {
+            src: Val::Constant(1),
+            dst: Val::Var("result.1".into()),
+        },
+        Instruction::Label("end.1".into()),
+        Instruction::Return(Some(Val::Var("result.1".into()))),
     ];
 
-    assert_eq!(expected_instructions, program.0.body);
+    assert_eq!(expected_instructions, tac.0[0].body);
+}
+
+#[test]
+fn test_format() {
+    let tac = TAC::from(String::from(
+        "int main(void) { int a = 2; if (a > 1) return ~a; return a && 3; }",
+    ))
+    .to_tac_program();
+
+    let expected = "\
+main:
+\ta = 2
+\ttmp.1 = a GreaterThan 1
+\tJumpIfZero tmp.1, end.1
+\ttmp.2 = Complement a
+\tReturn tmp.2
+end.1:
+\tJumpIfZero a, and_false.2
+\tJumpIfZero 3, and_false.2
+\tresult.2 = 1
+\tJump end.2
+and_false.2:
+\tresult.2 = 0
+end.2:
+\tReturn result.2
+";
+
+    assert_eq!(tac.format(), expected);
+}
+
+#[test]
+fn test_deeply_nested_blocks() {
+    let depth = 500;
+    let source = format!(
+        "int main(void) {{ {} return 2; {} }}",
+        "{ ".repeat(depth),
+        "} ".repeat(depth)
+    );
+
+    let tac = TAC::from(source).to_tac_program();
+
+    assert_eq!(
+        vec![Instruction::Return(Some(Val::Constant(2)))],
+        tac.0[0].body
+    );
+}
+
+#[test]
+fn test_build() {
+    let tac = TAC::build("int main(void) { return 2; }").to_tac_program();
+
+    assert_eq!(
+        vec![Instruction::Return(Some(Val::Constant(2)))],
+        tac.0[0].body
+    );
+}
+
+#[test]
+fn test_while_loop() {
+    let program = Parser::build("int main(void) { while (1) break; return 0; }")
+        .to_ast_program()
+        .expect("Should parse the program");
+    let program = LoopLabeling::from(program)
+        .get_updated_program()
+        .expect("Should label the loop");
+
+    let expected = "\
+main:
+continue_loop.1:
+\tJumpIfZero 1, break_loop.1
+\tJump break_loop.1
+\tJump continue_loop.1
+break_loop.1:
+\tReturn 0
+";
+
+    assert_eq!(TAC::from(program).to_tac_program().format(), expected);
+}
+
+#[test]
+fn test_comma() {
+    let program = TAC::build("int main(void) { int x; return (x = 1, x + 1); }").to_tac_program();
+
+    let expected = "\
+main:
+\tx = 1
+\ttmp.1 = x Add 1
+\tReturn tmp.1
+";
+
+    assert_eq!(program.format(), expected);
+}
+
+#[test]
+fn test_character_literal() {
+    let program = TAC::build("int main(void) { return 'A'; }").to_tac_program();
+
+    assert_eq!(
+        vec![Instruction::Return(Some(Val::Constant(65)))],
+        program.0[0].body
+    );
+}
+
+#[test]
+fn test_sizeof_is_not_evaluated() {
+    let program = TAC::build("int main(void) { int x = 1; return sizeof(x = 5) + sizeof(int); }")
+        .to_tac_program();
+
+    // Only the initializer assigns `x`.
+    assert_eq!(
+        program.format(),
+        "main:\n\tx = 1\n\ttmp.1 = 4 Add 4 (unsigned int)\n\tReturn tmp.1\n"
+    );
+}
+
+#[test]
+fn test_copy_propagation() {
+    let tac = TAC::build("int main(void) { int x = 4; return x; }").to_tac_program();
+    let program = optimizations::optimize(tac, 2);
+
+    // The copy into `x` is dropped once nothing reads it.
+    assert_eq!(
+        vec![Instruction::Return(Some(Val::Constant(4)))],
+        program.0[0].body
+    );
+}
+
+#[test]
+fn test_copy_propagation_boundaries() {
+    let optimize = |source: &str| {
+        let program = Parser::build(source)
+            .to_ast_program()
+            .expect("Should parse the program");
+        let program = LoopLabeling::from(program)
+            .get_updated_program()
+            .expect("Should label the loops");
+
+        optimizations::optimize(TAC::from(program).to_tac_program(), 2).format()
+    };
+
+    // `x` is written within the loop, so its copy doesn't reach
+    // the condition past the label.
+    assert_eq!(
+        optimize("int main(void) { int x = 1; while (x < 3) x = x + 1; return x; }"),
+        "\
+main:
+\tx = 1
+continue_loop.1:
+\ttmp.1 = x LessThan 3
+\tJumpIfZero tmp.1, break_loop.1
+\ttmp.2 = x Add 1
+\tx = tmp.2
+\tJump continue_loop.1
+break_loop.1:
+\tReturn x
+"
+    );
+
+    // The callee may write `g`, but `x` keeps its value.
+    assert_eq!(
+        optimize("int g = 1; int main(void) { int x = g; putchar(x); return x; }"),
+        "\
+static g = 1
+main:
+\tx = g
+\ttmp.1 = Call putchar(g)
+\tReturn x
+"
+    );
 }
@@ -1,4 +1,11 @@
-use nous::{errors::Result, utils::parser_from_path, visitor::VariableResolution};
+use miette::{Diagnostic, Severity};
+use nous::{
+    ast::{BinaryOperator, BlockItem, Declaration, Expression, Statement, Type},
+    errors::{Error, Result},
+    parser::Parser,
+    utils::parser_from_path,
+    visitor::{LoopLabeling, ReturnAnalysis, UnusedValueAnalysis, VariableResolution},
+};
 
 #[test]
 // TODO: Improve error testing: Which error variant?
@@ -29,3 +36,730 @@ fn test_invalid_lval() -> Result<()> {
     assert!(verify.get_updated_block_items().is_err());
     Ok(())
 }
+
+#[test]
+fn test_conditional_lval() -> Result<()> {
+    let resolve = |source: &str| {
+        VariableResolution::from(Parser::build(source).to_ast_program()?).get_updated_program()
+    };
+
+    // Without parentheses the conditional still binds tighter.
+    for source in [
+        "int main(void) { int a; int b; int c; (a ? b : c) = 1; return 0; }",
+        "int main(void) { int a; int b; int c; a ? b : c = 1; return 0; }",
+    ] {
+        assert!(
+            matches!(
+                resolve(source),
+                Err(Error::InvalidLVal {
+                    value: Expression::Conditional { .. }
+                })
+            ),
+            "{source}"
+        );
+    }
+
+    resolve(
+        "int main(void) { int a; int b; int c; int x = a ? b : c; \
+         x = (a ? b : c); x = a ? b = 1 : c; return a ? x : b; }",
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_rename_separator() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { int a = 2; return a; }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?).separator('$');
+
+    let block_items = &verify.get_updated_block_items()?[0];
+
+    assert_eq!(
+        block_items[0],
+        BlockItem::D(Declaration {
+            name: "a$1".into(),
+            ty: Type::Int,
+            initializer: Some(Expression::Constant(2)),
+        })
+    );
+    assert_eq!(
+        block_items[1],
+        BlockItem::S(Statement::Return(Some(Expression::Var("a$1".into()))))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_block_scopes() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { int a = 1; { int a = 2; a = 3; } return a; }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+    let block_items = &verify.get_updated_block_items()?[0];
+
+    assert_eq!(
+        block_items[1],
+        BlockItem::S(Statement::Compound(vec![
+            BlockItem::D(Declaration {
+                name: "a.2".into(),
+                ty: Type::Int,
+                initializer: Some(Expression::Constant(2)),
+            }),
+            BlockItem::S(Statement::Expression(Expression::Assignment(
+                Box::new(Expression::Var("a.2".into())),
+                Box::new(Expression::Constant(3)),
+            ))),
+        ]))
+    );
+    assert_eq!(
+        block_items[2],
+        BlockItem::S(Statement::Return(Some(Expression::Var("a.1".into()))))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_block_duplicate_declaration() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { { int a = 1; int a = 2; } return 0; }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+    assert!(verify.get_updated_block_items().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_nested_shadowing() -> Result<()> {
+    let mut parser = Parser::build(
+        "int g = 1; \
+         int main(void) { int a = 1; { int a = 2; { int g = a; a = g; } a = 4; } return a + g; }",
+    );
+    let program = VariableResolution::from(parser.to_ast_program()?).get_updated_program()?;
+    let body = &program.0[0].body;
+
+    let BlockItem::S(Statement::Compound(outer)) = &body[1] else {
+        panic!("Expected a compound statement");
+    };
+    let BlockItem::S(Statement::Compound(inner)) = &outer[1] else {
+        panic!("Expected a nested compound statement");
+    };
+    assert_eq!(
+        inner,
+        &vec![
+            BlockItem::D(Declaration {
+                name: "g.3".into(),
+                ty: Type::Int,
+                initializer: Some(Expression::Var("a.2".into())),
+            }),
+            BlockItem::S(Statement::Expression(Expression::Assignment(
+                Box::new(Expression::Var("a.2".into())),
+                Box::new(Expression::Var("g.3".into())),
+            ))),
+        ]
+    );
+    assert_eq!(
+        outer[2],
+        BlockItem::S(Statement::Expression(Expression::Assignment(
+            Box::new(Expression::Var("a.2".into())),
+            Box::new(Expression::Constant(4)),
+        )))
+    );
+    // Once the blocks end, `a` and `g` are the outer ones again.
+    assert_eq!(
+        body[2],
+        BlockItem::S(Statement::Return(Some(Expression::Binary(
+            BinaryOperator::Add,
+            Box::new(Expression::Var("a.1".into())),
+            Box::new(Expression::Var("g".into())),
+        ))))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_variable_shadowing_function() -> Result<()> {
+    let mut parser = Parser::build(
+        "int helper(void) { return 1; } \
+         int main(void) { { int helper = 2; helper = helper + 1; } return helper(); }",
+    );
+    let program = VariableResolution::from(parser.to_ast_program()?).get_updated_program()?;
+
+    let BlockItem::S(Statement::Compound(block)) = &program.0[1].body[0] else {
+        panic!("Expected a compound statement");
+    };
+    assert_eq!(
+        block[1],
+        BlockItem::S(Statement::Expression(Expression::Assignment(
+            Box::new(Expression::Var("helper.1".into())),
+            Box::new(Expression::Binary(
+                BinaryOperator::Add,
+                Box::new(Expression::Var("helper.1".into())),
+                Box::new(Expression::Constant(1)),
+            )),
+        )))
+    );
+
+    // The function can't be called while the variable hides it.
+    let mut parser = Parser::build(
+        "int helper(void) { return 1; } int main(void) { int helper = 2; return helper(); }",
+    );
+    assert!(matches!(
+        VariableResolution::from(parser.to_ast_program()?).get_updated_program(),
+        Err(Error::NotAFunction { name }) if name.0 == "helper"
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_same_block_duplicate() -> Result<()> {
+    for source in [
+        "int main(void) { int a = 1; int a = 2; return a; }",
+        // Only the second `b` of the last block is a duplicate,
+        // the inner `a` is a legal shadow.
+        "int main(void) { int a = 1; { int a = 2; } int b = 3; { int b; int b; } return a; }",
+    ] {
+        let mut parser = Parser::build(source);
+
+        assert!(matches!(
+            VariableResolution::from(parser.to_ast_program()?).get_updated_program(),
+            Err(Error::DuplicateVarDeclaration { .. })
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_break_outside_loop() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { if (1) break; return 0; }");
+    let mut labeling = LoopLabeling::from(parser.to_ast_program()?);
+
+    assert!(matches!(
+        labeling.get_updated_program(),
+        Err(Error::BreakOutsideLoop { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_continue_outside_loop() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { { continue; } return 0; }");
+    let mut labeling = LoopLabeling::from(parser.to_ast_program()?);
+
+    assert!(matches!(
+        labeling.get_updated_program(),
+        Err(Error::ContinueOutsideLoop { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_loop_labels() -> Result<()> {
+    let mut parser =
+        Parser::build("int main(void) { while (1) { for (;;) break; continue; } return 0; }");
+    let program = LoopLabeling::from(parser.to_ast_program()?).get_updated_program()?;
+
+    let BlockItem::S(Statement::While { body, label, .. }) = &program.0[0].body[0] else {
+        panic!("Expected a while loop");
+    };
+    assert_eq!(label, &Some("loop.1".into()));
+
+    let Statement::Compound(block_items) = body.as_ref() else {
+        panic!("Expected a compound statement");
+    };
+    assert!(matches!(
+        &block_items[0],
+        BlockItem::S(Statement::For { body, label: Some(label), .. })
+            if label.0 == "loop.2"
+                && **body == Statement::Break(Some("loop.2".into()))
+    ));
+    assert_eq!(
+        block_items[1],
+        BlockItem::S(Statement::Continue(Some("loop.1".into())))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_undeclared_function() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { return missing(); }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+    assert!(matches!(
+        verify.get_updated_program(),
+        Err(Error::UndeclaredFunction { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_forward_call() -> Result<()> {
+    // Every function of the file is declared before any body is
+    // resolved, so `main` may call one defined below it.
+    let mut parser =
+        Parser::build("int main(void) { return helper(); } int helper(void) { return 3; }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?);
+    verify.get_updated_program()?;
+
+    let mut parser =
+        Parser::build("int main(void) { return helper(); } int helper(void) { return missing(); }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?);
+    assert!(matches!(
+        verify.get_updated_program(),
+        Err(Error::UndeclaredFunction { name }) if name.0 == "missing"
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_argument_count() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { exit(1, 2); return 0; }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+    assert!(matches!(
+        verify.get_updated_program(),
+        Err(Error::ArgumentCount {
+            expected: 1,
+            found: 2,
+            ..
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_extern_function() -> Result<()> {
+    let mut parser = Parser::build("int main(void) { return abs(-1); }");
+    let mut verify = VariableResolution::from(parser.to_ast_program()?).extern_function("abs", 1);
+
+    assert!(verify.get_updated_program().is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_globals_keep_their_names() -> Result<()> {
+    let mut parser = Parser::build("int g = -1; int main(void) { int g = 2; return g; }");
+    let program = VariableResolution::from(parser.to_ast_program()?).get_updated_program()?;
+
+    assert_eq!(program.1[0].name, "g".into());
+    assert_eq!(
+        program.0[0].body[1],
+        BlockItem::S(Statement::Return(Some(Expression::Var("g.1".into()))))
+    );
+
+    let mut parser = Parser::build("int g; int main(void) { return g; }");
+    let program = VariableResolution::from(parser.to_ast_program()?).get_updated_program()?;
+
+    assert_eq!(
+        program.0[0].body[0],
+        BlockItem::S(Statement::Return(Some(Expression::Var("g".into()))))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_globals() -> Result<()> {
+    for source in [
+        "int g = 1 + 2; int main(void) { return g; }",
+        "int g = 1; int g; int main(void) { return g; }",
+        "int main; int main(void) { return 0; }",
+    ] {
+        let mut parser = Parser::build(source);
+        let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+        assert!(
+            matches!(
+                verify.get_updated_program(),
+                Err(Error::NonConstantInitializer { .. } | Error::DuplicateVarDeclaration { .. })
+            ),
+            "{source}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_missing_return() -> Result<()> {
+    let mut parser = Parser::build(
+        "int helper(void) { if (1) return 1; } \
+         int both(void) { if (1) { return 1; } else return 2; } \
+         int main(void) { int x = 1; }",
+    );
+    let mut analysis = ReturnAnalysis::from(parser.to_ast_program()?);
+    let program = analysis.get_updated_program()?;
+
+    // Only `helper` and `main` can reach their end.
+    assert_eq!(program.0[0].body.len(), 2);
+    assert_eq!(program.0[1].body.len(), 1);
+    assert_eq!(
+        program.0[2].body.last(),
+        Some(&BlockItem::S(Statement::Return(Some(
+            Expression::Constant(0)
+        ))))
+    );
+
+    // Reaching the end of `main` is fine.
+    assert!(matches!(
+        analysis.warnings(),
+        [Error::MissingReturn { name }] if name.0 == "helper"
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_conflicting_functions() -> Result<()> {
+    for source in [
+        "int f(void) { return 1; } int f(void) { return 2; } int main(void) { return f(); }",
+        // `exit` takes an argument.
+        "int exit(void) { return 1; } int main(void) { return exit(); }",
+    ] {
+        let mut parser = Parser::build(source);
+        let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+        assert!(
+            matches!(
+                verify.get_updated_program(),
+                Err(Error::ConflictingDeclaration { .. })
+            ),
+            "{source}"
+        );
+    }
+
+    // Two bodies, or declarations disagreeing with the definition.
+    for source in [
+        "int f(int); int f(void) { return 1; } int main(void) { return f(); }",
+        "int f(int a) { return a; } int f(int, int); int main(void) { return 0; }",
+        "void f(void); int f(void) { return 1; } int main(void) { return f(); }",
+        "int f(void); int f(void) { return 1; } int f(void) { return 2; } \
+         int main(void) { return f(); }",
+    ] {
+        let mut parser = Parser::build(source);
+        let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+        assert!(
+            matches!(
+                verify.get_updated_program(),
+                Err(Error::ConflictingDeclaration { .. })
+            ),
+            "{source}"
+        );
+    }
+
+    // Defining an external function with its arity is fine.
+    let mut parser =
+        Parser::build("int abort(void) { return 1; } int main(void) { return abort(); }");
+    assert!(VariableResolution::from(parser.to_ast_program()?)
+        .get_updated_program()
+        .is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_function_declarations() -> Result<()> {
+    for source in [
+        "int f(int); int f(int a) { return a; } int main(void) { return f(1); }",
+        "int f(void); int main(void) { return f(); } int f(void) { return 1; }",
+        // Declaring a function several times, or one defined elsewhere.
+        "int f(int); int f(int b); int main(void) { return f(2) + g(); } int g(void); \
+         int f(int a) { return a; }",
+        "int putchar(int c); int main(void) { return putchar(65); }",
+    ] {
+        let mut parser = Parser::build(source);
+        let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+        assert!(verify.get_updated_program().is_ok(), "{source}");
+    }
+
+    // Calls are checked against the declaration.
+    let mut parser = Parser::build("int f(int, int); int main(void) { return f(1); }");
+    assert!(matches!(
+        VariableResolution::from(parser.to_ast_program()?).get_updated_program(),
+        Err(Error::ArgumentCount {
+            expected: 2,
+            found: 1,
+            ..
+        })
+    ));
+
+    // Parameters share the scope of the body, and are renamed.
+    let mut parser =
+        Parser::build("int f(int a) { int a; return a; } int main(void) { return 0; }");
+    assert!(matches!(
+        VariableResolution::from(parser.to_ast_program()?).get_updated_program(),
+        Err(Error::DuplicateVarDeclaration { .. })
+    ));
+
+    let mut parser = Parser::build("int f(int a) { return a; } int main(void) { return f(1); }");
+    let program = VariableResolution::from(parser.to_ast_program()?).get_updated_program()?;
+    assert_eq!(program.0[0].params[0].name.0, "a.1");
+    assert_eq!(
+        program.0[0].body[0],
+        BlockItem::S(Statement::Return(Some(Expression::Var("a.1".into()))))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_void_functions() -> Result<()> {
+    let mut parser = Parser::build(
+        "void early(void) { if (1) return; } \
+         void empty(void) {} \
+         int main(void) { early(); return 0; }",
+    );
+    let mut analysis = ReturnAnalysis::from(parser.to_ast_program()?);
+    let program = analysis.get_updated_program()?;
+
+    // Reaching the end of a `void` function is fine.
+    for function in &program.0[..2] {
+        assert_eq!(function.return_type, Type::Void);
+        assert_eq!(
+            function.body.last(),
+            Some(&BlockItem::S(Statement::Return(None)))
+        );
+    }
+    assert!(analysis.warnings().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_mismatched_returns() -> Result<()> {
+    for (source, return_type) in [
+        (
+            "void f(void) { return 1; } int main(void) { return 0; }",
+            Type::Void,
+        ),
+        ("int main(void) { while (1) { return; } }", Type::Int),
+    ] {
+        let mut parser = Parser::build(source);
+        let result = ReturnAnalysis::from(parser.to_ast_program()?).get_updated_program();
+
+        assert!(
+            matches!(&result, Err(Error::InvalidReturn { return_type: ty, .. }) if *ty == return_type),
+            "{source}: {result:?}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_break_at_function_scope() -> Result<()> {
+    let mut parser = Parser::build(
+        "int helper(void) { while (1) break; return 1; } \
+         int main(void) { break; return 0; }",
+    );
+    let mut labeling = LoopLabeling::from(parser.to_ast_program()?);
+
+    // The error names the function holding the `break`.
+    let result = labeling.get_updated_program();
+    assert!(
+        matches!(&result, Err(Error::BreakOutsideLoop { function }) if function.0 == "main"),
+        "{result:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_array_uses() -> Result<()> {
+    let resolve = |source: &str| {
+        VariableResolution::from(Parser::build(source).to_ast_program()?).get_updated_program()
+    };
+
+    resolve("int main(void) { int a[2]; a[0] = 1; return a[a[0]] + sizeof a; }")?;
+
+    for source in [
+        "int main(void) { int a[2]; return a; }",
+        "int main(void) { int a[2]; int b[2]; a = b; return 0; }",
+    ] {
+        assert!(
+            matches!(resolve(source), Err(Error::ArrayValue { name }) if name.0 == "a"),
+            "{source}"
+        );
+    }
+    for source in [
+        "int main(void) { int a = 1; return a[0]; }",
+        "int main(void) { int a[2]; return (a[0])[1]; }",
+    ] {
+        assert!(
+            matches!(resolve(source), Err(Error::NotAnArray { .. })),
+            "{source}"
+        );
+    }
+    assert!(matches!(
+        resolve("int g[2]; int main(void) { return 0; }"),
+        Err(Error::Unsupported {
+            feature: "file scope arrays"
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_enum_uses() -> Result<()> {
+    let resolve = |source: &str| {
+        VariableResolution::from(Parser::build(source).to_ast_program()?).get_updated_program()
+    };
+
+    // Enumerators become constants, shadowing like variables.
+    let program = resolve(
+        "enum { A, B = 4 }; int g = B; \
+         int main(void) { int A = 1; { enum { A = 7 }; return A + B; } }",
+    )?;
+    assert_eq!(program.1[0].initializer, Some(Expression::Constant(4)));
+    let BlockItem::S(Statement::Compound(inner)) = &program.0[0].body[1] else {
+        panic!("Expected the inner block, found {:?}", program.0[0].body[1]);
+    };
+    assert_eq!(
+        inner[1],
+        BlockItem::S(Statement::Return(Some(Expression::Binary(
+            BinaryOperator::Add,
+            Box::new(Expression::Constant(7)),
+            Box::new(Expression::Constant(4)),
+        ))))
+    );
+
+    for source in [
+        "enum { A }; int main(void) { A = 1; return 0; }",
+        "enum { A }; int main(void) { int *p = &A; return 0; }",
+    ] {
+        assert!(
+            matches!(resolve(source), Err(Error::InvalidLVal { .. })),
+            "{source}"
+        );
+    }
+    for source in [
+        "enum { A, A }; int main(void) { return 0; }",
+        "enum { A }; int A; int main(void) { return 0; }",
+        "enum { main }; int main(void) { return 0; }",
+        "int main(void) { int A; enum { A }; return 0; }",
+    ] {
+        assert!(
+            matches!(resolve(source), Err(Error::DuplicateVarDeclaration { .. })),
+            "{source}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pointer_uses() -> Result<()> {
+    let resolve = |source: &str| {
+        VariableResolution::from(Parser::build(source).to_ast_program()?).get_updated_program()
+    };
+
+    resolve("int main(void) { int x; int *p = &x; int *q; q = &*p; *q = 1; return *p + x; }")?;
+
+    for source in [
+        "int main(void) { int x; int *p = x; return 0; }",
+        "int main(void) { int x; int *p = &x; return p; }",
+        "int main(void) { int x; return *x; }",
+        "int main(void) { int x; int *p = &x; return p + 1; }",
+    ] {
+        assert!(
+            matches!(resolve(source), Err(Error::PointerMismatch { .. })),
+            "{source}"
+        );
+    }
+    assert!(matches!(
+        resolve("int main(void) { return &3; }"),
+        Err(Error::InvalidLVal { .. })
+    ));
+    for (source, unsupported) in [
+        (
+            "int main(void) { int a[2]; int *p = &a[0]; return 0; }",
+            "pointers to array elements",
+        ),
+        (
+            "int *g; int main(void) { return 0; }",
+            "file scope pointers",
+        ),
+    ] {
+        assert!(
+            matches!(
+                resolve(source),
+                Err(Error::Unsupported { feature }) if feature == unsupported
+            ),
+            "{source}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_unused_values() -> Result<()> {
+    let mut parser = Parser::build(
+        "int main(void) { int x = 1; x; x = x; if (x) { 1 + 1; } \
+         x = 2; putchar(x); x == 1 ? x : putchar(x); return x; }",
+    );
+    let program = parser.to_ast_program()?;
+    let mut analysis = UnusedValueAnalysis::from(program.clone());
+
+    // The program itself is left untouched.
+    assert_eq!(analysis.get_updated_program()?, program);
+
+    let mut warnings: Vec<String> = analysis
+        .warnings()
+        .iter()
+        .map(|warning| match warning {
+            Error::UnusedValue { value } => format!("unused {}", value.to_c()),
+            Error::SelfAssignment { value } => format!("self {}", value.to_c()),
+            warning => panic!("Unexpected warning {warning:?}"),
+        })
+        .collect();
+    warnings.sort();
+
+    // Variables keep the names of the source.
+    assert_eq!(warnings, ["self x", "unused 1 + 1", "unused x"]);
+    assert!(analysis
+        .warnings()
+        .iter()
+        .all(|warning| warning.severity() == Some(Severity::Warning)));
+    Ok(())
+}
+
+#[test]
+fn test_division_by_zero() -> Result<()> {
+    for source in [
+        "int main(void) { return 1 / 0; }",
+        "int main(void) { int x = 3; return x % (2 - 2); }",
+        "int main(void) { return 1u / 0u; }",
+        "enum { ZERO }; int main(void) { return 4 / ZERO; }",
+    ] {
+        let mut parser = Parser::build(source);
+        let mut verify = VariableResolution::from(parser.to_ast_program()?);
+
+        assert!(
+            matches!(
+                verify.get_updated_program(),
+                Err(Error::DivisionByZero { .. })
+            ),
+            "{source}"
+        );
+    }
+
+    // A divisor only known at runtime is the program's business,
+    // and so is a division that may never be evaluated.
+    for source in [
+        "int main(void) { int x = 0; return 0 / 1 + 1 / x; }",
+        "int main(void) { int x = 0; return x && 1 / 0; }",
+        "int main(void) { int x = 1; return x || 1 % 0; }",
+        "int main(void) { int x = 0; return x ? 1 / 0 : 2; }",
+        "int main(void) { return sizeof (1 / 0); }",
+    ] {
+        let mut parser = Parser::build(source);
+        VariableResolution::from(parser.to_ast_program()?).get_updated_program()?;
+    }
+
+    // The error names the variables of the source.
+    let mut parser =
+        Parser::build("enum { ZERO }; int main(void) { int x = 3; return 1 + x % (ZERO * 2); }");
+    let result = VariableResolution::from(parser.to_ast_program()?).get_updated_program();
+    let Err(error @ Error::DivisionByZero { .. }) = result else {
+        panic!("Expected a division by zero error, found {result:?}");
+    };
+    assert_eq!(
+        error.to_string(),
+        "The divisor of `x % (ZERO * 2)` is always zero"
+    );
+
+    // The left operand of `&&` is always evaluated.
+    let mut parser = Parser::build("int main(void) { int x = 0; return 1 / 0 && x; }");
+    let result = VariableResolution::from(parser.to_ast_program()?).get_updated_program();
+    assert!(matches!(result, Err(Error::DivisionByZero { .. })));
+    Ok(())
+}
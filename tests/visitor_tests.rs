@@ -0,0 +1,447 @@
+use nous::{
+    assembly::{Assembly, BinaryOperator, Function, Instruction, Operand, Program, Reg},
+    register_allocation::ALLOCATABLE_REGISTERS,
+    tac::{self, Val},
+    visitor::AssemblyPass,
+};
+
+fn allocated_stack(program: &Program) -> Option<i64> {
+    program.0[0]
+        .instructions
+        .iter()
+        .find_map(|instruction| match instruction {
+            Instruction::AllocateStack(i) => Some(*i),
+            _ => None,
+        })
+}
+
+#[test]
+fn test_allocate_stack_from_pseudo_registers() {
+    let file = String::from("int main(void) { int a = 1; int b = 2; return a + b; }");
+    let mut assembly = Assembly::from(file);
+    assembly.parse_program();
+
+    let mut visitor = AssemblyPass::build(assembly);
+    visitor
+        .replace_pseudo_registers()
+        .rewrite_mov()
+        .rewrite_binop()
+        .rewrite_cmp()
+        .allocate_stack();
+
+    let program = visitor.modify_program();
+
+    // `a`, `b` and the temporary holding `a + b`, rounded up
+    // to 16 bytes.
+    assert_eq!(allocated_stack(&program), Some(16));
+}
+
+#[test]
+fn test_allocate_stack_counts_new_slots() {
+    let program = Program(
+        vec![Function {
+            name: "main".into(),
+            instructions: vec![
+                Instruction::Mov {
+                    src: Operand::Imm(2),
+                    dst: Operand::Stack(4),
+                },
+                Instruction::Mov {
+                    src: Operand::Stack(4),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Ret,
+            ],
+        }],
+        vec![],
+    );
+
+    let visitor = AssemblyPass::from(program);
+
+    // Simulates a rewrite pass spilling a register into a new slot.
+    let mut spilled = visitor.modify_program();
+    spilled.0[0].instructions.insert(
+        1,
+        Instruction::Mov {
+            src: Operand::Register(Reg::R10),
+            dst: Operand::Stack(20),
+        },
+    );
+
+    let mut visitor = AssemblyPass::from(spilled);
+    visitor.allocate_stack();
+
+    assert_eq!(allocated_stack(&visitor.modify_program()), Some(32));
+}
+
+#[test]
+fn test_remove_redundant_mov() {
+    let program = Program(
+        vec![Function {
+            name: "main".into(),
+            instructions: vec![
+                Instruction::Mov {
+                    src: Operand::Imm(2),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Mov {
+                    src: Operand::Register(Reg::AX),
+                    dst: Operand::Stack(4),
+                },
+                Instruction::Mov {
+                    src: Operand::Stack(4),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Mov {
+                    src: Operand::Register(Reg::R10),
+                    dst: Operand::Register(Reg::R10),
+                },
+                Instruction::Ret,
+            ],
+        }],
+        vec![],
+    );
+
+    let mut visitor = AssemblyPass::from(program);
+    visitor.remove_redundant_mov();
+
+    let expected_instructions = vec![
+        Instruction::Mov {
+            src: Operand::Imm(2),
+            dst: Operand::Register(Reg::AX),
+        },
+        Instruction::Mov {
+            src: Operand::Register(Reg::AX),
+            dst: Operand::Stack(4),
+        },
+        Instruction::Ret,
+    ];
+
+    assert_eq!(
+        visitor.modify_program().0[0].instructions,
+        expected_instructions
+    );
+}
+
+#[test]
+fn test_rewrite_extensions() {
+    // A byte argument widened into a local, and a constant byte.
+    let program = Program(
+        vec![Function {
+            name: "main".into(),
+            instructions: vec![
+                Instruction::Movsx {
+                    src: Operand::Register(Reg::DI),
+                    dst: Operand::Stack(4),
+                },
+                Instruction::Movzx {
+                    src: Operand::Imm(200),
+                    dst: Operand::Register(Reg::AX),
+                },
+                Instruction::Ret,
+            ],
+        }],
+        vec![],
+    );
+
+    let program = AssemblyPass::from(program).rewrite_mov().modify_program();
+    let instructions: Vec<String> = program.0[0]
+        .instructions
+        .iter()
+        .map(Instruction::format)
+        .collect();
+
+    assert_eq!(
+        instructions[..4],
+        [
+            "movsbl\t%dil, %r11d",
+            "movl\t%r11d, -4(%rbp)",
+            "movl\t$200, %r10d",
+            "movzbl\t%r10b, %eax",
+        ]
+    );
+}
+
+#[test]
+fn test_rewrite_shifts() {
+    // Counts other than an immediate byte are moved into `CL`.
+    let program = Program(
+        vec![Function {
+            name: "main".into(),
+            instructions: vec![
+                Instruction::Binary(BinaryOperator::Sal, Operand::Imm(3), Operand::Stack(4)),
+                Instruction::Binary(BinaryOperator::Sar, Operand::Stack(8), Operand::Stack(4)),
+                Instruction::Binary(
+                    BinaryOperator::Shr,
+                    Operand::Imm(300),
+                    Operand::Register(Reg::AX),
+                ),
+                Instruction::Ret,
+            ],
+        }],
+        vec![],
+    );
+
+    let program = AssemblyPass::from(program).rewrite_binop().modify_program();
+    let instructions: Vec<String> = program.0[0]
+        .instructions
+        .iter()
+        .map(Instruction::format)
+        .collect();
+
+    assert_eq!(
+        instructions[..5],
+        [
+            "sall\t$3, -4(%rbp)",
+            "movl\t-8(%rbp), %ecx",
+            "sarl\t%cl, -4(%rbp)",
+            "movl\t$300, %ecx",
+            "shrl\t%cl, %eax",
+        ]
+    );
+}
+
+#[test]
+fn test_frame_size() {
+    let file = String::from("int main(void) { int a = 1; int b = 2; int c = 3; return c; }");
+    let mut assembly = Assembly::from(file);
+    assembly.parse_program();
+
+    let mut visitor = AssemblyPass::build(assembly);
+    assert_eq!(visitor.modify_program().0[0].frame_size(), 0);
+
+    visitor
+        .replace_pseudo_registers()
+        .rewrite_mov()
+        .rewrite_binop()
+        .rewrite_cmp()
+        .allocate_stack();
+
+    assert_eq!(visitor.modify_program().0[0].frame_size(), 16);
+}
+
+#[test]
+fn test_frame_size_per_function() {
+    // `f` needs a single slot, `main` one for each of its variables
+    // and of the temporaries of the sum.
+    let file = String::from(
+        "int f(void) { int a = 1; return a; } \
+         int main(void) { int a = 1; int b = 2; int c = 3; int d = 4; return a + b + c + d; }",
+    );
+    let mut assembly = Assembly::from(file);
+    assembly.parse_program();
+
+    let program = AssemblyPass::build(assembly)
+        .run_passes(|_, _| {})
+        .modify_program();
+    let frame_sizes: Vec<i64> = program.0.iter().map(|f| f.frame_size()).collect();
+
+    assert_eq!(frame_sizes, vec![16, 32]);
+    assert!(program.0[0].format().contains("subq\t$16, %rsp"));
+    assert!(program.0[1].format().contains("subq\t$32, %rsp"));
+}
+
+#[test]
+fn test_pseudo_registers_per_function() {
+    let function = |name: &str, locals: &[&str]| tac::Function {
+        identifier: name.into(),
+        params: vec![],
+        body: locals
+            .iter()
+            .map(|local| tac::Instruction::Copy {
+                src: Val::Constant(1),
+                dst: Val::Var((*local).into()),
+            })
+            .chain([tac::Instruction::Return(Some(Val::Var("x".into())))])
+            .collect(),
+        arrays: vec![],
+        pointers: Default::default(),
+    };
+    // `x` is the second slot of `f`, but the first one of `main`.
+    let mut assembly = Assembly::from(tac::Program(
+        vec![function("f", &["a", "x"]), function("main", &["x"])],
+        vec![],
+    ));
+    assembly.parse_program();
+
+    let mut visitor = AssemblyPass::build(assembly);
+    let program = visitor.replace_pseudo_registers().modify_program();
+    let destinations: Vec<Vec<Operand>> = program
+        .0
+        .iter()
+        .map(|function| {
+            function
+                .instructions
+                .iter()
+                .filter_map(|instruction| match instruction {
+                    Instruction::Mov {
+                        src: Operand::Imm(1),
+                        dst,
+                    } => Some(dst.clone()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect();
+
+    assert_eq!(
+        destinations,
+        vec![
+            vec![Operand::Stack(4), Operand::Stack(8)],
+            vec![Operand::Stack(4)]
+        ]
+    );
+}
+
+#[test]
+fn test_chained_passes() {
+    let source = String::from("int main(void) { int a = 3; return a * 2 < 7 ? a / 2 : 1; }");
+    let assembly = || {
+        let mut assembly = Assembly::from(source.clone());
+        assembly.parse_program();
+        assembly
+    };
+
+    // A single chain, in the order of `run_passes`.
+    let chained = AssemblyPass::build(assembly())
+        .replace_pseudo_registers()
+        .rewrite_mov()
+        .remove_redundant_mov()
+        .rewrite_binop()
+        .rewrite_cmp()
+        .allocate_stack()
+        .modify_program();
+    let run = AssemblyPass::build(assembly())
+        .run_passes(|_, _| {})
+        .modify_program();
+
+    assert_eq!(chained.format(), run.format());
+}
+
+#[test]
+fn test_replace_both_operands() {
+    let file = String::from("int main(void) { int a = 1; int b = 2; return a < b; }");
+    let mut assembly = Assembly::from(file);
+    let before = assembly.parse_program();
+
+    let mut visitor = AssemblyPass::build(assembly);
+    visitor.replace_pseudo_registers();
+    let after = visitor.modify_program();
+
+    // The comparison of `a` and `b` takes two pseudo registers.
+    let position = before.0[0]
+        .instructions
+        .iter()
+        .position(|instruction| {
+            matches!(
+                instruction,
+                Instruction::Cmp(Operand::Pseudo(_), Operand::Pseudo(_))
+            )
+        })
+        .expect("Should compare two pseudo registers");
+
+    match &after.0[0].instructions[position] {
+        Instruction::Cmp(Operand::Stack(b), Operand::Stack(a)) => assert_ne!(a, b),
+        instruction => panic!("Expected a comparison of two stack slots, found {instruction:?}"),
+    }
+    assert!(after.0[0]
+        .instructions
+        .iter()
+        .flat_map(Instruction::operands)
+        .all(|operand| !matches!(operand, Operand::Pseudo(_))));
+}
+
+#[test]
+fn test_register_allocation() {
+    let assembly = || {
+        let mut assembly = Assembly::from(String::from(
+            "int main(void) { int a = 1; int b = 2; return a + b; }",
+        ));
+        assembly.parse_program();
+        assembly
+    };
+    let registers = |program: &Program| {
+        program.0[0]
+            .instructions
+            .iter()
+            .flat_map(Instruction::operands)
+            .filter(|operand| {
+                matches!(operand, Operand::Register(reg) if ALLOCATABLE_REGISTERS.contains(reg))
+            })
+            .count()
+    };
+
+    let mut visitor = AssemblyPass::build(assembly());
+    visitor.allocate_registers();
+    let program = visitor.modify_program();
+
+    assert!(registers(&program) > 0);
+    // Nothing is left for the stack.
+    assert!(program.0[0]
+        .instructions
+        .iter()
+        .flat_map(Instruction::operands)
+        .all(|operand| !matches!(operand, Operand::Pseudo(_))));
+
+    // Only enabled on request in `run_passes`.
+    let mut visitor = AssemblyPass::build(assembly());
+    visitor.run_passes(|_, _| {});
+    assert_eq!(registers(&visitor.modify_program()), 0);
+
+    let mut visitor = AssemblyPass::build(assembly()).register_allocation(true);
+    visitor.run_passes(|_, _| {});
+    assert!(registers(&visitor.modify_program()) > 0);
+}
+
+#[test]
+fn test_register_allocation_across_calls() {
+    let allocate = |source: &str| {
+        let mut assembly = Assembly::from(String::from(source));
+        assembly.parse_program();
+        let mut visitor = AssemblyPass::build(assembly);
+        visitor.allocate_registers().replace_pseudo_registers();
+        visitor.modify_program()
+    };
+    let stores = |instructions: &[Instruction]| {
+        instructions
+            .iter()
+            .filter(|instruction| {
+                matches!(
+                    instruction,
+                    Instruction::Mov {
+                        src: Operand::Imm(1),
+                        dst: Operand::Stack(_)
+                    }
+                )
+            })
+            .count()
+    };
+
+    // `a` stays in a register, saved around the call on an
+    // aligned stack.
+    let program = allocate("int main(void) { int a = 1; putchar(a); return a; }");
+    let instructions = &program.0[0].instructions;
+    let call = instructions
+        .iter()
+        .position(|instruction| matches!(instruction, Instruction::Call { .. }))
+        .expect("Should call putchar");
+    let Instruction::Push(Operand::Register(saved)) = &instructions[call - 1] else {
+        panic!("Expected a push before the call: {instructions:?}");
+    };
+    assert_eq!(instructions[call - 2], Instruction::AllocateStack(8));
+    assert_eq!(
+        instructions[call + 1],
+        Instruction::Pop(Operand::Register(saved.clone()))
+    );
+    assert_eq!(instructions[call + 2], Instruction::DeallocateStack(8));
+    assert_eq!(stores(instructions), 0);
+
+    // Saved registers would sit between the stack arguments and
+    // the callee, so `a` is kept on the stack instead.
+    let program = allocate("int main(void) { int a = 1; f(1, 2, 3, 4, 5, 6, 7); return a; }");
+    let instructions = &program.0[0].instructions;
+    assert!(!instructions
+        .iter()
+        .any(|instruction| matches!(instruction, Instruction::Pop(_))));
+    assert_eq!(stores(instructions), 1);
+}